@@ -33,15 +33,21 @@ fn convert(toml: Toml) -> Json {
     match toml {
         Toml::String(s) => Json::String(s),
         Toml::Integer(i) => Json::Number(i.into()),
+        Toml::UInteger(i) => Json::Number(i.into()),
         Toml::Float(f) => {
             let n = serde_json::Number::from_f64(f).expect("float infinite and nan not allowed");
             Json::Number(n)
         }
+        Toml::Number(n) => match n.as_i64() {
+            Some(i) => Json::Number(i.into()),
+            None => convert(Toml::Float(n.as_f64().expect("integer too large"))),
+        },
         Toml::Boolean(b) => Json::Bool(b),
         Toml::Array(arr) => Json::Array(arr.into_iter().map(convert).collect()),
         Toml::Table(table) => {
             Json::Object(table.into_iter().map(|(k, v)| (k, convert(v))).collect())
         }
         Toml::Datetime(dt) => Json::String(dt.to_string()),
+        _ => unreachable!("not parsed by default"),
     }
 }
@@ -0,0 +1,453 @@
+//! Loading configuration layered from several sources.
+//!
+//! A [`Loader`] reads an ordered list of sources, such as built-in defaults,
+//! a system-wide file and a user's own file, and merges them in order with
+//! `Value::merge`, so that later sources override earlier ones. The
+//! resulting [`Config`] remembers which source, and where in it, each value
+//! came from. Errors, including those deserializing the merged configuration
+//! into a type, point at the source which defined the offending value rather
+//! than at the merged result.
+//!
+//! ```rust
+//! use serde_derive::Deserialize;
+//! use toml::config::Loader;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let defaults = toml::toml! {
+//!     [server]
+//!     host = "localhost"
+//!     port = 80
+//! };
+//! let config = Loader::new()
+//!     .value("defaults", defaults)
+//!     .str("local.toml", "[server]\nport = 70000\n")
+//!     .load()
+//!     .unwrap();
+//!
+//! let origin = config.origin(&["server", "host"]).unwrap();
+//! assert_eq!(origin.source(), "defaults");
+//! let origin = config.origin(&["server", "port"]).unwrap();
+//! assert_eq!(origin.to_string(), "local.toml at line 2 column 8");
+//!
+//! let err = config.try_into_at::<Server>(&["server"]).unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "invalid value: integer `70000`, expected u16 for key `server.port` \
+//!      in local.toml at line 2 column 8"
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use serde::de;
+
+use crate::spanned::SpannedValue;
+use crate::value::{MergeError, MergeOptions, Value};
+
+/// Loads a configuration from a list of sources, merging later sources into
+/// earlier ones.
+///
+/// Sources are added with `file`, `optional_file`, `str` and `value`, and
+/// are only read when `load` is called, so a `Loader` can be kept around to
+/// reload the configuration later.
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    sources: Vec<Source>,
+    options: MergeOptions,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    File { path: PathBuf, required: bool },
+    Str { name: String, text: String },
+    Value { name: String, value: Value },
+}
+
+impl Loader {
+    /// Creates a loader with no sources, which merges values with the
+    /// default `MergeOptions`.
+    pub fn new() -> Loader {
+        Loader::default()
+    }
+
+    /// Adds a TOML file. Loading fails if the file can't be read.
+    pub fn file<P: Into<PathBuf>>(mut self, path: P) -> Loader {
+        self.sources.push(Source::File {
+            path: path.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Adds a TOML file which is skipped if it doesn't exist.
+    pub fn optional_file<P: Into<PathBuf>>(mut self, path: P) -> Loader {
+        self.sources.push(Source::File {
+            path: path.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Adds a TOML document held in memory, referred to as `name` in origins
+    /// and errors.
+    pub fn str(mut self, name: &str, text: &str) -> Loader {
+        self.sources.push(Source::Str {
+            name: name.to_string(),
+            text: text.to_string(),
+        });
+        self
+    }
+
+    /// Adds a value, such as a table of defaults, referred to as `name` in
+    /// origins and errors.
+    ///
+    /// Values have no source text, so their origins have no position.
+    pub fn value(mut self, name: &str, value: Value) -> Loader {
+        self.sources.push(Source::Value {
+            name: name.to_string(),
+            value,
+        });
+        self
+    }
+
+    /// Sets the options used to merge each source into the ones before it.
+    pub fn merge_options(mut self, options: MergeOptions) -> Loader {
+        self.options = options;
+        self
+    }
+
+    /// Reads and merges all of the sources, in the order they were added.
+    ///
+    /// Stops at the first source which can't be read, parsed or merged.
+    pub fn load(&self) -> Result<Config, Error> {
+        let mut config = Config {
+            value: Value::Table(Default::default()),
+            origins: BTreeMap::new(),
+        };
+        for source in &self.sources {
+            let mut origins = BTreeMap::new();
+            let (name, value) = match *source {
+                Source::File { ref path, required } => {
+                    let name = path.display().to_string();
+                    let text = match fs::read_to_string(path) {
+                        Ok(text) => text,
+                        Err(ref e) if !required && e.kind() == io::ErrorKind::NotFound => continue,
+                        Err(e) => {
+                            return Err(Error::new(ErrorKind::Io(e), Some(Origin::new(name))))
+                        }
+                    };
+                    let value = parse(&name, &text, &mut origins)?;
+                    (name, value)
+                }
+                Source::Str { ref name, ref text } => {
+                    (name.clone(), parse(name, text, &mut origins)?)
+                }
+                Source::Value {
+                    ref name,
+                    ref value,
+                } => {
+                    value_origins(value, &mut Vec::new(), &mut |path| {
+                        origins.insert(path.to_vec(), Origin::new(name.clone()));
+                    });
+                    (name.clone(), value.clone())
+                }
+            };
+
+            if let Err(e) = config.value.merge(value, &self.options) {
+                let origin = lookup(&origins, e.path()).unwrap_or_else(|| Origin::new(name));
+                return Err(Error::new(ErrorKind::Merge(e), Some(origin)));
+            }
+            config.origins.append(&mut origins);
+        }
+
+        // Drop the origins of values which were replaced along with a table
+        // or array containing them.
+        let value = &config.value;
+        config.origins.retain(|path, _| {
+            path.iter()
+                .try_fold(value, |value, key| value.get(key.as_str()))
+                .is_some()
+        });
+        Ok(config)
+    }
+}
+
+/// Parses the source `name`, adding the origins of its values to `origins`.
+fn parse(
+    name: &str,
+    text: &str,
+    origins: &mut BTreeMap<Vec<String>, Origin>,
+) -> Result<Value, Error> {
+    let spanned = text.parse::<SpannedValue>().map_err(|e| {
+        let origin = Origin::at(name, text, e.span());
+        Error::new(ErrorKind::Parse(e), Some(origin))
+    })?;
+    spanned_origins(&spanned, &mut Vec::new(), &mut |path, span| {
+        origins.insert(path.to_vec(), Origin::at(name, text, Some(span)));
+    });
+    Ok(Value::from(spanned))
+}
+
+/// Calls `f` with the path and span of every value within `value`, except
+/// for the elements of arrays.
+fn spanned_origins<F>(value: &SpannedValue, path: &mut Vec<String>, f: &mut F)
+where
+    F: FnMut(&[String], Range<usize>),
+{
+    if !path.is_empty() {
+        let (start, end) = value.span();
+        f(path, start..end);
+    }
+    if let Some(table) = value.as_table() {
+        for (key, value) in table.iter() {
+            path.push(key.get_ref().clone());
+            spanned_origins(value, path, f);
+            path.pop();
+        }
+    }
+}
+
+/// Calls `f` with the path of every value within `value`, except for the
+/// elements of arrays.
+fn value_origins<F>(value: &Value, path: &mut Vec<String>, f: &mut F)
+where
+    F: FnMut(&[String]),
+{
+    if !path.is_empty() {
+        f(path);
+    }
+    if let Some(table) = value.as_table() {
+        for (key, value) in table {
+            path.push(key.clone());
+            value_origins(value, path, f);
+            path.pop();
+        }
+    }
+}
+
+/// Returns the origin of the value at `path`, or of the innermost table
+/// containing it which has one.
+fn lookup<S: AsRef<str>>(origins: &BTreeMap<Vec<String>, Origin>, path: &[S]) -> Option<Origin> {
+    let mut path = path
+        .iter()
+        .map(|s| s.as_ref().to_string())
+        .collect::<Vec<_>>();
+    loop {
+        if let Some(origin) = origins.get(&path) {
+            return Some(origin.clone());
+        }
+        path.pop()?;
+    }
+}
+
+/// A configuration loaded by a [`Loader`], along with where each of its
+/// values came from.
+#[derive(Debug, Clone)]
+pub struct Config {
+    value: Value,
+    origins: BTreeMap<Vec<String>, Origin>,
+}
+
+impl Config {
+    /// Returns the merged configuration.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consumes the configuration, returning the merged value.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Returns where the value at `path` came from, or `None` if there is no
+    /// value at `path`.
+    ///
+    /// Each element of `path` is a key into a table, starting at the root of
+    /// the configuration. The origin of a value is the last source which
+    /// defined it. That includes tables, which can be defined in several
+    /// sources, and arrays, which can be combined from several sources
+    /// depending on the `MergeOptions`. The elements of arrays have no
+    /// origins of their own.
+    pub fn origin(&self, path: &[&str]) -> Option<&Origin> {
+        let path = path.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        self.origins.get(&path)
+    }
+
+    /// Interprets the configuration as an instance of type `T`.
+    ///
+    /// If this fails, the error points at where the offending value came
+    /// from, or at the innermost table containing it for errors such as a
+    /// missing field.
+    pub fn try_into<'de, T>(&self) -> Result<T, Error>
+    where
+        T: de::Deserialize<'de>,
+    {
+        self.value.clone().try_into().map_err(|e| self.error(e))
+    }
+
+    /// Interprets the value at `path` within the configuration as an
+    /// instance of type `T`, as with `Value::try_into_at`.
+    ///
+    /// Returns `Ok(None)` if there is no value at `path`. Errors point at
+    /// where the offending value came from, as with `try_into`.
+    pub fn try_into_at<'de, T>(&self, path: &[&str]) -> Result<Option<T>, Error>
+    where
+        T: de::Deserialize<'de>,
+    {
+        self.value
+            .clone()
+            .try_into_at(path)
+            .map_err(|e| self.error(e))
+    }
+
+    fn error(&self, e: crate::de::Error) -> Error {
+        let origin = lookup(&self.origins, e.key_path());
+        Error::new(ErrorKind::Deserialize(e), origin)
+    }
+}
+
+/// Where a value in a [`Config`] came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    source: String,
+    span: Option<Range<usize>>,
+    line_col: Option<(usize, usize)>,
+}
+
+impl Origin {
+    fn new(source: String) -> Origin {
+        Origin {
+            source,
+            span: None,
+            line_col: None,
+        }
+    }
+
+    fn at(source: &str, text: &str, span: Option<Range<usize>>) -> Origin {
+        let line_col = span
+            .as_ref()
+            .map(|span| crate::de::Deserializer::new(text).to_linecol(span.start));
+        Origin {
+            source: source.to_string(),
+            span,
+            line_col,
+        }
+    }
+
+    /// Returns the name of the source, which is the path of a file or the
+    /// name a string or value was added with.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the byte range within the source which defines the value, if
+    /// the source is TOML text.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    /// Returns the (line, column) pair of the start of the span, if there is
+    /// one.
+    ///
+    /// All indexes are 0-based.
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        self.line_col
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)?;
+        if let Some((line, col)) = self.line_col {
+            write!(f, " at line {} column {}", line + 1, col + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors loading a configuration or interpreting it as a type.
+#[derive(Debug)]
+pub struct Error {
+    inner: Box<ErrorInner>,
+}
+
+#[derive(Debug)]
+struct ErrorInner {
+    kind: ErrorKind,
+    origin: Option<Origin>,
+}
+
+/// The kinds of error returned by [`Loader::load`] and [`Config::try_into`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A file could not be read.
+    Io(io::Error),
+    /// A source is not a valid TOML document.
+    Parse(crate::de::Error),
+    /// A source could not be merged into the sources before it.
+    Merge(MergeError),
+    /// The configuration could not be interpreted as the requested type.
+    Deserialize(crate::de::Error),
+}
+
+impl Error {
+    fn new(kind: ErrorKind, origin: Option<Origin>) -> Error {
+        Error {
+            inner: Box::new(ErrorInner { kind, origin }),
+        }
+    }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner.kind
+    }
+
+    /// Returns the source, and the place within it, this error refers to.
+    ///
+    /// This is `None` for deserialization errors which aren't about any
+    /// value in particular.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.inner.origin.as_ref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.inner.kind, &self.inner.origin) {
+            (ErrorKind::Io(e), Some(origin)) => {
+                return write!(f, "failed to read {}: {}", origin.source, e)
+            }
+            // Parse errors already include the position within the source.
+            (ErrorKind::Parse(e), Some(origin)) => return write!(f, "{} in {}", e, origin.source),
+            (ErrorKind::Io(e), None) => e.fmt(f)?,
+            (ErrorKind::Parse(e), None) | (ErrorKind::Deserialize(e), _) => e.fmt(f)?,
+            (ErrorKind::Merge(e), _) => e.fmt(f)?,
+        }
+        if let Some(ref origin) = self.inner.origin {
+            write!(f, " in {}", origin)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.inner.kind {
+            ErrorKind::Io(ref e) => Some(e),
+            ErrorKind::Parse(ref e) | ErrorKind::Deserialize(ref e) => Some(e),
+            ErrorKind::Merge(ref e) => Some(e),
+        }
+    }
+}
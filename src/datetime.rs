@@ -4,6 +4,8 @@ use std::str::{self, FromStr};
 
 use serde::{de, ser};
 
+use crate::de::TomlVersion;
+
 /// A parsed TOML datetime value
 ///
 /// This structure is intended to represent the datetime primitive type that can
@@ -94,7 +96,9 @@ pub struct Datetime {
 /// Error returned from parsing a `Datetime` in the `FromStr` implementation.
 #[derive(Debug, Clone)]
 pub struct DatetimeParseError {
-    _private: (),
+    // Names the component which was out of range, if it wasn't the format of
+    // the datetime which was wrong.
+    reason: Option<&'static str>,
 }
 
 // Currently serde itself doesn't have a datetime type, so we map our `Datetime`
@@ -233,194 +237,285 @@ impl fmt::Display for Offset {
     }
 }
 
+impl Datetime {
+    /// Parses a datetime like `FromStr`, but only checks that each component
+    /// is within its largest possible range.
+    ///
+    /// This accepts dates such as February 30th, hour 24 and offsets of more
+    /// than a day, as versions of this crate before strict validation did.
+    pub fn from_str_lenient(date: &str) -> Result<Datetime, DatetimeParseError> {
+        parse(date, false, TomlVersion::V1_0)
+    }
+}
+
 impl FromStr for Datetime {
     type Err = DatetimeParseError;
 
+    /// Parses an RFC 3339 datetime, or a part of one, as allowed by TOML.
+    ///
+    /// Impossible dates and times are rejected, such as February 29th in a
+    /// year which isn't a leap year, or a leap second at any time other than
+    /// the last minute of a UTC day. See `Datetime::from_str_lenient` for a
+    /// more permissive alternative.
     fn from_str(date: &str) -> Result<Datetime, DatetimeParseError> {
-        // Accepted formats:
-        //
-        // 0000-00-00T00:00:00.00Z
-        // 0000-00-00T00:00:00.00
-        // 0000-00-00
-        // 00:00:00.00
-        if date.len() < 3 {
-            return Err(DatetimeParseError { _private: () });
+        parse(date, true, TomlVersion::V1_0)
+    }
+}
+
+/// Parses a datetime, checking that it is a possible one if `strict`, in
+/// the format of the TOML version `spec`.
+pub(crate) fn parse(
+    date: &str,
+    strict: bool,
+    spec: TomlVersion,
+) -> Result<Datetime, DatetimeParseError> {
+    // Accepted formats:
+    //
+    // 0000-00-00T00:00:00.00Z
+    // 0000-00-00T00:00:00.00
+    // 0000-00-00
+    // 00:00:00.00
+    //
+    // Seconds may be omitted from times as of TOML 1.1.
+    if date.len() < 3 {
+        return Err(DatetimeParseError { reason: None });
+    }
+    let mut offset_allowed = true;
+    let mut utc_shift = 0;
+    let mut chars = date.chars();
+
+    // First up, parse the full date if we can
+    let full_date = if chars.clone().nth(2) == Some(':') {
+        offset_allowed = false;
+        None
+    } else {
+        let y1 = u16::from(digit(&mut chars)?);
+        let y2 = u16::from(digit(&mut chars)?);
+        let y3 = u16::from(digit(&mut chars)?);
+        let y4 = u16::from(digit(&mut chars)?);
+
+        match chars.next() {
+            Some('-') => {}
+            _ => return Err(DatetimeParseError { reason: None }),
         }
-        let mut offset_allowed = true;
-        let mut chars = date.chars();
 
-        // First up, parse the full date if we can
-        let full_date = if chars.clone().nth(2) == Some(':') {
-            offset_allowed = false;
-            None
-        } else {
-            let y1 = u16::from(digit(&mut chars)?);
-            let y2 = u16::from(digit(&mut chars)?);
-            let y3 = u16::from(digit(&mut chars)?);
-            let y4 = u16::from(digit(&mut chars)?);
+        let m1 = digit(&mut chars)?;
+        let m2 = digit(&mut chars)?;
 
-            match chars.next() {
-                Some('-') => {}
-                _ => return Err(DatetimeParseError { _private: () }),
-            }
+        match chars.next() {
+            Some('-') => {}
+            _ => return Err(DatetimeParseError { reason: None }),
+        }
 
-            let m1 = digit(&mut chars)?;
-            let m2 = digit(&mut chars)?;
+        let d1 = digit(&mut chars)?;
+        let d2 = digit(&mut chars)?;
 
-            match chars.next() {
-                Some('-') => {}
-                _ => return Err(DatetimeParseError { _private: () }),
-            }
+        let date = Date {
+            year: y1 * 1000 + y2 * 100 + y3 * 10 + y4,
+            month: m1 * 10 + m2,
+            day: d1 * 10 + d2,
+        };
 
-            let d1 = digit(&mut chars)?;
-            let d2 = digit(&mut chars)?;
+        if date.month < 1 || date.month > 12 {
+            return Err(DatetimeParseError::out_of_range(
+                "month must be between 01 and 12",
+            ));
+        }
+        let max_day = if strict {
+            days_in_month(date.year, date.month)
+        } else {
+            31
+        };
+        if date.day < 1 || date.day > max_day {
+            return Err(DatetimeParseError::out_of_range(
+                "day is out of range for the month",
+            ));
+        }
 
-            let date = Date {
-                year: y1 * 1000 + y2 * 100 + y3 * 10 + y4,
-                month: m1 * 10 + m2,
-                day: d1 * 10 + d2,
-            };
+        Some(date)
+    };
+
+    // Next parse the "partial-time" if available
+    let next = chars.clone().next();
+    let partial_time =
+        if full_date.is_some() && (next == Some('T') || next == Some('t') || next == Some(' ')) {
+            chars.next();
+            true
+        } else {
+            full_date.is_none()
+        };
 
-            if date.month < 1 || date.month > 12 {
-                return Err(DatetimeParseError { _private: () });
+    let time = if partial_time {
+        let h1 = digit(&mut chars)?;
+        let h2 = digit(&mut chars)?;
+        match chars.next() {
+            Some(':') => {}
+            _ => return Err(DatetimeParseError { reason: None }),
+        }
+        let m1 = digit(&mut chars)?;
+        let m2 = digit(&mut chars)?;
+        let seconds = chars.clone().next() == Some(':');
+        if !seconds && spec < TomlVersion::V1_1 {
+            return Err(DatetimeParseError { reason: None });
+        }
+        let (s1, s2) = if seconds {
+            chars.next();
+            (digit(&mut chars)?, digit(&mut chars)?)
+        } else {
+            (0, 0)
+        };
+
+        let mut nanosecond = 0;
+        if seconds && chars.clone().next() == Some('.') {
+            chars.next();
+            let whole = chars.as_str();
+
+            let mut end = whole.len();
+            for (i, byte) in whole.bytes().enumerate() {
+                match byte {
+                    b'0'..=b'9' => {
+                        if i < 9 {
+                            let p = 10_u32.pow(8 - i as u32);
+                            nanosecond += p * u32::from(byte - b'0');
+                        }
+                    }
+                    _ => {
+                        end = i;
+                        break;
+                    }
+                }
             }
-            if date.day < 1 || date.day > 31 {
-                return Err(DatetimeParseError { _private: () });
+            if end == 0 {
+                return Err(DatetimeParseError { reason: None });
             }
+            chars = whole[end..].chars();
+        }
 
-            Some(date)
+        let time = Time {
+            hour: h1 * 10 + h2,
+            minute: m1 * 10 + m2,
+            second: s1 * 10 + s2,
+            nanosecond,
         };
 
-        // Next parse the "partial-time" if available
+        if time.hour > if strict { 23 } else { 24 } {
+            return Err(DatetimeParseError::out_of_range(
+                "hour must be between 00 and 23",
+            ));
+        }
+        if time.minute > 59 {
+            return Err(DatetimeParseError::out_of_range(
+                "minute must be between 00 and 59",
+            ));
+        }
+        if time.second > 60 {
+            return Err(DatetimeParseError::out_of_range(
+                "second must be between 00 and 60",
+            ));
+        }
+        if time.nanosecond > 999_999_999 {
+            return Err(DatetimeParseError { reason: None });
+        }
+
+        Some(time)
+    } else {
+        offset_allowed = false;
+        None
+    };
+
+    // And finally, parse the offset
+    let offset = if offset_allowed {
         let next = chars.clone().next();
-        let partial_time = if full_date.is_some()
-            && (next == Some('T') || next == Some('t') || next == Some(' '))
-        {
+        if next == Some('Z') || next == Some('z') {
             chars.next();
-            true
+            Some(Offset::Z)
+        } else if next.is_none() {
+            None
         } else {
-            full_date.is_none()
-        };
-
-        let time = if partial_time {
-            let h1 = digit(&mut chars)?;
-            let h2 = digit(&mut chars)?;
+            let sign = match next {
+                Some('+') => 1,
+                Some('-') => -1,
+                _ => return Err(DatetimeParseError { reason: None }),
+            };
+            chars.next();
+            let h1 = digit(&mut chars)? as i8;
+            let h2 = digit(&mut chars)? as i8;
             match chars.next() {
                 Some(':') => {}
-                _ => return Err(DatetimeParseError { _private: () }),
+                _ => return Err(DatetimeParseError { reason: None }),
             }
             let m1 = digit(&mut chars)?;
             let m2 = digit(&mut chars)?;
-            match chars.next() {
-                Some(':') => {}
-                _ => return Err(DatetimeParseError { _private: () }),
-            }
-            let s1 = digit(&mut chars)?;
-            let s2 = digit(&mut chars)?;
-
-            let mut nanosecond = 0;
-            if chars.clone().next() == Some('.') {
-                chars.next();
-                let whole = chars.as_str();
-
-                let mut end = whole.len();
-                for (i, byte) in whole.bytes().enumerate() {
-                    match byte {
-                        b'0'..=b'9' => {
-                            if i < 9 {
-                                let p = 10_u32.pow(8 - i as u32);
-                                nanosecond += p * u32::from(byte - b'0');
-                            }
-                        }
-                        _ => {
-                            end = i;
-                            break;
-                        }
-                    }
-                }
-                if end == 0 {
-                    return Err(DatetimeParseError { _private: () });
-                }
-                chars = whole[end..].chars();
-            }
 
-            let time = Time {
-                hour: h1 * 10 + h2,
-                minute: m1 * 10 + m2,
-                second: s1 * 10 + s2,
-                nanosecond,
-            };
-
-            if time.hour > 24 {
-                return Err(DatetimeParseError { _private: () });
-            }
-            if time.minute > 59 {
-                return Err(DatetimeParseError { _private: () });
+            let (hours, minutes) = (h1 * 10 + h2, m1 * 10 + m2);
+            if strict && hours > 23 {
+                return Err(DatetimeParseError::out_of_range(
+                    "offset hour must be between 00 and 23",
+                ));
             }
-            if time.second > 59 {
-                return Err(DatetimeParseError { _private: () });
+            if strict && minutes > 59 {
+                return Err(DatetimeParseError::out_of_range(
+                    "offset minute must be between 00 and 59",
+                ));
             }
-            if time.nanosecond > 999_999_999 {
-                return Err(DatetimeParseError { _private: () });
-            }
-
-            Some(time)
-        } else {
-            offset_allowed = false;
-            None
-        };
-
-        // And finally, parse the offset
-        let offset = if offset_allowed {
-            let next = chars.clone().next();
-            if next == Some('Z') || next == Some('z') {
-                chars.next();
-                Some(Offset::Z)
-            } else if next.is_none() {
-                None
-            } else {
-                let sign = match next {
-                    Some('+') => 1,
-                    Some('-') => -1,
-                    _ => return Err(DatetimeParseError { _private: () }),
-                };
-                chars.next();
-                let h1 = digit(&mut chars)? as i8;
-                let h2 = digit(&mut chars)? as i8;
-                match chars.next() {
-                    Some(':') => {}
-                    _ => return Err(DatetimeParseError { _private: () }),
-                }
-                let m1 = digit(&mut chars)?;
-                let m2 = digit(&mut chars)?;
+            utc_shift = -i32::from(sign) * (i32::from(hours) * 60 + i32::from(minutes));
+            Some(Offset::Custom {
+                hours: sign * hours,
+                minutes,
+            })
+        }
+    } else {
+        None
+    };
+
+    // Return an error if we didn't hit eof, otherwise return our parsed
+    // date
+    if chars.next().is_some() {
+        return Err(DatetimeParseError { reason: None });
+    }
 
-                Some(Offset::Custom {
-                    hours: sign * (h1 * 10 + h2),
-                    minutes: m1 * 10 + m2,
-                })
-            }
-        } else {
-            None
+    // Leap seconds are inserted at the end of a UTC day. Without an
+    // offset all that can be checked is that it's the end of a minute.
+    if let Some(Time {
+        hour,
+        minute,
+        second: 60,
+        ..
+    }) = time
+    {
+        let minutes = i32::from(hour) * 60 + i32::from(minute);
+        let leap = match offset {
+            Some(_) => (minutes + utc_shift).rem_euclid(24 * 60) == 24 * 60 - 1,
+            None => minute == 59,
         };
-
-        // Return an error if we didn't hit eof, otherwise return our parsed
-        // date
-        if chars.next().is_some() {
-            return Err(DatetimeParseError { _private: () });
+        if strict && !leap {
+            return Err(DatetimeParseError::out_of_range(
+                "second can only be 60 for a leap second",
+            ));
         }
+    }
+
+    Ok(Datetime {
+        date: full_date,
+        time,
+        offset,
+    })
+}
 
-        Ok(Datetime {
-            date: full_date,
-            time,
-            offset,
-        })
+fn days_in_month(year: u16, month: u8) -> u8 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
     }
 }
 
 fn digit(chars: &mut str::Chars<'_>) -> Result<u8, DatetimeParseError> {
     match chars.next() {
         Some(c) if '0' <= c && c <= '9' => Ok(c as u8 - b'0'),
-        _ => Err(DatetimeParseError { _private: () }),
+        _ => Err(DatetimeParseError { reason: None }),
     }
 }
 
@@ -524,7 +619,10 @@ impl<'de> de::Deserialize<'de> for DatetimeFromString {
             where
                 E: de::Error,
             {
-                match s.parse() {
+                // Whether a datetime is possible, and whether its seconds
+                // may be left out, is checked when parsing TOML according to
+                // the deserializer's options.
+                match parse(s, false, TomlVersion::V1_1) {
                     Ok(date) => Ok(DatetimeFromString { value: date }),
                     Err(e) => Err(de::Error::custom(e)),
                 }
@@ -535,9 +633,27 @@ impl<'de> de::Deserialize<'de> for DatetimeFromString {
     }
 }
 
+impl DatetimeParseError {
+    fn out_of_range(reason: &'static str) -> DatetimeParseError {
+        DatetimeParseError {
+            reason: Some(reason),
+        }
+    }
+
+    /// Describes the component of the datetime which was out of range, if
+    /// any, such as "day is out of range for the month".
+    pub(crate) fn reason(&self) -> Option<&'static str> {
+        self.reason
+    }
+}
+
 impl fmt::Display for DatetimeParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "failed to parse datetime".fmt(f)
+        "failed to parse datetime".fmt(f)?;
+        if let Some(reason) = self.reason {
+            write!(f, ": {}", reason)?;
+        }
+        Ok(())
     }
 }
 
@@ -5,12 +5,18 @@
 //! provided at the top of the crate.
 
 use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::error;
 use std::f64;
 use std::fmt;
+use std::io;
 use std::iter;
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
 use std::str;
 use std::vec;
 
@@ -19,11 +25,13 @@ use serde::de::value::BorrowedStrDeserializer;
 use serde::de::IntoDeserializer;
 
 use crate::datetime;
-use crate::spanned;
+use crate::number;
+use crate::spanned::{self, Spanned, SpannedTable, SpannedValue};
 use crate::tokens::{Error as TokenError, Span, Token, Tokenizer};
+use crate::value;
 
 /// Type Alias for a TOML Table pair
-type TablePair<'a> = ((Span, Cow<'a, str>), Value<'a>);
+pub(crate) type TablePair<'a> = ((Span, Cow<'a, str>), Value<'a>);
 
 /// Deserializes a byte slice into a type.
 ///
@@ -39,6 +47,21 @@ where
     }
 }
 
+/// Deserializes a type from an IO stream of TOML.
+///
+/// TOML cannot be parsed incrementally, so the whole stream is read into
+/// memory before deserializing `T` from it. Failing to read from `reader` is
+/// reported as an I/O error, and the data read must be valid UTF-8.
+pub fn from_reader<R, T>(mut reader: R) -> Result<T, Error>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(Error::io)?;
+    from_slice(&bytes)
+}
+
 /// Deserializes a string into a type.
 ///
 /// This function will attempt to interpret `s` as a TOML document and
@@ -82,6 +105,516 @@ where
     Ok(ret)
 }
 
+/// Deserializes a type from a string of TOML, parsed according to the
+/// `options` provided.
+///
+/// # Examples
+///
+/// ```
+/// use toml::de::{Options, TomlVersion};
+///
+/// let input = "point = { x = 1, y = 2, }";
+/// assert!(toml::from_str::<toml::Value>(input).is_err());
+///
+/// let options = Options::new().spec(TomlVersion::V1_1);
+/// let value = toml::de::from_str_with_options::<toml::Value>(input, &options).unwrap();
+/// assert_eq!(value["point"]["y"].as_integer(), Some(2));
+/// ```
+pub fn from_str_with_options<'de, T>(s: &'de str, options: &Options) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let mut d = Deserializer::with_options(s, options);
+    let ret = T::deserialize(&mut d)?;
+    d.end()?;
+    Ok(ret)
+}
+
+/// Deserializes only the value at `path` in a TOML document into a type.
+///
+/// Each element of `path` is a key, so `["package", "metadata", "tool"]`
+/// deserializes the `[package.metadata.tool]` table of a manifest. Every
+/// other part of the document is skipped over without being deserialized,
+/// but it is still checked for errors, and errors within the value carry the
+/// full key path and position as they would with `from_str`.
+///
+/// Returns `Ok(None)` if there is no value at `path`. An empty `path`
+/// deserializes the whole document.
+///
+/// # Examples
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Tool {
+///     level: u32,
+/// }
+///
+/// let manifest = r#"
+///     [package]
+///     name = "foo"
+///
+///     [package.metadata.tool]
+///     level = 3
+/// "#;
+///
+/// let path = ["package", "metadata", "tool"];
+/// let tool = toml::de::from_str_at::<Tool>(manifest, &path).unwrap();
+/// assert_eq!(tool.unwrap().level, 3);
+///
+/// let path = ["package", "metadata", "other"];
+/// assert!(toml::de::from_str_at::<Tool>(manifest, &path).unwrap().is_none());
+/// ```
+pub fn from_str_at<'de, T>(s: &'de str, path: &[&str]) -> Result<Option<T>, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let mut d = Deserializer::new(s);
+    let ret = de::DeserializeSeed::deserialize(PathSeed::new(path), &mut d)?;
+    d.end()?;
+    Ok(ret)
+}
+
+/// Deserializes the value at the end of a path of keys, ignoring the rest.
+struct PathSeed<'p, T> {
+    path: &'p [&'p str],
+    marker: PhantomData<T>,
+}
+
+impl<'p, T> PathSeed<'p, T> {
+    fn new(path: &'p [&'p str]) -> PathSeed<'p, T> {
+        PathSeed {
+            path,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, 'p, T> de::DeserializeSeed<'de> for PathSeed<'p, T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Value = Option<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        if self.path.is_empty() {
+            T::deserialize(deserializer).map(Some)
+        } else {
+            deserializer.deserialize_map(self)
+        }
+    }
+}
+
+impl<'de, 'p, T> de::Visitor<'de> for PathSeed<'p, T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Value = Option<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a table")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Option<T>, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let (key, rest) = self.path.split_first().expect("path is empty");
+        let mut ret = None;
+        // The other keys are skipped rather than returning early, so that
+        // errors such as duplicate tables are reported just like `from_str`.
+        while let Some(matches) = map.next_key_seed(KeyMatches(key))? {
+            if matches && ret.is_none() {
+                ret = map.next_value_seed(PathSeed::new(rest))?;
+            } else {
+                map.next_value::<de::IgnoredAny>()?;
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Deserializes a key into whether it is equal to the given one.
+struct KeyMatches<'k>(&'k str);
+
+impl<'de, 'k> de::DeserializeSeed<'de> for KeyMatches<'k> {
+    type Value = bool;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<bool, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, 'k> de::Visitor<'de> for KeyMatches<'k> {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<bool, E> {
+        Ok(s == self.0)
+    }
+}
+
+/// Deserializes a string into a type, also returning the keys that the type
+/// ignored.
+///
+/// Each ignored key is returned with its full path and the span of the key
+/// in `s`, in the order they were encountered. See
+/// `Deserializer::set_unused_key_callback` for details.
+///
+/// # Examples
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     server: Server,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// let input = "[server]\nport = 80\nhots = ['a']\n";
+/// let (config, unused) = toml::de::from_str_with_unused::<Config>(input).unwrap();
+/// assert_eq!(config.server.port, 80);
+/// assert_eq!(unused, [(vec!["server".to_string(), "hots".to_string()], 19..23)]);
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn from_str_with_unused<'de, T>(
+    s: &'de str,
+) -> Result<(T, Vec<(Vec<String>, Range<usize>)>), Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let unused = Rc::new(RefCell::new(Vec::new()));
+    let mut d = Deserializer::new(s);
+    let sink = unused.clone();
+    d.set_unused_key_callback(move |path, span| sink.borrow_mut().push((path.to_vec(), span)));
+    let ret = T::deserialize(&mut d)?;
+    d.end()?;
+    let unused = mem::take(&mut *unused.borrow_mut());
+    Ok((ret, unused))
+}
+
+/// Parses a TOML document, reporting every error found in it.
+///
+/// Unlike `from_str`, parsing does not stop at the first syntax error. After a
+/// malformed key/value pair, table header, array or inline table the parser
+/// skips to the next line that starts a new statement and carries on, so all
+/// of the problems in a document can be reported at once. Errors which can
+/// only be detected once the document has been read, such as duplicate keys,
+/// are only reported for the parts of the document which parsed successfully.
+///
+/// The returned errors are ordered by their position in the input. The value
+/// is `Some` if a value could be assembled from the statements that parsed
+/// successfully, so it is possible to get both a value and errors.
+///
+/// # Examples
+///
+/// ```
+/// let (value, errors) = toml::de::parse_with_diagnostics("a = 1\nb = \nc = [1, 2\nd = 4\n");
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(errors[0].line_col(), Some((1, 4)));
+/// assert_eq!(errors[1].line_col(), Some((3, 0)));
+///
+/// let value = value.unwrap();
+/// assert_eq!(value["a"].as_integer(), Some(1));
+/// assert_eq!(value["d"].as_integer(), Some(4));
+/// ```
+pub fn parse_with_diagnostics(s: &str) -> (Option<crate::Value>, Vec<Error>) {
+    let mut d = Deserializer::new(s);
+    d.errors = Some(Vec::new());
+    let res = de::Deserialize::deserialize(&mut d);
+    let mut errors = d.errors.take().unwrap_or_default();
+    let value = match res {
+        Ok(value) => Some(value),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+    errors.sort_by_key(|e| (e.inner.span.is_none(), e.offset()));
+    (value, errors)
+}
+
+/// The tables of a document read by `document::Document`.
+///
+/// A document reads its input itself to record the layout, reading keys and
+/// values through a `Deserializer` and handing each statement over here as it
+/// goes. Once the whole input has been read the tables are checked for
+/// duplicate keys and the like, as they are by `from_str`.
+pub(crate) struct DocumentTables<'a> {
+    tables: Vec<Table<'a>>,
+}
+
+impl<'a> DocumentTables<'a> {
+    /// Creates the deserializer reading the keys and values of `input`,
+    /// which are kept as `i64` and `f64` numbers.
+    pub(crate) fn deserializer(input: &'a str) -> Deserializer<'a> {
+        let mut d = Deserializer::new(input);
+        d.raw_numbers = false;
+        d.arbitrary_precision = false;
+        d
+    }
+
+    pub(crate) fn new() -> DocumentTables<'a> {
+        DocumentTables {
+            tables: vec![Table {
+                at: 0,
+                end: 0,
+                header: Vec::new(),
+                values: None,
+                array: false,
+            }],
+        }
+    }
+
+    /// Starts the table defined by the header spanning `span`.
+    pub(crate) fn header(
+        &mut self,
+        span: Range<usize>,
+        header: Vec<(Span, Cow<'a, str>)>,
+        array: bool,
+    ) {
+        self.tables.push(Table {
+            at: span.start,
+            end: span.end,
+            header,
+            values: Some(Vec::new()),
+            array,
+        });
+    }
+
+    /// Adds a key/value pair to the current table.
+    pub(crate) fn entry(
+        &mut self,
+        de: &Deserializer<'a>,
+        key: Vec<(Span, Cow<'a, str>)>,
+        value: Value<'a>,
+    ) -> Result<(), Error> {
+        let table = self
+            .tables
+            .last_mut()
+            .expect("root table is always present");
+        de.add_dotted_key(key, value, table.values.get_or_insert_with(Vec::new))
+    }
+
+    pub(crate) fn check(mut self, de: &mut Deserializer<'a>) -> Result<(), Error> {
+        self.tables
+            .retain(|table| !table.header.is_empty() || table.values.is_some());
+        de.visit_tables(self.tables, de::IgnoredAny).map(|_| ())
+    }
+}
+
+/// Checks that `input` is a single TOML integer or float, of any size, as
+/// kept by `Number`.
+pub(crate) fn validate_number(input: &str) -> Result<(), Error> {
+    let mut d = Deserializer::new(input);
+    d.raw_numbers = true;
+    d.arbitrary_precision = true;
+    match d.value()?.e {
+        E::Number(s, _) if s.len() == input.len() => Ok(()),
+        _ => Err(d.error(0..input.len(), ErrorKind::NumberInvalid)),
+    }
+}
+
+/// Parses `input` into a `SpannedValue`, building it straight from the
+/// tables and values read by the deserializer.
+pub(crate) fn parse_spanned(input: &str) -> Result<SpannedValue, Error> {
+    let mut d = Deserializer::new(input);
+    let tables = d.tables()?;
+    let root = spanned_root(&d, &tables);
+    // The tables are then checked for errors such as duplicate keys, as they
+    // would be when deserializing a `Value`, which are reported first.
+    d.visit_tables(tables, de::IgnoredAny)?;
+    Ok(SpannedValue::Table(Spanned::new(0..input.len(), root?)))
+}
+
+fn spanned_root(d: &Deserializer<'_>, tables: &[Table<'_>]) -> Result<SpannedTable, Error> {
+    let mut root = SpannedTable::default();
+    for table in tables {
+        let mut cur = &mut root;
+        if let Some((last, parents)) = table.header.split_last() {
+            for key in parents {
+                cur = spanned_subtable(cur, key, None, false);
+            }
+            cur = spanned_subtable(cur, last, Some(table.span()), table.array);
+        }
+        for (key, value) in table.values.iter().flatten() {
+            insert_spanned(d, cur, key, value).map_err(|mut e| {
+                for key in table.header.iter().rev() {
+                    e.add_key_context(&key.1);
+                }
+                e
+            })?;
+        }
+    }
+    Ok(root)
+}
+
+fn spanned_key(key: &(Span, Cow<'_, str>)) -> Spanned<String> {
+    Spanned::new(key.0.start..key.0.end, key.1.to_string())
+}
+
+/// Looks up the table at `key` in `table`, or the last table if `key` is an
+/// array of tables, creating it if it doesn't exist yet.
+///
+/// `header` is the span of the table header defining the table, if any, and
+/// `array` is set if that header appends a new table to an array of tables.
+/// Tables which are only defined implicitly are attributed to their key.
+fn spanned_subtable<'t>(
+    table: &'t mut SpannedTable,
+    key: &(Span, Cow<'_, str>),
+    header: Option<Range<usize>>,
+    array: bool,
+) -> &'t mut SpannedTable {
+    let span = header.clone().unwrap_or(key.0.start..key.0.end);
+    let (key, value) = table.entry(spanned_key(key), || {
+        if array {
+            SpannedValue::Array(Spanned::new(span.clone(), Vec::new()))
+        } else {
+            SpannedValue::Table(Spanned::new(span.clone(), SpannedTable::default()))
+        }
+    });
+    match value {
+        SpannedValue::Array(tables) => {
+            let tables = tables.get_mut();
+            if array || tables.is_empty() {
+                let table = Spanned::new(span, SpannedTable::default());
+                tables.push(SpannedValue::Table(table));
+            }
+            value_table(tables.last_mut().expect("array of tables is empty"))
+        }
+        SpannedValue::Table(t) => {
+            // A table defined implicitly before its header still has the span
+            // of the key which first named it.
+            if header.is_some() && t.span() == key.span() {
+                *t = Spanned::new(span, mem::take(t.get_mut()));
+            }
+            t.get_mut()
+        }
+        value => value_table(value),
+    }
+}
+
+/// Returns the table `value` holds.
+///
+/// The tree is built before it is checked for errors, so with invalid input
+/// anything else is replaced with an empty table, to be discarded once
+/// `visit_tables` reports the error.
+fn value_table(value: &mut SpannedValue) -> &mut SpannedTable {
+    if !matches!(value, SpannedValue::Table(_)) {
+        *value = SpannedValue::Table(Spanned::new(0..0, SpannedTable::default()));
+    }
+    match value {
+        SpannedValue::Table(t) => t.get_mut(),
+        _ => unreachable!(),
+    }
+}
+
+fn insert_spanned(
+    d: &Deserializer<'_>,
+    table: &mut SpannedTable,
+    key: &(Span, Cow<'_, str>),
+    value: &Value<'_>,
+) -> Result<(), Error> {
+    insert_spanned_value(d, table, key, value).map_err(|mut e| {
+        e.add_key_context(&key.1);
+        e
+    })
+}
+
+fn insert_spanned_value(
+    d: &Deserializer<'_>,
+    table: &mut SpannedTable,
+    key: &(Span, Cow<'_, str>),
+    value: &Value<'_>,
+) -> Result<(), Error> {
+    if let E::DottedTable(ref values) = value.e {
+        // Dotted keys only define tables implicitly, so the table is
+        // attributed to its key.
+        let (_, entry) = table.entry(spanned_key(key), || {
+            SpannedValue::Table(Spanned::new(
+                key.0.start..key.0.end,
+                SpannedTable::default(),
+            ))
+        });
+        let entry = value_table(entry);
+        for (key, value) in values {
+            insert_spanned(d, entry, key, value)?;
+        }
+        return Ok(());
+    }
+    let value = spanned_value(d, value)?;
+    table.entry(spanned_key(key), || value);
+    Ok(())
+}
+
+fn spanned_value(d: &Deserializer<'_>, value: &Value<'_>) -> Result<SpannedValue, Error> {
+    let span = value.start..value.end;
+    Ok(match value.e {
+        E::Integer(i) => SpannedValue::Integer(Spanned::new(span, i)),
+        E::UInteger(i) => SpannedValue::UInteger(Spanned::new(span, i)),
+        E::Number(s, true) => {
+            SpannedValue::Number(Spanned::new(span, number::Number::from_raw(s.to_string())))
+        }
+        // Numbers represented exactly are read like a `Value` reads them.
+        E::Number(s, false) => {
+            let n = number::Number::from_raw(s.to_string());
+            if n.is_float() {
+                SpannedValue::Float(Spanned::new(span, n.as_f64().expect("float is exact")))
+            } else if let Some(i) = n.as_i64() {
+                SpannedValue::Integer(Spanned::new(span, i))
+            } else {
+                SpannedValue::UInteger(Spanned::new(span, n.as_u64().expect("integer is exact")))
+            }
+        }
+        E::Float(f) => SpannedValue::Float(Spanned::new(span, f)),
+        E::Boolean(b) => SpannedValue::Boolean(Spanned::new(span, b)),
+        E::String(ref s) => SpannedValue::String(Spanned::new(span, s.to_string())),
+        E::Datetime(s) => match datetime::parse(s, false, d.spec) {
+            Ok(date) => SpannedValue::Datetime(Spanned::new(span, date)),
+            Err(e) => {
+                let mut err = Error::custom(Some(span), e.to_string());
+                d.fix_error(&mut err);
+                return Err(err);
+            }
+        },
+        E::Array(ref values) => SpannedValue::Array(Spanned::new(
+            span,
+            values
+                .iter()
+                .map(|v| spanned_value(d, v))
+                .collect::<Result<_, _>>()?,
+        )),
+        E::InlineTable(ref values) | E::DottedTable(ref values) => {
+            let mut table = SpannedTable::default();
+            for (key, value) in values {
+                insert_spanned(d, &mut table, key, value)?;
+            }
+            SpannedValue::Table(Spanned::new(span, table))
+        }
+    })
+}
+
+/// Converts an error produced by the tokenizer into a deserialization error
+/// positioned within `input`.
+pub(crate) fn token_error(input: &str, error: TokenError) -> Error {
+    Deserializer::new(input).token_error(error)
+}
+
 /// Errors that can occur when deserializing a type.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Error {
@@ -93,14 +626,20 @@ struct ErrorInner {
     kind: ErrorKind,
     line: Option<usize>,
     col: usize,
-    at: Option<usize>,
+    span: Option<Range<usize>>,
     message: String,
     key: Vec<String>,
+    suggestion: Option<&'static str>,
+    original: Option<Range<usize>>,
 }
 
-/// Errors that can occur when deserializing a type.
+/// The kinds of errors that can occur when deserializing a type.
+///
+/// New kinds of errors may be added in the future, so matches on this type
+/// need a wildcard arm.
 #[derive(Debug, PartialEq, Eq, Clone)]
-enum ErrorKind {
+#[non_exhaustive]
+pub enum ErrorKind {
     /// EOF was reached when looking for a value
     UnexpectedEof,
 
@@ -149,6 +688,9 @@ enum ErrorKind {
     /// A duplicate table definition was found.
     DuplicateTable(String),
 
+    /// A key was defined more than once in the same table.
+    DuplicateKey(String),
+
     /// A previously defined table was redefined as an array.
     RedefineAsArray,
 
@@ -194,63 +736,341 @@ enum ErrorKind {
     /// Unquoted string was found when quoted one was expected
     UnquotedString,
 
-    #[doc(hidden)]
-    __Nonexhaustive,
+    /// Reading the input failed
+    Io(io::ErrorKind),
+
+    /// An array contained values of different types, which TOML 0.5 does
+    /// not allow.
+    MixedArrayType,
+
+    /// The input exceeded one of the limits set with
+    /// `Deserializer::set_limits`.
+    LimitExceeded {
+        /// The name of the limit, such as `max_depth`.
+        limit: &'static str,
+        /// The value the limit was set to.
+        max: usize,
+    },
 }
 
-/// Deserialization implementation for TOML.
-pub struct Deserializer<'a> {
+/// A version of the TOML specification.
+///
+/// Newer versions only add to the syntax of older ones, so versions are
+/// ordered by the syntax they accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub enum TomlVersion {
+    /// [TOML 0.5.0](https://toml.io/en/v0.5.0), in which arrays cannot mix
+    /// values of different types.
+    V0_5,
+    /// [TOML 1.0.0](https://toml.io/en/v1.0.0), the default.
+    #[default]
+    V1_0,
+    /// TOML 1.1, which allows newlines, comments and a trailing comma in
+    /// inline tables, the `\e` and `\xHH` escapes in basic strings, and
+    /// times without seconds such as `07:32`.
+    V1_1,
+}
+
+/// What to do with a key or table which is defined more than once.
+///
+/// TOML doesn't allow this, but layered or hand-edited files sometimes need
+/// to be read anyway. A table is only defined by its header or as an inline
+/// table: the tables implied by dotted keys and by the headers of the tables
+/// within them can be extended by any number of keys and headers, and are
+/// merged with an existing table of the same name whatever the policy. An
+/// inline table can't be extended by dotted keys under any policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum DuplicateKeyPolicy {
+    /// Rejects the document, the default. The error points at the duplicate,
+    /// and `Error::original_span` at the original definition.
+    #[default]
+    Error,
+    /// Keeps the first definition, ignoring any later ones.
+    FirstWins,
+    /// Keeps the last definition, replacing any earlier ones.
+    LastWins,
+    /// Merges tables which are defined more than once, resolving keys defined
+    /// in both of them in the same way. Otherwise the last definition wins.
+    MergeTables,
+}
+
+/// Options controlling how TOML is parsed.
+///
+/// Options are built up from `Options::new` and passed to
+/// `Deserializer::with_options` or `from_str_with_options`.
+#[derive(Debug, Clone)]
+pub struct Options {
+    spec: TomlVersion,
+    strict_datetimes: bool,
+    unsigned_integers: bool,
+    preserve_number_format: bool,
     require_newline_after_table: bool,
-    allow_duplciate_after_longer_table: bool,
-    input: &'a str,
-    tokens: Tokenizer<'a>,
+    allow_duplicate_after_longer_table: bool,
+    duplicate_key_policy: DuplicateKeyPolicy,
+    limits: Limits,
 }
 
-impl<'de, 'b> de::Deserializer<'de> for &'b mut Deserializer<'de> {
-    type Error = Error;
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            spec: TomlVersion::default(),
+            strict_datetimes: true,
+            unsigned_integers: false,
+            preserve_number_format: false,
+            require_newline_after_table: true,
+            allow_duplicate_after_longer_table: false,
+            duplicate_key_policy: DuplicateKeyPolicy::default(),
+            limits: Limits::default(),
+        }
+    }
+}
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
-    where
-        V: de::Visitor<'de>,
-    {
-        let mut tables = self.tables()?;
-        let table_indices = build_table_indices(&tables);
-        let table_pindices = build_table_pindices(&tables);
+impl Options {
+    /// Creates the default options, which parse TOML 1.0.
+    pub fn new() -> Options {
+        Options::default()
+    }
 
-        let res = visitor.visit_map(MapVisitor {
-            values: Vec::new().into_iter().peekable(),
-            next_value: None,
-            depth: 0,
-            cur: 0,
-            cur_parent: 0,
-            max: tables.len(),
-            table_indices: &table_indices,
-            table_pindices: &table_pindices,
-            tables: &mut tables,
-            array: false,
-            de: self,
-        });
-        res.map_err(|mut err| {
-            // Errors originating from this library (toml), have an offset
-            // attached to them already. Other errors, like those originating
-            // from serde (like "missing field") or from a custom deserializer,
-            // do not have offsets on them. Here, we do a best guess at their
-            // location, by attributing them to the "current table" (the last
-            // item in `tables`).
-            err.fix_offset(|| tables.last().map(|table| table.at));
-            err.fix_linecol(|at| self.to_linecol(at));
-            err
-        })
+    /// Selects the version of the TOML specification to parse.
+    ///
+    /// Syntax introduced by a later version of the specification is rejected
+    /// as it would be by a parser for the selected version.
+    pub fn spec(mut self, version: TomlVersion) -> Options {
+        self.spec = version;
+        self
     }
 
-    // Called when the type to deserialize is an enum, as opposed to a field in the type.
-    fn deserialize_enum<V>(
-        self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        visitor: V,
-    ) -> Result<V::Value, Error>
-    where
+    /// Sets whether datetimes are checked to be possible ones (the default),
+    /// rejecting for example February 30th, or hour 24.
+    ///
+    /// With this set to `false` each component of a datetime is only checked
+    /// to be within its largest possible range, as older versions of toml-rs
+    /// did. See `Datetime::from_str_lenient`.
+    pub fn strict_datetimes(mut self, strict: bool) -> Options {
+        self.strict_datetimes = strict;
+        self
+    }
+
+    /// Sets whether integers larger than `i64::MAX` are accepted (the default
+    /// is `false`, as the TOML specification only requires 64-bit signed
+    /// integers).
+    ///
+    /// When enabled such integers, up to `u64::MAX`, can be deserialized into
+    /// `u64` and `u128` fields and are represented as `Value::UInteger`.
+    /// `Serializer::unsigned_integers` enables writing them back out.
+    pub fn unsigned_integers(mut self, unsigned: bool) -> Options {
+        self.unsigned_integers = unsigned;
+        self
+    }
+
+    /// Sets whether numbers deserialized into a `Value` remember how they
+    /// were written (the default is `false`).
+    ///
+    /// When enabled numbers are kept as a `Value::Number`, holding their text
+    /// such as `0o755`, `1_000_000` or `6.02e23`, which is written back
+    /// unchanged when the value is serialized. They are still checked to be
+    /// in range as usual. Only `Value` and `Number` are affected: other
+    /// types, including those deserialized through `#[serde(flatten)]` or
+    /// `#[serde(untagged)]`, are given the numbers themselves.
+    ///
+    /// Without this, the `arbitrary_precision` feature only keeps numbers
+    /// which an `i64` or `f64` can't represent exactly.
+    pub fn preserve_number_format(mut self, preserve: bool) -> Options {
+        self.preserve_number_format = preserve;
+        self
+    }
+
+    /// See `Deserializer::set_require_newline_after_table`.
+    pub fn require_newline_after_table(mut self, require: bool) -> Options {
+        self.require_newline_after_table = require;
+        self
+    }
+
+    /// See `Deserializer::set_allow_duplicate_after_longer_table`.
+    pub fn allow_duplicate_after_longer_table(mut self, allow: bool) -> Options {
+        self.allow_duplicate_after_longer_table = allow;
+        self
+    }
+
+    /// See `Deserializer::set_duplicate_key_policy`.
+    pub fn duplicate_key_policy(mut self, policy: DuplicateKeyPolicy) -> Options {
+        self.duplicate_key_policy = policy;
+        self
+    }
+
+    /// See `Deserializer::set_limits`.
+    pub fn limits(mut self, limits: Limits) -> Options {
+        self.limits = limits;
+        self
+    }
+}
+
+/// Limits on the input a `Deserializer` accepts, for parsing untrusted
+/// documents.
+///
+/// Input going over a limit is rejected with `ErrorKind::LimitExceeded`.
+/// Every limit but `max_depth` defaults to `usize::MAX`, i.e. no limit, while
+/// `max_depth` defaults to `Limits::DEFAULT_MAX_DEPTH`. Only the limits of
+/// interest need to be set:
+///
+/// ```
+/// use toml::de::{Deserializer, ErrorKind, Limits};
+/// use serde::Deserialize;
+///
+/// let mut de = Deserializer::new("a = [[1]]");
+/// de.set_limits(Limits {
+///     max_depth: 2,
+///     ..Limits::default()
+/// });
+/// let err = toml::Value::deserialize(&mut de).unwrap_err();
+/// assert_eq!(
+///     *err.kind(),
+///     ErrorKind::LimitExceeded {
+///         limit: "max_depth",
+///         max: 2
+///     }
+/// );
+/// assert_eq!(err.span(), Some(5..6));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The maximum nesting depth of a value, counting each key and array on
+    /// the path to it. `a.b = [1]` puts `1` at a depth of 3, as does
+    /// `c = 1` in an `[a.b]` table.
+    ///
+    /// This defaults to `Limits::DEFAULT_MAX_DEPTH`. The input itself is
+    /// parsed without recursion, but deserializing a type from it recurses
+    /// once per level of nesting, so a much larger limit may overflow the
+    /// stack.
+    pub max_depth: usize,
+    /// The maximum number of keys in the document, counting each key/value
+    /// pair, including those in inline tables, and each table header.
+    pub max_keys: usize,
+    /// The maximum length of a string or key in bytes, after escapes have
+    /// been processed. Strings are rejected as soon as they go over it,
+    /// rather than once they have been read in full.
+    pub max_string_len: usize,
+    /// The maximum length of the whole input in bytes.
+    pub max_input_len: usize,
+    /// The maximum number of values in an array. Arrays of tables are bounded
+    /// by `max_keys` instead.
+    pub max_array_len: usize,
+}
+
+impl Limits {
+    /// The default `max_depth`, which no reasonable document comes close to.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_depth: Limits::DEFAULT_MAX_DEPTH,
+            max_keys: usize::MAX,
+            max_string_len: usize::MAX,
+            max_input_len: usize::MAX,
+            max_array_len: usize::MAX,
+        }
+    }
+}
+
+/// Deserialization implementation for TOML.
+pub struct Deserializer<'a> {
+    spec: TomlVersion,
+    strict_datetimes: bool,
+    unsigned_integers: bool,
+    // Whether numbers are kept as their text for `Number`, whether integers
+    // of any size are then accepted, and whether a `Value` keeps all of them
+    // as a `Number`.
+    raw_numbers: bool,
+    arbitrary_precision: bool,
+    preserve_number_format: bool,
+    require_newline_after_table: bool,
+    allow_duplciate_after_longer_table: bool,
+    duplicate_key_policy: DuplicateKeyPolicy,
+    limits: Limits,
+    // The depth of the value being parsed and the number of keys seen so
+    // far, checked against `limits`.
+    depth: usize,
+    keys: usize,
+    input: &'a str,
+    tokens: Tokenizer<'a>,
+    errors: Option<Vec<Error>>,
+    unused: Option<Rc<UnusedKeys<'a>>>,
+}
+
+type UnusedKeyCallback<'a> = Box<dyn FnMut(&[String], Range<usize>) + 'a>;
+
+/// Tracks the path to the value being deserialized so that keys ignored by
+/// the target type can be reported.
+struct UnusedKeys<'a> {
+    callback: RefCell<UnusedKeyCallback<'a>>,
+    path: RefCell<Vec<(Span, String)>>,
+    // Set while skipping over an ignored table, whose contents are not
+    // reported on their own.
+    ignoring: Cell<bool>,
+}
+
+impl<'a> UnusedKeys<'a> {
+    fn within<T, F>(unused: &Option<Rc<Self>>, key: &(Span, Cow<'_, str>), f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        if let Some(unused) = unused {
+            unused.path.borrow_mut().push((key.0, key.1.to_string()));
+            let ret = f();
+            unused.path.borrow_mut().pop();
+            ret
+        } else {
+            f()
+        }
+    }
+
+    /// Reports the current key as unused, and runs `f` to skip over its value
+    /// without reporting anything inside of it.
+    fn ignore<T, F>(unused: &Option<Rc<Self>>, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let unused = match unused {
+            Some(unused) if !unused.ignoring.get() => unused,
+            _ => return f(),
+        };
+        {
+            let path = unused.path.borrow();
+            if let Some(&(span, _)) = path.last() {
+                let keys = path.iter().map(|k| k.1.clone()).collect::<Vec<_>>();
+                (unused.callback.borrow_mut())(&keys, span.start..span.end);
+            }
+        }
+        unused.ignoring.set(true);
+        let ret = f();
+        unused.ignoring.set(false);
+        ret
+    }
+}
+
+impl<'de, 'b> de::Deserializer<'de> for &'b mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let tables = self.tables()?;
+        self.visit_tables(tables, visitor)
+    }
+
+    // Called when the type to deserialize is an enum, as opposed to a field in the type.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
         V: de::Visitor<'de>,
     {
         let (value, name) = self.string_or_table()?;
@@ -258,8 +1078,8 @@ impl<'de, 'b> de::Deserializer<'de> for &'b mut Deserializer<'de> {
             E::String(val) => visitor.visit_enum(val.into_deserializer()),
             E::InlineTable(values) => {
                 if values.len() != 1 {
-                    Err(Error::from_kind(
-                        Some(value.start),
+                    Err(Error::from_kind_spanned(
+                        value.start..value.end,
                         ErrorKind::Wanted {
                             expected: "exactly 1 element",
                             found: if values.is_empty() {
@@ -273,6 +1093,8 @@ impl<'de, 'b> de::Deserializer<'de> for &'b mut Deserializer<'de> {
                     visitor.visit_enum(InlineTableDeserializer {
                         values: values.into_iter(),
                         next_value: None,
+                        unused: None,
+                        defined: HashMap::new(),
                     })
                 }
             }
@@ -280,8 +1102,8 @@ impl<'de, 'b> de::Deserializer<'de> for &'b mut Deserializer<'de> {
                 name: name.expect("Expected table header to be passed."),
                 value,
             }),
-            e => Err(Error::from_kind(
-                Some(value.start),
+            e => Err(Error::from_kind_spanned(
+                value.start..value.end,
                 ErrorKind::Wanted {
                     expected: "string or table",
                     found: e.type_name(),
@@ -316,7 +1138,7 @@ impl<'de, 'b> de::Deserializer<'de> for &'b mut Deserializer<'de> {
     }
 
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string seq
+        bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64 char str string seq
         bytes byte_buf map unit newtype_struct
         ignored_any unit_struct tuple_struct tuple option identifier
     }
@@ -377,12 +1199,20 @@ fn headers_equal<'a, 'b>(hdr_a: &[(Span, Cow<'a, str>)], hdr_b: &[(Span, Cow<'b,
 }
 
 struct Table<'a> {
+    // The span of the table's header, which is empty for the root table.
     at: usize,
+    end: usize,
     header: Vec<(Span, Cow<'a, str>)>,
     values: Option<Vec<TablePair<'a>>>,
     array: bool,
 }
 
+impl<'a> Table<'a> {
+    fn span(&self) -> Range<usize> {
+        self.at..self.end
+    }
+}
+
 struct MapVisitor<'de, 'b> {
     values: iter::Peekable<vec::IntoIter<TablePair<'de>>>,
     next_value: Option<TablePair<'de>>,
@@ -394,9 +1224,28 @@ struct MapVisitor<'de, 'b> {
     table_pindices: &'b HashMap<Vec<Cow<'de, str>>, Vec<usize>>,
     tables: &'b mut [Table<'de>],
     array: bool,
+    // The keys returned so far, and where they were defined. Table headers
+    // are recorded by their offset alone.
+    defined: HashMap<Cow<'de, str>, Range<usize>>,
     de: &'b mut Deserializer<'de>,
 }
 
+impl<'de, 'b> MapVisitor<'de, 'b> {
+    /// Records that `key` was defined at `span`, failing if it already was.
+    fn define(&mut self, key: Cow<'de, str>, span: Range<usize>) -> Result<(), Error> {
+        match self.defined.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(span);
+                Ok(())
+            }
+            Entry::Occupied(entry) => {
+                let kind = ErrorKind::DuplicateKey(entry.key().to_string());
+                Err(self.de.duplicate(entry.get().clone(), span, kind))
+            }
+        }
+    }
+}
+
 impl<'de, 'b> de::MapAccess<'de> for MapVisitor<'de, 'b> {
     type Error = Error;
 
@@ -411,6 +1260,7 @@ impl<'de, 'b> de::MapAccess<'de> for MapVisitor<'de, 'b> {
         loop {
             assert!(self.next_value.is_none());
             if let Some((key, value)) = self.values.next() {
+                self.define(key.1.clone(), key.0.start..key.0.end)?;
                 let ret = seed.deserialize(StrDeserializer::spanned(key.clone()))?;
                 self.next_value = Some((key, value));
                 return Ok(Some(ret));
@@ -450,14 +1300,16 @@ impl<'de, 'b> de::MapAccess<'de> for MapVisitor<'de, 'b> {
                     &self.tables[self.cur_parent].header,
                     &self.tables[pos].header,
                 ) {
-                    let at = self.tables[pos].at;
+                    let span = self.tables[pos].span();
+                    let original = self.tables[self.cur_parent].span();
                     let name = self.tables[pos]
                         .header
                         .iter()
                         .map(|k| k.1.to_owned())
                         .collect::<Vec<_>>()
                         .join(".");
-                    return Err(self.de.error(at, ErrorKind::DuplicateTable(name)));
+                    let kind = ErrorKind::DuplicateTable(name);
+                    return Err(self.de.duplicate(original, span, kind));
                 }
 
                 // If we're here we know we should share the same prefix, and if
@@ -473,24 +1325,26 @@ impl<'de, 'b> de::MapAccess<'de> for MapVisitor<'de, 'b> {
                 }
             }
 
-            let table = &mut self.tables[pos];
-
             // If we're not yet at the appropriate depth for this table then we
             // just next the next portion of its header and then continue
             // decoding.
-            if self.depth != table.header.len() {
-                let key = &table.header[self.depth];
-                let key = seed.deserialize(StrDeserializer::spanned(key.clone()))?;
+            if self.depth != self.tables[pos].header.len() {
+                let key = self.tables[pos].header[self.depth].clone();
+                let span = self.tables[pos].span();
+                self.define(key.1.clone(), span)?;
+                let key = seed.deserialize(StrDeserializer::spanned(key))?;
                 return Ok(Some(key));
             }
 
+            let table = &mut self.tables[pos];
+
             // Rule out cases like:
             //
             //      [[foo.bar]]
             //      [[foo]]
             if table.array {
                 let kind = ErrorKind::RedefineAsArray;
-                return Err(self.de.error(table.at, kind));
+                return Err(self.de.error(table.span(), kind));
             }
 
             self.values = table
@@ -507,7 +1361,11 @@ impl<'de, 'b> de::MapAccess<'de> for MapVisitor<'de, 'b> {
         V: de::DeserializeSeed<'de>,
     {
         if let Some((k, v)) = self.next_value.take() {
-            match seed.deserialize(ValueDeserializer::new(v)) {
+            let unused = self.de.unused.clone();
+            let res = UnusedKeys::within(&unused, &k, || {
+                seed.deserialize(ValueDeserializer::new(v).with_unused(unused.clone()))
+            });
+            match res {
                 Ok(v) => return Ok(v),
                 Err(mut e) => {
                     e.add_key_context(&k.1);
@@ -519,18 +1377,23 @@ impl<'de, 'b> de::MapAccess<'de> for MapVisitor<'de, 'b> {
         let array =
             self.tables[self.cur].array && self.depth == self.tables[self.cur].header.len() - 1;
         self.cur += 1;
-        let res = seed.deserialize(MapVisitor {
-            values: Vec::new().into_iter().peekable(),
-            next_value: None,
-            depth: self.depth + if array { 0 } else { 1 },
-            cur_parent: self.cur - 1,
-            cur: 0,
-            max: self.max,
-            array,
-            table_indices: &*self.table_indices,
-            table_pindices: &*self.table_pindices,
-            tables: &mut *self.tables,
-            de: &mut *self.de,
+        let unused = self.de.unused.clone();
+        let key = self.tables[self.cur - 1].header[self.depth].clone();
+        let res = UnusedKeys::within(&unused, &key, || {
+            seed.deserialize(MapVisitor {
+                values: Vec::new().into_iter().peekable(),
+                next_value: None,
+                depth: self.depth + if array { 0 } else { 1 },
+                cur_parent: self.cur - 1,
+                cur: 0,
+                max: self.max,
+                array,
+                table_indices: &*self.table_indices,
+                table_pindices: &*self.table_pindices,
+                tables: &mut *self.tables,
+                defined: HashMap::new(),
+                de: &mut *self.de,
+            })
         });
         res.map_err(|mut e| {
             e.add_key_context(&self.tables[self.cur - 1].header[self.depth].1);
@@ -592,6 +1455,7 @@ impl<'de, 'b> de::SeqAccess<'de> for MapVisitor<'de, 'b> {
             table_indices: &*self.table_indices,
             table_pindices: &*self.table_pindices,
             tables: &mut self.tables,
+            defined: HashMap::new(),
             de: &mut self.de,
         })?;
         self.cur_parent = next;
@@ -677,14 +1541,14 @@ impl<'de, 'b> de::Deserializer<'de> for MapVisitor<'de, 'b> {
     {
         if self.tables.len() != 1 {
             return Err(Error::custom(
-                Some(self.cur),
+                Some(self.cur..self.cur),
                 "enum table must contain exactly one table".into(),
             ));
         }
         let table = &mut self.tables[0];
         let values = table.values.take().expect("table has no values?");
         if table.header.is_empty() {
-            return Err(self.de.error(self.cur, ErrorKind::EmptyTableKey));
+            return Err(self.de.error(table.span(), ErrorKind::EmptyTableKey));
         }
         let name = table.header[table.header.len() - 1].1.to_owned();
         visitor.visit_enum(DottedTableDeserializer {
@@ -697,10 +1561,20 @@ impl<'de, 'b> de::Deserializer<'de> for MapVisitor<'de, 'b> {
         })
     }
 
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // The tables still have to be walked to keep track of where the next
+        // one starts.
+        let unused = self.de.unused.clone();
+        UnusedKeys::ignore(&unused, || self.deserialize_any(visitor))
+    }
+
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string seq
+        bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64 char str string seq
         bytes byte_buf map unit identifier
-        ignored_any unit_struct tuple_struct tuple
+        unit_struct tuple_struct tuple
     }
 }
 
@@ -736,10 +1610,16 @@ impl<'de> de::Deserializer<'de> for StrDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.key {
+        let span = self.span;
+        let res: Result<V::Value, Error> = match self.key {
             Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
             Cow::Owned(s) => visitor.visit_string(s),
-        }
+        };
+        res.map_err(|mut err| {
+            // Attribute errors such as unknown fields to the key itself.
+            err.fix_span(|| span.map(|span| span.start..span.end));
+            err
+        })
     }
 
     fn deserialize_struct<V>(
@@ -765,15 +1645,16 @@ impl<'de> de::Deserializer<'de> for StrDeserializer<'de> {
     }
 
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string seq
+        bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64 char str string seq
         bytes byte_buf map option unit newtype_struct
         ignored_any unit_struct tuple_struct tuple enum identifier
     }
 }
 
-struct ValueDeserializer<'a> {
+pub(crate) struct ValueDeserializer<'a> {
     value: Value<'a>,
     validate_struct_keys: bool,
+    unused: Option<Rc<UnusedKeys<'a>>>,
 }
 
 impl<'a> ValueDeserializer<'a> {
@@ -781,15 +1662,43 @@ impl<'a> ValueDeserializer<'a> {
         ValueDeserializer {
             value,
             validate_struct_keys: false,
+            unused: None,
         }
     }
 
+    fn with_unused(mut self, unused: Option<Rc<UnusedKeys<'a>>>) -> Self {
+        self.unused = unused;
+        self
+    }
+
     fn with_struct_key_validation(mut self) -> Self {
         self.validate_struct_keys = true;
         self
     }
 }
 
+/// Implements the `deserialize_*` methods of the primitive number types,
+/// which take the value of a number kept as text rather than its text.
+macro_rules! deserialize_numbers {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                if let E::Number(s, _) = self.value.e {
+                    let (start, end) = (self.value.start, self.value.end);
+                    return number::visit(s, visitor).map_err(|mut err: Error| {
+                        err.fix_span(|| Some(start..end));
+                        err
+                    });
+                }
+                self.deserialize_any(visitor)
+            }
+        )*
+    };
+}
+
 impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
     type Error = Error;
 
@@ -797,11 +1706,13 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let start = self.value.start;
+        let (start, end) = (self.value.start, self.value.end);
         let res = match self.value.e {
             E::Integer(i) => visitor.visit_i64(i),
+            E::UInteger(i) => visitor.visit_u64(i),
             E::Boolean(b) => visitor.visit_bool(b),
             E::Float(f) => visitor.visit_f64(f),
+            E::Number(s, _) => number::visit(s, visitor),
             E::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
             E::String(Cow::Owned(s)) => visitor.visit_string(s),
             E::Datetime(s) => visitor.visit_map(DatetimeDeserializer {
@@ -809,21 +1720,27 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
                 visited: false,
             }),
             E::Array(values) => {
-                let mut s = de::value::SeqDeserializer::new(values.into_iter());
-                let ret = visitor.visit_seq(&mut s)?;
-                s.end()?;
-                Ok(ret)
+                let unused = self.unused;
+                let values = values
+                    .into_iter()
+                    .map(|v| ValueDeserializer::new(v).with_unused(unused.clone()));
+                let mut s = de::value::SeqDeserializer::new(values);
+                visitor
+                    .visit_seq(&mut s)
+                    .and_then(|ret| s.end().map(|()| ret))
             }
             E::InlineTable(values) | E::DottedTable(values) => {
                 visitor.visit_map(InlineTableDeserializer {
                     values: values.into_iter(),
                     next_value: None,
+                    unused: self.unused,
+                    defined: HashMap::new(),
                 })
             }
         };
         res.map_err(|mut err| {
             // Attribute the error to whatever value returned the error.
-            err.fix_offset(|| Some(start));
+            err.fix_span(|| Some(start..end));
             err
         })
     }
@@ -862,8 +1779,9 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
                         .collect::<Vec<_>>();
 
                     if !extra_fields.is_empty() {
-                        return Err(Error::from_kind(
-                            Some(self.value.start),
+                        let span = extra_fields[0].0;
+                        let mut err = Error::from_kind_spanned(
+                            span.start..span.end,
                             ErrorKind::UnexpectedKeys {
                                 keys: extra_fields
                                     .iter()
@@ -871,7 +1789,9 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
                                     .collect::<Vec<_>>(),
                                 available: fields,
                             },
-                        ));
+                        );
+                        err.inner.suggestion = suggest(&extra_fields[0].1, fields);
+                        return Err(err);
                     }
                 }
                 _ => {}
@@ -915,8 +1835,8 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
             E::String(val) => visitor.visit_enum(val.into_deserializer()),
             E::InlineTable(values) => {
                 if values.len() != 1 {
-                    Err(Error::from_kind(
-                        Some(self.value.start),
+                    Err(Error::from_kind_spanned(
+                        self.value.start..self.value.end,
                         ErrorKind::Wanted {
                             expected: "exactly 1 element",
                             found: if values.is_empty() {
@@ -930,11 +1850,13 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
                     visitor.visit_enum(InlineTableDeserializer {
                         values: values.into_iter(),
                         next_value: None,
+                        unused: None,
+                        defined: HashMap::new(),
                     })
                 }
             }
-            e => Err(Error::from_kind(
-                Some(self.value.start),
+            e => Err(Error::from_kind_spanned(
+                self.value.start..self.value.end,
                 ErrorKind::Wanted {
                     expected: "string or inline table",
                     found: e.type_name(),
@@ -945,19 +1867,62 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Error>
     where
         V: de::Visitor<'de>,
     {
+        if name == value::NAME {
+            match self.value.e {
+                E::UInteger(i) => return value::visit_unsigned(i, visitor),
+                E::Number(s, true) => return visit_number(s, visitor),
+                E::Number(s, false) => {
+                    if let Some(u) = number::as_large_u64(s) {
+                        return value::visit_unsigned(u, visitor);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if name == number::NAME {
+            if let E::Number(s, _) = self.value.e {
+                return visit_number(s, visitor);
+            }
+        }
         visitor.visit_newtype_struct(self)
     }
 
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let unused = self.unused.clone();
+        UnusedKeys::ignore(&unused, || match self.value.e {
+            // Numbers too large for any primitive type are still valid.
+            E::Number(..) => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        })
+    }
+
+    deserialize_numbers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string seq
+        bool char str string seq
         bytes byte_buf map unit identifier
-        ignored_any unit_struct tuple_struct tuple
+        unit_struct tuple_struct tuple
+    }
+}
+
+impl<'de> de::IntoDeserializer<'de, Error> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
     }
 }
 
@@ -1032,6 +1997,17 @@ where
     }
 }
 
+/// Visits the text of a number as the map which `Number` deserializes from.
+fn visit_number<'de, V>(s: &'de str, visitor: V) -> Result<V::Value, Error>
+where
+    V: de::Visitor<'de>,
+{
+    let mut map = de::value::MapDeserializer::new(iter::once((number::FIELD, s)));
+    visitor
+        .visit_map(&mut map)
+        .and_then(|ret| map.end().map(|()| ret))
+}
+
 struct DatetimeDeserializer<'a> {
     visited: bool,
     date: &'a str,
@@ -1072,7 +2048,7 @@ impl<'de> de::Deserializer<'de> for DatetimeFieldDeserializer {
     }
 
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string seq
+        bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64 char str string seq
         bytes byte_buf map struct option unit newtype_struct
         ignored_any unit_struct tuple_struct tuple enum identifier
     }
@@ -1099,7 +2075,10 @@ impl<'de> de::EnumAccess<'de> for DottedTableDeserializer<'de> {
 
 struct InlineTableDeserializer<'a> {
     values: vec::IntoIter<TablePair<'a>>,
-    next_value: Option<Value<'a>>,
+    next_value: Option<TablePair<'a>>,
+    unused: Option<Rc<UnusedKeys<'a>>>,
+    // The keys returned so far, and where they were defined.
+    defined: HashMap<Cow<'a, str>, Range<usize>>,
 }
 
 impl<'de> de::MapAccess<'de> for InlineTableDeserializer<'de> {
@@ -1113,7 +2092,17 @@ impl<'de> de::MapAccess<'de> for InlineTableDeserializer<'de> {
             Some(pair) => pair,
             None => return Ok(None),
         };
-        self.next_value = Some(value);
+        let span = key.0.start..key.0.end;
+        match self.defined.entry(key.1.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(span);
+            }
+            Entry::Occupied(entry) => {
+                let kind = ErrorKind::DuplicateKey(key.1.to_string());
+                return Err(Error::duplicate(entry.get().clone(), span, kind));
+            }
+        }
+        self.next_value = Some((key.clone(), value));
         seed.deserialize(StrDeserializer::spanned(key)).map(Some)
     }
 
@@ -1121,8 +2110,11 @@ impl<'de> de::MapAccess<'de> for InlineTableDeserializer<'de> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        let value = self.next_value.take().expect("Unable to read table values");
-        seed.deserialize(ValueDeserializer::new(value))
+        let (key, value) = self.next_value.take().expect("Unable to read table values");
+        let unused = &self.unused;
+        UnusedKeys::within(unused, &key, || {
+            seed.deserialize(ValueDeserializer::new(value).with_unused(unused.clone()))
+        })
     }
 }
 
@@ -1147,7 +2139,7 @@ impl<'de> de::EnumAccess<'de> for InlineTableDeserializer<'de> {
             }
         };
 
-        seed.deserialize(StrDeserializer::new(key.1))
+        seed.deserialize(StrDeserializer::spanned(key))
             .map(|val| (val, TableEnumDeserializer { value }))
     }
 }
@@ -1166,14 +2158,14 @@ impl<'de> de::VariantAccess<'de> for TableEnumDeserializer<'de> {
                 if values.is_empty() {
                     Ok(())
                 } else {
-                    Err(Error::from_kind(
-                        Some(self.value.start),
+                    Err(Error::from_kind_spanned(
+                        self.value.start..self.value.end,
                         ErrorKind::ExpectedEmptyTable,
                     ))
                 }
             }
-            e => Err(Error::from_kind(
-                Some(self.value.start),
+            e => Err(Error::from_kind_spanned(
+                self.value.start..self.value.end,
                 ErrorKind::Wanted {
                     expected: "table",
                     found: e.type_name(),
@@ -1201,7 +2193,7 @@ impl<'de> de::VariantAccess<'de> for TableEnumDeserializer<'de> {
                     .map(|(index, (key, value))| match key.1.parse::<usize>() {
                         Ok(key_index) if key_index == index => Ok(value),
                         Ok(_) | Err(_) => Err(Error::from_kind(
-                            Some(key.0.start),
+                            Some(key.0.start..key.0.end),
                             ErrorKind::ExpectedTupleIndex {
                                 expected: index,
                                 found: key.1.to_string(),
@@ -1230,14 +2222,14 @@ impl<'de> de::VariantAccess<'de> for TableEnumDeserializer<'de> {
                         visitor,
                     )
                 } else {
-                    Err(Error::from_kind(
-                        Some(self.value.start),
+                    Err(Error::from_kind_spanned(
+                        self.value.start..self.value.end,
                         ErrorKind::ExpectedTuple(len),
                     ))
                 }
             }
-            e => Err(Error::from_kind(
-                Some(self.value.start),
+            e => Err(Error::from_kind_spanned(
+                self.value.start..self.value.end,
                 ErrorKind::Wanted {
                     expected: "table",
                     found: e.type_name(),
@@ -1267,11 +2259,31 @@ impl<'a> Deserializer<'a> {
     /// Creates a new deserializer which will be deserializing the string
     /// provided.
     pub fn new(input: &'a str) -> Deserializer<'a> {
+        Deserializer::with_options(input, &Options::default())
+    }
+
+    /// Creates a new deserializer which will be deserializing the string
+    /// provided according to `options`.
+    pub fn with_options(input: &'a str, options: &Options) -> Deserializer<'a> {
+        let mut tokens = Tokenizer::with_spec(input, options.spec);
+        tokens.set_max_string_len(options.limits.max_string_len);
         Deserializer {
-            tokens: Tokenizer::new(input),
+            tokens,
             input,
-            require_newline_after_table: true,
-            allow_duplciate_after_longer_table: false,
+            spec: options.spec,
+            strict_datetimes: options.strict_datetimes,
+            unsigned_integers: options.unsigned_integers,
+            raw_numbers: options.preserve_number_format || cfg!(feature = "arbitrary_precision"),
+            arbitrary_precision: cfg!(feature = "arbitrary_precision"),
+            preserve_number_format: options.preserve_number_format,
+            require_newline_after_table: options.require_newline_after_table,
+            allow_duplciate_after_longer_table: options.allow_duplicate_after_longer_table,
+            duplicate_key_policy: options.duplicate_key_policy,
+            limits: options.limits,
+            depth: 0,
+            keys: 0,
+            errors: None,
+            unused: None,
         }
     }
 
@@ -1303,44 +2315,131 @@ impl<'a> Deserializer<'a> {
         self.allow_duplciate_after_longer_table = allow;
     }
 
+    /// Sets what to do with a key or table which is defined more than once
+    /// (the default is `DuplicateKeyPolicy::Error`).
+    ///
+    /// ```
+    /// use toml::de::{Deserializer, DuplicateKeyPolicy};
+    /// use serde::Deserialize;
+    ///
+    /// let input = "[server]\nport = 80\nhost = 'a'\n\n[server]\nport = 8080\n";
+    /// let mut de = Deserializer::new(input);
+    /// de.set_duplicate_key_policy(DuplicateKeyPolicy::MergeTables);
+    /// let value = toml::Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(value["server"]["port"].as_integer(), Some(8080));
+    /// assert_eq!(value["server"]["host"].as_str(), Some("a"));
+    /// ```
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.duplicate_key_policy = policy;
+    }
+
+    /// Sets limits on the input which is accepted, guarding against
+    /// documents crafted to exhaust the stack or memory of a program parsing
+    /// untrusted input. See `Limits`.
+    ///
+    /// Input going over a limit is rejected with `ErrorKind::LimitExceeded`,
+    /// pointing at where the limit was exceeded.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.tokens.set_max_string_len(limits.max_string_len);
+        self.limits = limits;
+    }
+
+    /// Sets a callback which is called for every key in the input that the
+    /// type being deserialized ignored, such as a misspelled field of a
+    /// struct that does not use `#[serde(deny_unknown_fields)]`.
+    ///
+    /// The callback receives the full path of the key, e.g. `["a", "b"]` for
+    /// `b` in table `[a]`, and the span of the key in the input. It is called
+    /// as the keys are encountered, so it may be called before an error
+    /// aborts deserialization. Only the table itself is reported when a whole
+    /// table is ignored, not the keys inside of it.
+    pub fn set_unused_key_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&[String], Range<usize>) + 'a,
+    {
+        self.unused = Some(Rc::new(UnusedKeys {
+            callback: RefCell::new(Box::new(callback)),
+            path: RefCell::new(Vec::new()),
+            ignoring: Cell::new(false),
+        }));
+    }
+
     fn tables(&mut self) -> Result<Vec<Table<'a>>, Error> {
+        self.check_input_len()?;
         let mut tables = Vec::new();
         let mut cur_table = Table {
             at: 0,
+            end: 0,
             header: Vec::new(),
             values: None,
             array: false,
         };
 
-        while let Some(line) = self.line()? {
+        // Set when recovering from a malformed table header, in which case
+        // the key/value pairs following it are dropped.
+        let mut skip_values = false;
+
+        loop {
+            // Key/value pairs are nested within the current table, even
+            // after an error left the depth of a broken statement behind.
+            self.depth = cur_table.header.len() + cur_table.array as usize;
+            let start = self.tokens.clone();
+            let line = match self.line() {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    self.recover(&start, e)?;
+                    continue;
+                }
+            };
             match line {
                 Line::Table {
                     at,
                     mut header,
                     array,
                 } => {
+                    let mut parts = Vec::new();
+                    let res = loop {
+                        match header.next() {
+                            Ok(Some(part)) => {
+                                let depth = parts.len() + 1 + array as usize;
+                                if let Err(e) = self.check_key(&part, depth) {
+                                    break Err(e);
+                                }
+                                parts.push(part);
+                            }
+                            Ok(None) => break self.count_key(at..header.end),
+                            Err(e) => break Err(self.token_error(e)),
+                        }
+                    };
+                    if let Err(e) = res {
+                        self.recover(&start, e)?;
+                        skip_values = true;
+                        continue;
+                    }
+                    skip_values = false;
                     if !cur_table.header.is_empty() || cur_table.values.is_some() {
                         tables.push(cur_table);
                     }
                     cur_table = Table {
                         at,
-                        header: Vec::new(),
+                        end: header.end,
+                        header: parts,
                         values: Some(Vec::new()),
                         array,
                     };
-                    loop {
-                        let part = header.next().map_err(|e| self.token_error(e));
-                        match part? {
-                            Some(part) => cur_table.header.push(part),
-                            None => break,
-                        }
-                    }
                 }
                 Line::KeyValue(key, value) => {
+                    if skip_values {
+                        continue;
+                    }
                     if cur_table.values.is_none() {
                         cur_table.values = Some(Vec::new());
                     }
-                    self.add_dotted_key(key, value, cur_table.values.as_mut().unwrap())?;
+                    let res = self.add_dotted_key(key, value, cur_table.values.as_mut().unwrap());
+                    if let Err(e) = res {
+                        self.recover(&start, e)?;
+                    }
                 }
             }
         }
@@ -1350,6 +2449,116 @@ impl<'a> Deserializer<'a> {
         Ok(tables)
     }
 
+    /// Visits the tables of a document as a map, checking for duplicate keys
+    /// and tables as it goes.
+    fn visit_tables<V>(&mut self, mut tables: Vec<Table<'a>>, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'a>,
+    {
+        let last = tables.last().map(Table::span);
+        if self.duplicate_key_policy != DuplicateKeyPolicy::Error {
+            tables = vec![self.merge_tables(tables)];
+        }
+        let table_indices = build_table_indices(&tables);
+        let table_pindices = build_table_pindices(&tables);
+
+        let res = visitor.visit_map(MapVisitor {
+            values: Vec::new().into_iter().peekable(),
+            next_value: None,
+            depth: 0,
+            cur: 0,
+            cur_parent: 0,
+            max: tables.len(),
+            table_indices: &table_indices,
+            table_pindices: &table_pindices,
+            tables: &mut tables,
+            array: false,
+            defined: HashMap::new(),
+            de: self,
+        });
+        res.map_err(|mut err| {
+            // Errors originating from this library (toml), have an offset
+            // attached to them already. Other errors, like those originating
+            // from serde (like "missing field") or from a custom deserializer,
+            // do not have offsets on them. Here, we do a best guess at their
+            // location, by attributing them to the "current table" (the last
+            // item in `tables`).
+            err.fix_span(|| last);
+            self.fix_error(&mut err);
+            err
+        })
+    }
+
+    /// Handles an error encountered while reading the statement which started
+    /// where the tokenizer `start` is.
+    ///
+    /// Unless errors are being collected the error is returned as is.
+    /// Otherwise it is recorded and the tokenizer is moved past the line the
+    /// error occurred on, along with any lines continuing that broken
+    /// statement, so that parsing can resume at the next key or header.
+    fn recover(&mut self, start: &Tokenizer<'a>, err: Error) -> Result<(), Error> {
+        let errors = match self.errors {
+            Some(ref mut errors) => errors,
+            None => return Err(err),
+        };
+        let mut tokens = start.clone();
+        let start = tokens.current();
+        let cur = self.tokens.current();
+        let at = err.offset().unwrap_or(cur).min(cur);
+        errors.push(err);
+
+        // A statement which runs onto following lines, such as an unclosed
+        // array, may fail on a line which actually starts a new statement.
+        // Parsing resumes at that line rather than skipping it.
+        let line_start = self.input[..at].rfind('\n').map_or(0, |i| i + 1);
+        if line_start > start && self.input[line_start..at].trim().is_empty() {
+            // Only the statement itself is skipped over again to get there.
+            while tokens.current() < line_start {
+                tokens.one();
+            }
+            let prev = mem::replace(&mut self.tokens, tokens);
+            if self.at_statement_start() {
+                return Ok(());
+            }
+            self.tokens = prev;
+        }
+
+        // The statement may have already consumed the newline ending the
+        // erroneous line, in which case we're at the start of the next one.
+        if !(self.input[at..cur].contains('\n') && self.input[..cur].ends_with('\n')) {
+            self.tokens.skip_to_newline();
+        }
+        while self.tokens.current() < self.input.len() && !self.at_statement_start() {
+            self.tokens.skip_to_newline();
+        }
+        Ok(())
+    }
+
+    /// Returns whether the current line looks like the start of a statement,
+    /// as opposed to the continuation of a malformed multi-line value.
+    fn at_statement_start(&self) -> bool {
+        let mut tokens = self.tokens.clone();
+        if tokens.eat_whitespace().is_err() {
+            return true;
+        }
+        match tokens.next() {
+            Ok(Some((_, Token::Keylike(_)))) | Ok(Some((_, Token::String { .. }))) => {}
+            Ok(Some((_, Token::Newline)))
+            | Ok(Some((_, Token::Comment(_))))
+            | Ok(Some((_, Token::LeftBracket)))
+            | Ok(None)
+            | Err(_) => return true,
+            Ok(Some(_)) => return false,
+        }
+        if tokens.eat_whitespace().is_err() {
+            return true;
+        }
+        matches!(
+            tokens.next(),
+            Ok(Some((_, Token::Equals))) | Ok(Some((_, Token::Period))) | Err(_)
+        )
+    }
+
     fn line(&mut self) -> Result<Option<Line<'a>>, Error> {
         loop {
             self.eat_whitespace()?;
@@ -1404,7 +2613,10 @@ impl<'a> Deserializer<'a> {
         self.expect(Token::Equals)?;
         self.eat_whitespace()?;
 
+        let depth = self.depth;
+        self.depth += key.len();
         let value = self.value()?;
+        self.depth = depth;
         self.eat_whitespace()?;
         if !self.eat_comment()? {
             self.eat_newline_or_eof()?;
@@ -1413,55 +2625,155 @@ impl<'a> Deserializer<'a> {
         Ok(Line::KeyValue(key, value))
     }
 
+    /// Parses a value, along with any arrays and inline tables nested in it.
+    ///
+    /// Arrays and inline tables are kept on an explicit stack while their
+    /// contents are parsed, rather than parsed recursively, so that no depth
+    /// of nesting can overflow the stack.
     fn value(&mut self) -> Result<Value<'a>, Error> {
-        let at = self.tokens.current();
-        let value = match self.next()? {
-            Some((Span { start, end }, Token::String { val, .. })) => Value {
-                e: E::String(val),
-                start,
-                end,
-            },
-            Some((Span { start, end }, Token::Keylike("true"))) => Value {
-                e: E::Boolean(true),
-                start,
-                end,
-            },
-            Some((Span { start, end }, Token::Keylike("false"))) => Value {
-                e: E::Boolean(false),
-                start,
-                end,
-            },
-            Some((span, Token::Keylike(key))) => self.parse_keylike(at, span, key)?,
-            Some((span, Token::Plus)) => self.number_leading_plus(span)?,
-            Some((Span { start, .. }, Token::LeftBrace)) => {
-                self.inline_table().map(|(Span { end, .. }, table)| Value {
-                    e: E::InlineTable(table),
+        let mut stack = Vec::new();
+        loop {
+            let mut value = match self.value_start(&mut stack)? {
+                Some(value) => value,
+                None => continue,
+            };
+            // Add the value to the array or inline table it is in, which may
+            // complete that one too.
+            loop {
+                let end = match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(Nested::Array { values, .. }) => self.array_value(values, value)?,
+                    Some(Nested::InlineTable {
+                        pairs, key, depth, ..
+                    }) => {
+                        self.depth = *depth;
+                        self.inline_table_value(pairs, key, value)?
+                    }
+                };
+                let Span { end, .. } = match end {
+                    Some(span) => span,
+                    None => break,
+                };
+                value = match stack.pop().expect("value is nested") {
+                    Nested::Array { start, values } => {
+                        self.depth -= 1;
+                        Value {
+                            e: E::Array(values),
+                            start,
+                            end,
+                        }
+                    }
+                    Nested::InlineTable { start, pairs, .. } => Value {
+                        e: E::InlineTable(pairs),
+                        start,
+                        end,
+                    },
+                };
+            }
+        }
+    }
+
+    /// Parses a value which is not an array or an inline table, returning it
+    /// both as read and as a `Scalar`.
+    pub(crate) fn scalar(&mut self) -> Result<(Value<'a>, Scalar<'a>), Error> {
+        let value = self.value()?;
+        let scalar = match value.e {
+            E::String(ref s) => Scalar::String(s.clone()),
+            E::Integer(i) => Scalar::Integer(i),
+            E::UInteger(i) => Scalar::UInteger(i),
+            E::Float(f) => Scalar::Float(f),
+            E::Boolean(b) => Scalar::Boolean(b),
+            E::Datetime(s) => Scalar::Datetime(
+                datetime::parse(s, false, self.spec)
+                    .map_err(|e| Error::custom(Some(value.start..value.end), e.to_string()))?,
+            ),
+            E::Number(..) | E::Array(_) | E::InlineTable(_) | E::DottedTable(_) => {
+                return Err(self.error(
+                    value.start..value.end,
+                    ErrorKind::Wanted {
+                        expected: "a scalar",
+                        found: value.e.type_name(),
+                    },
+                ));
+            }
+        };
+        Ok((value, scalar))
+    }
+
+    /// Parses a scalar, or the start of an array or inline table, which is
+    /// pushed onto `stack` unless it is empty.
+    fn value_start(&mut self, stack: &mut Vec<Nested<'a>>) -> Result<Option<Value<'a>>, Error> {
+        let value = match self.next()? {
+            Some((Span { start, end }, Token::String { val, .. })) => Value {
+                e: E::String(val),
+                start,
+                end,
+            },
+            Some((Span { start, end }, Token::Keylike("true"))) => Value {
+                e: E::Boolean(true),
+                start,
+                end,
+            },
+            Some((Span { start, end }, Token::Keylike("false"))) => Value {
+                e: E::Boolean(false),
+                start,
+                end,
+            },
+            Some((span, Token::Keylike(key))) => self.parse_keylike(span, key)?,
+            Some((span, Token::Plus)) => self.number_leading_plus(span)?,
+            Some((Span { start, .. }, Token::LeftBrace)) => {
+                self.eat_inline_table_whitespace()?;
+                if let Some(Span { end, .. }) = self.eat_spanned(Token::RightBrace)? {
+                    return Ok(Some(Value {
+                        e: E::InlineTable(Vec::new()),
+                        start,
+                        end,
+                    }));
+                }
+                let depth = self.depth;
+                let key = self.inline_table_key()?;
+                stack.push(Nested::InlineTable {
                     start,
-                    end,
-                })?
+                    pairs: Vec::new(),
+                    key,
+                    depth,
+                });
+                return Ok(None);
             }
             Some((Span { start, .. }, Token::LeftBracket)) => {
-                self.array().map(|(Span { end, .. }, array)| Value {
-                    e: E::Array(array),
+                self.depth += 1;
+                let span = start..start + 1;
+                self.check_limit(span, "max_depth", self.limits.max_depth, self.depth)?;
+                self.eat_array_whitespace()?;
+                if let Some(Span { end, .. }) = self.eat_spanned(Token::RightBracket)? {
+                    self.depth -= 1;
+                    return Ok(Some(Value {
+                        e: E::Array(Vec::new()),
+                        start,
+                        end,
+                    }));
+                }
+                stack.push(Nested::Array {
                     start,
-                    end,
-                })?
+                    values: Vec::new(),
+                });
+                return Ok(None);
             }
-            Some(token) => {
+            Some((Span { start, end }, token)) => {
                 return Err(self.error(
-                    at,
+                    start..end,
                     ErrorKind::Wanted {
                         expected: "a value",
-                        found: token.1.describe(),
+                        found: token.describe(),
                     },
                 ));
             }
             None => return Err(self.eof()),
         };
-        Ok(value)
+        Ok(Some(value))
     }
 
-    fn parse_keylike(&mut self, at: usize, span: Span, key: &'a str) -> Result<Value<'a>, Error> {
+    fn parse_keylike(&mut self, span: Span, key: &'a str) -> Result<Value<'a>, Error> {
         if key == "inf" || key == "nan" {
             return self.number_or_date(span, key);
         }
@@ -1469,7 +2781,7 @@ impl<'a> Deserializer<'a> {
         let first_char = key.chars().next().expect("key should not be empty here");
         match first_char {
             '-' | '0'..='9' => self.number_or_date(span, key),
-            _ => Err(self.error(at, ErrorKind::UnquotedString)),
+            _ => Err(self.error(span.start..span.end, ErrorKind::UnquotedString)),
         }
     }
 
@@ -1501,12 +2813,13 @@ impl<'a> Deserializer<'a> {
     /// Used to deserialize enums. Unit enums may be represented as a string or a table, all other
     /// structures (tuple, newtype, struct) must be represented as a table.
     fn string_or_table(&mut self) -> Result<(Value<'a>, Option<Cow<'a, str>>), Error> {
+        self.check_input_len()?;
         match self.peek()? {
             Some((span, Token::LeftBracket)) => {
                 let tables = self.tables()?;
                 if tables.len() != 1 {
                     return Err(Error::from_kind(
-                        Some(span.start),
+                        Some(span.start..span.end),
                         ErrorKind::Wanted {
                             expected: "exactly 1 table",
                             found: if tables.is_empty() {
@@ -1548,12 +2861,18 @@ impl<'a> Deserializer<'a> {
         }
     }
 
-    fn number(&mut self, Span { start, end }: Span, s: &'a str) -> Result<Value<'a>, Error> {
-        let to_integer = |f| Value {
-            e: E::Integer(f),
-            start,
-            end,
-        };
+    fn number(&mut self, span: Span, s: &'a str) -> Result<Value<'a>, Error> {
+        let mut value = self.typed_number(span, s)?;
+        if self.raw_numbers {
+            let s = &self.input[value.start..value.end];
+            let keep = self.preserve_number_format || !number::is_exact(s, self.unsigned_integers);
+            value.e = E::Number(s, keep);
+        }
+        Ok(value)
+    }
+
+    fn typed_number(&mut self, Span { start, end }: Span, s: &'a str) -> Result<Value<'a>, Error> {
+        let to_integer = |e| Value { e, start, end };
         if s.starts_with("0x") {
             self.integer(&s[2..], 16).map(to_integer)
         } else if s.starts_with("0o") {
@@ -1561,22 +2880,25 @@ impl<'a> Deserializer<'a> {
         } else if s.starts_with("0b") {
             self.integer(&s[2..], 2).map(to_integer)
         } else if s.contains('e') || s.contains('E') {
-            self.float(s, None).map(|f| Value {
+            let f = self.float(s, None)?;
+            Ok(Value {
                 e: E::Float(f),
                 start,
-                end,
+                end: self.tokens.current(),
             })
         } else if self.eat(Token::Period)? {
+            // Missing digits are reported where they should have been.
             let at = self.tokens.current();
-            match self.next()? {
-                Some((Span { start, end }, Token::Keylike(after))) => {
-                    self.float(s, Some(after)).map(|f| Value {
+            match self.next_keylike()? {
+                Some((_, after)) => {
+                    let f = self.float(s, Some(after))?;
+                    Ok(Value {
                         e: E::Float(f),
                         start,
-                        end,
+                        end: self.tokens.current(),
                     })
                 }
-                _ => Err(self.error(at, ErrorKind::NumberInvalid)),
+                None => Err(self.error(at..at, ErrorKind::NumberInvalid)),
             }
         } else if s == "inf" {
             Ok(Value {
@@ -1608,23 +2930,36 @@ impl<'a> Deserializer<'a> {
     }
 
     fn number_leading_plus(&mut self, Span { start, .. }: Span) -> Result<Value<'a>, Error> {
-        let start_token = self.tokens.current();
-        match self.next()? {
-            Some((Span { end, .. }, Token::Keylike(s))) => self.number(Span { start, end }, s),
-            _ => Err(self.error(start_token, ErrorKind::NumberInvalid)),
+        match self.next_keylike()? {
+            Some((Span { end, .. }, s)) => self.number(Span { start, end }, s),
+            None => Err(self.read_error(start, ErrorKind::NumberInvalid)),
         }
     }
 
-    fn integer(&self, s: &'a str, radix: u32) -> Result<i64, Error> {
+    fn integer(&self, s: &'a str, radix: u32) -> Result<E<'a>, Error> {
         let allow_sign = radix == 10;
         let allow_leading_zeros = radix != 10;
         let (prefix, suffix) = self.parse_integer(s, allow_sign, allow_leading_zeros, radix)?;
         let start = self.tokens.substr_offset(s);
         if suffix != "" {
-            return Err(self.error(start, ErrorKind::NumberInvalid));
+            return Err(self.read_error(start, ErrorKind::NumberInvalid));
+        }
+        if self.arbitrary_precision {
+            // The digits are valid, and integers of any size are kept as
+            // text by `number`.
+            return Ok(E::Number(s, true));
+        }
+        let digits = prefix.replace("_", "");
+        let digits = digits.trim_start_matches('+');
+        match i64::from_str_radix(digits, radix) {
+            Ok(i) => Ok(E::Integer(i)),
+            Err(_) if self.unsigned_integers && !digits.starts_with('-') => {
+                u64::from_str_radix(digits, radix)
+                    .map(E::UInteger)
+                    .map_err(|_e| self.read_error(start, ErrorKind::NumberInvalid))
+            }
+            Err(_) => Err(self.read_error(start, ErrorKind::NumberInvalid)),
         }
-        i64::from_str_radix(&prefix.replace("_", "").trim_start_matches('+'), radix)
-            .map_err(|_e| self.error(start, ErrorKind::NumberInvalid))
     }
 
     fn parse_integer(
@@ -1650,11 +2985,11 @@ impl<'a> Deserializer<'a> {
                 first_zero = true;
             } else if c.is_digit(radix) {
                 if !first && first_zero && !allow_leading_zeros {
-                    return Err(self.error(at, ErrorKind::NumberInvalid));
+                    return Err(self.read_error(at, ErrorKind::NumberInvalid));
                 }
                 underscore = false;
             } else if c == '_' && first {
-                return Err(self.error(at, ErrorKind::NumberInvalid));
+                return Err(self.read_error(at, ErrorKind::NumberInvalid));
             } else if c == '_' && !underscore {
                 underscore = true;
             } else {
@@ -1664,7 +2999,7 @@ impl<'a> Deserializer<'a> {
             first = false;
         }
         if first || underscore {
-            return Err(self.error(start, ErrorKind::NumberInvalid));
+            return Err(self.read_error(start, ErrorKind::NumberInvalid));
         }
         Ok((&s[..end], &s[end..]))
     }
@@ -1676,7 +3011,7 @@ impl<'a> Deserializer<'a> {
         let mut fraction = None;
         if let Some(after) = after_decimal {
             if suffix != "" {
-                return Err(self.error(start, ErrorKind::NumberInvalid));
+                return Err(self.read_error(start, ErrorKind::NumberInvalid));
             }
             let (a, b) = self.parse_integer(after, false, true, 10)?;
             fraction = Some(a);
@@ -1687,19 +3022,19 @@ impl<'a> Deserializer<'a> {
         if suffix.starts_with('e') || suffix.starts_with('E') {
             let (a, b) = if suffix.len() == 1 {
                 self.eat(Token::Plus)?;
-                match self.next()? {
-                    Some((_, Token::Keylike(s))) => self.parse_integer(s, false, true, 10)?,
-                    _ => return Err(self.error(start, ErrorKind::NumberInvalid)),
+                match self.next_keylike()? {
+                    Some((_, s)) => self.parse_integer(s, false, true, 10)?,
+                    None => return Err(self.read_error(start, ErrorKind::NumberInvalid)),
                 }
             } else {
                 self.parse_integer(&suffix[1..], true, true, 10)?
             };
             if b != "" {
-                return Err(self.error(start, ErrorKind::NumberInvalid));
+                return Err(self.read_error(start, ErrorKind::NumberInvalid));
             }
             exponent = Some(a);
         } else if !suffix.is_empty() {
-            return Err(self.error(start, ErrorKind::NumberInvalid));
+            return Err(self.read_error(start, ErrorKind::NumberInvalid));
         }
 
         let mut number = integral
@@ -1717,12 +3052,14 @@ impl<'a> Deserializer<'a> {
         }
         number
             .parse()
-            .map_err(|_e| self.error(start, ErrorKind::NumberInvalid))
+            .map_err(|_e| self.read_error(start, ErrorKind::NumberInvalid))
             .and_then(|n: f64| {
-                if n.is_finite() {
+                // Floats of any size are kept as text by `number`, like
+                // integers are.
+                if n.is_finite() || self.arbitrary_precision {
                     Ok(n)
                 } else {
-                    Err(self.error(start, ErrorKind::NumberInvalid))
+                    Err(self.read_error(start, ErrorKind::NumberInvalid))
                 }
             })
     }
@@ -1747,326 +3084,1222 @@ impl<'a> Deserializer<'a> {
 
         if colon_eaten || self.eat(Token::Colon)? {
             // minutes
-            match self.next()? {
-                Some((_, Token::Keylike(_))) => {}
-                _ => return Err(self.error(start, ErrorKind::DateInvalid)),
-            }
-            // Seconds
-            self.expect(Token::Colon)?;
-            match self.next()? {
-                Some((Span { end, .. }, Token::Keylike(_))) => {
-                    span.end = end;
+            match self.next_keylike()? {
+                Some(_) => {}
+                None => return Err(self.read_error(start, ErrorKind::DateInvalid)),
+            }
+            // Seconds, which are optional as of TOML 1.1
+            if self.spec < TomlVersion::V1_1 || self.peek_colon()? {
+                self.expect(Token::Colon)?;
+                match self.next_keylike()? {
+                    Some((Span { end, .. }, _)) => {
+                        span.end = end;
+                    }
+                    None => return Err(self.read_error(start, ErrorKind::DateInvalid)),
                 }
-                _ => return Err(self.error(start, ErrorKind::DateInvalid)),
             }
             // Fractional seconds
             if self.eat(Token::Period)? {
-                match self.next()? {
-                    Some((Span { end, .. }, Token::Keylike(_))) => {
+                match self.next_keylike()? {
+                    Some((Span { end, .. }, _)) => {
                         span.end = end;
                     }
-                    _ => return Err(self.error(start, ErrorKind::DateInvalid)),
+                    None => return Err(self.read_error(start, ErrorKind::DateInvalid)),
                 }
             }
 
             // offset
             if self.eat(Token::Plus)? {
-                match self.next()? {
-                    Some((Span { end, .. }, Token::Keylike(_))) => {
+                match self.next_keylike()? {
+                    Some((Span { end, .. }, _)) => {
                         span.end = end;
                     }
-                    _ => return Err(self.error(start, ErrorKind::DateInvalid)),
+                    None => return Err(self.read_error(start, ErrorKind::DateInvalid)),
+                }
+            }
+            if self.eat(Token::Colon)? {
+                match self.next_keylike()? {
+                    Some((Span { end, .. }, _)) => {
+                        span.end = end;
+                    }
+                    None => return Err(self.read_error(start, ErrorKind::DateInvalid)),
+                }
+            }
+        }
+
+        let end = self.tokens.current();
+        let date = &self.tokens.input()[start..end];
+        // Tokens following the minutes may also be the offset's, so check
+        // the seconds weren't left out by a version that requires them.
+        if self.spec < TomlVersion::V1_1 && !time_has_seconds(date) {
+            return Err(self.read_error(start, ErrorKind::DateInvalid));
+        }
+        // Impossible datetimes are reported here, while malformed ones are
+        // reported when they're deserialized.
+        if let Some(reason) = datetime::parse(date, self.strict_datetimes, self.spec)
+            .err()
+            .and_then(|e| e.reason())
+        {
+            let mut err = self.read_error(start, ErrorKind::DateInvalid);
+            err.inner.message = reason.to_string();
+            return Err(err);
+        }
+        Ok((span, date))
+    }
+
+    /// Parses a key of an inline table up to its value, which is then parsed
+    /// at the depth of the key.
+    fn inline_table_key(&mut self) -> Result<Vec<(Span, Cow<'a, str>)>, Error> {
+        let key = self.dotted_key()?;
+        self.eat_whitespace()?;
+        self.expect(Token::Equals)?;
+        self.eat_whitespace()?;
+        self.depth += key.len();
+        Ok(key)
+    }
+
+    /// Adds the value of `key` to an inline table, and parses up to the
+    /// next key. Returns the span of the closing brace if the table ends.
+    // TODO(#140): shouldn't buffer up this entire table in memory, it'd be
+    // great to defer parsing everything until later.
+    fn inline_table_value(
+        &mut self,
+        pairs: &mut Vec<TablePair<'a>>,
+        key: &mut Vec<(Span, Cow<'a, str>)>,
+        value: Value<'a>,
+    ) -> Result<Option<Span>, Error> {
+        self.add_dotted_key(mem::take(key), value, pairs)?;
+        self.eat_inline_table_whitespace()?;
+        if let Some(span) = self.eat_spanned(Token::RightBrace)? {
+            return Ok(Some(span));
+        }
+        self.expect(Token::Comma)?;
+        self.eat_inline_table_whitespace()?;
+        if self.spec >= TomlVersion::V1_1 {
+            if let Some(span) = self.eat_spanned(Token::RightBrace)? {
+                return Ok(Some(span));
+            }
+        }
+        *key = self.inline_table_key()?;
+        Ok(None)
+    }
+
+    /// Skips the whitespace between the entries of an inline table, which
+    /// as of TOML 1.1 includes newlines and comments.
+    pub(crate) fn eat_inline_table_whitespace(&mut self) -> Result<(), Error> {
+        self.eat_whitespace()?;
+        if self.spec >= TomlVersion::V1_1 {
+            while self.eat(Token::Newline)? || self.eat_comment()? {
+                self.eat_whitespace()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a value to an array, and parses up to the next value. Returns the
+    /// span of the closing bracket if the array ends.
+    // TODO(#140): shouldn't buffer up this entire array in memory, it'd be
+    // great to defer parsing everything until later.
+    fn array_value(
+        &mut self,
+        values: &mut Vec<Value<'a>>,
+        value: Value<'a>,
+    ) -> Result<Option<Span>, Error> {
+        let max = self.limits.max_array_len;
+        let span = value.start..value.end;
+        self.check_limit(span.clone(), "max_array_len", max, values.len() + 1)?;
+        if self.spec < TomlVersion::V1_0 {
+            if let Some(first) = values.first() {
+                if value.e.type_name() != first.e.type_name() {
+                    return Err(self.error(span, ErrorKind::MixedArrayType));
+                }
+            }
+        }
+        values.push(value);
+        self.eat_array_whitespace()?;
+        if self.eat(Token::Comma)? {
+            self.eat_array_whitespace()?;
+            self.eat_spanned(Token::RightBracket)
+        } else {
+            self.expect_spanned(Token::RightBracket).map(Some)
+        }
+    }
+
+    /// Skips the whitespace, newlines and comments between the values of an
+    /// array.
+    fn eat_array_whitespace(&mut self) -> Result<(), Error> {
+        loop {
+            self.eat_whitespace()?;
+            if !self.eat(Token::Newline)? && !self.eat_comment()? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn table_key(&mut self) -> Result<(Span, Cow<'a, str>), Error> {
+        self.tokens.table_key().map_err(|e| self.token_error(e))
+    }
+
+    pub(crate) fn dotted_key(&mut self) -> Result<Vec<(Span, Cow<'a, str>)>, Error> {
+        let mut result = Vec::new();
+        let key = self.table_key()?;
+        self.count_key(key.0.start..key.0.end)?;
+        self.check_key(&key, self.depth + 1)?;
+        result.push(key);
+        self.eat_whitespace()?;
+        while self.eat(Token::Period)? {
+            self.eat_whitespace()?;
+            let key = self.table_key()?;
+            self.check_key(&key, self.depth + result.len() + 1)?;
+            result.push(key);
+            self.eat_whitespace()?;
+        }
+        Ok(result)
+    }
+
+    /// Counts a key/value pair or table header at `span` towards
+    /// `Limits::max_keys`.
+    fn count_key(&mut self, span: Range<usize>) -> Result<(), Error> {
+        self.keys += 1;
+        self.check_limit(span, "max_keys", self.limits.max_keys, self.keys)
+    }
+
+    /// Returns the version of the specification the input is parsed as.
+    pub(crate) fn spec(&self) -> TomlVersion {
+        self.spec
+    }
+
+    /// Returns the depth of the value being parsed.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Moves to a value at a depth of `depth`, checking it against
+    /// `Limits::max_depth` and reporting `span` if it is too deep.
+    pub(crate) fn set_depth(&mut self, span: Range<usize>, depth: usize) -> Result<(), Error> {
+        self.check_limit(span, "max_depth", self.limits.max_depth, depth)?;
+        self.depth = depth;
+        Ok(())
+    }
+
+    /// Checks a key found at a depth of `depth` against the limits.
+    fn check_key(&self, key: &(Span, Cow<'a, str>), depth: usize) -> Result<(), Error> {
+        let span = key.0.start..key.0.end;
+        self.check_limit(
+            span.clone(),
+            "max_string_len",
+            self.limits.max_string_len,
+            key.1.len(),
+        )?;
+        self.check_limit(span, "max_depth", self.limits.max_depth, depth)
+    }
+
+    fn check_input_len(&self) -> Result<(), Error> {
+        let max = self.limits.max_input_len;
+        if self.input.len() <= max {
+            return Ok(());
+        }
+        let mut at = max;
+        while !self.input.is_char_boundary(at) {
+            at -= 1;
+        }
+        self.check_limit(at..self.input.len(), "max_input_len", max, self.input.len())
+    }
+
+    /// Returns an error at `span` if `len` is over the limit `limit`.
+    fn check_limit(
+        &self,
+        span: Range<usize>,
+        limit: &'static str,
+        max: usize,
+        len: usize,
+    ) -> Result<(), Error> {
+        if len > max {
+            Err(self.error(span, ErrorKind::LimitExceeded { limit, max }))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stores a value in the appropriate hierarchical structure positioned based on the dotted key.
+    ///
+    /// Given the following definition: `multi.part.key = "value"`, `multi` and `part` are
+    /// intermediate parts which are mapped to the relevant fields in the deserialized type's data
+    /// hierarchy.
+    ///
+    /// # Parameters
+    ///
+    /// * `key_parts`: Each segment of the dotted key, e.g. `part.one` maps to
+    ///                `vec![Cow::Borrowed("part"), Cow::Borrowed("one")].`
+    /// * `value`: The parsed value.
+    /// * `values`: The `Vec` to store the value in.
+    pub(crate) fn add_dotted_key(
+        &self,
+        mut key_parts: Vec<(Span, Cow<'a, str>)>,
+        value: Value<'a>,
+        mut values: &mut Vec<TablePair<'a>>,
+    ) -> Result<(), Error> {
+        let mut key = key_parts.pop().expect("dotted keys have a part");
+        if self.duplicate_key_policy != DuplicateKeyPolicy::Error {
+            // Whatever the policy, a table defined inline can't be extended.
+            let mut pairs = &*values;
+            for part in &key_parts {
+                let i = match pairs.iter().rposition(|(k, _)| k.1 == part.1) {
+                    Some(i) => i,
+                    None => break,
+                };
+                pairs = match pairs[i].1.e {
+                    E::DottedTable(ref v) => v,
+                    E::InlineTable(_) => return Err(self.dotted_key_invalid(pairs[i].0 .0, part.0)),
+                    _ => break,
+                };
+            }
+            // Nest the value in a dotted table for each intermediate part,
+            // which is then merged with any existing table.
+            let mut value = value;
+            while let Some(part) = key_parts.pop() {
+                let (start, end) = (value.start, value.end);
+                value = Value {
+                    e: E::DottedTable(vec![(key, value)]),
+                    start,
+                    end,
+                };
+                key = part;
+            }
+            self.merge_pair(values, key, value);
+            return Ok(());
+        }
+        for part in key_parts {
+            let span = part.0;
+            let i = match values.iter().position(|(k, _)| *k.1 == part.1) {
+                Some(i) => i,
+                None => {
+                    // The start/end value is somewhat misleading here.
+                    let table_values = Value {
+                        e: E::DottedTable(Vec::new()),
+                        start: value.start,
+                        end: value.end,
+                    };
+                    values.push((part, table_values));
+                    values.len() - 1
+                }
+            };
+            let original = values[i].0 .0;
+            values = match values[i].1 {
+                Value {
+                    e: E::DottedTable(ref mut v),
+                    ..
+                } => v,
+                _ => return Err(self.dotted_key_invalid(original, span)),
+            };
+        }
+        values.push((key, value));
+        Ok(())
+    }
+
+    /// Creates an error for a part of a dotted key at `part` naming the key
+    /// at `original`, whose value isn't a table the dotted key can extend.
+    fn dotted_key_invalid(&self, original: Span, part: Span) -> Error {
+        self.duplicate(
+            original.start..original.end,
+            part.start..part.end,
+            ErrorKind::DottedKeyInvalidType,
+        )
+    }
+
+    /// Adds a key/value pair to `pairs`, resolving a key which is already
+    /// defined there according to the duplicate key policy.
+    fn merge_pair(
+        &self,
+        pairs: &mut Vec<TablePair<'a>>,
+        key: (Span, Cow<'a, str>),
+        value: Value<'a>,
+    ) {
+        let i = match pairs.iter().rposition(|(k, _)| k.1 == key.1) {
+            Some(i) => i,
+            None => return pairs.push((key, value)),
+        };
+        let old = &mut pairs[i].1;
+        // Dotted tables are those implied by dotted keys and headers, which
+        // are merged with any table. Tables defined by a header or inline
+        // table are inline tables here.
+        let merge = match (&old.e, &value.e) {
+            (&E::DottedTable(_), &E::DottedTable(_))
+            | (&E::DottedTable(_), &E::InlineTable(_))
+            | (&E::InlineTable(_), &E::DottedTable(_)) => true,
+            (&E::InlineTable(_), &E::InlineTable(_)) => {
+                self.duplicate_key_policy == DuplicateKeyPolicy::MergeTables
+            }
+            _ => false,
+        };
+        if !merge {
+            if self.duplicate_key_policy != DuplicateKeyPolicy::FirstWins {
+                pairs[i] = (key, value);
+            }
+            return;
+        }
+        let (new, defined) = match value.e {
+            E::InlineTable(new) => (new, true),
+            E::DottedTable(new) => (new, false),
+            _ => unreachable!(),
+        };
+        if let E::DottedTable(ref mut old_pairs) = old.e {
+            if defined {
+                let old_pairs = mem::take(old_pairs);
+                old.e = E::InlineTable(old_pairs);
+            }
+        }
+        if let Some(old_pairs) = table_pairs(old) {
+            for (key, value) in new {
+                self.merge_pair(old_pairs, key, value);
+            }
+        }
+    }
+
+    /// Merges the tables of a document into a single table, resolving keys
+    /// and tables which are defined more than once according to the
+    /// duplicate key policy.
+    fn merge_tables(&self, tables: Vec<Table<'a>>) -> Table<'a> {
+        let mut root = Vec::new();
+        for table in tables {
+            self.merge_table(&mut root, table);
+        }
+        Table {
+            at: 0,
+            end: 0,
+            header: Vec::new(),
+            values: Some(root),
+            array: false,
+        }
+    }
+
+    fn merge_table(&self, root: &mut Vec<TablePair<'a>>, table: Table<'a>) {
+        let mut header = table.header;
+        let values = table.values.unwrap_or_default();
+        let last = match header.pop() {
+            Some(last) => last,
+            None => {
+                for (key, value) in values {
+                    self.merge_pair(root, key, value);
+                }
+                return;
+            }
+        };
+
+        // Walk down to the table the header names, creating dotted tables
+        // for any parts which aren't defined yet and going into the last
+        // table of arrays of tables.
+        let mut pairs = root;
+        for part in header {
+            let i = match pairs.iter().rposition(|(k, _)| k.1 == part.1) {
+                Some(i) => i,
+                None => {
+                    pairs.push((part.clone(), Value::dotted_table(part.0)));
+                    pairs.len() - 1
+                }
+            };
+            if table_pairs(&mut pairs[i].1).is_none() {
+                if self.duplicate_key_policy == DuplicateKeyPolicy::FirstWins {
+                    return;
+                }
+                pairs[i] = (part.clone(), Value::dotted_table(part.0));
+            }
+            pairs = table_pairs(&mut pairs[i].1).expect("table was just checked");
+        }
+
+        let value = Value {
+            e: E::InlineTable(values),
+            start: table.at,
+            end: last.0.end,
+        };
+        if !table.array {
+            return self.merge_pair(pairs, last, value);
+        }
+        if let Some(i) = pairs.iter().rposition(|(k, _)| k.1 == last.1) {
+            if let E::Array(ref mut tables) = pairs[i].1.e {
+                if tables.iter().all(Value::is_table) {
+                    tables.push(value);
+                    return;
+                }
+            }
+        }
+        let (start, end) = (value.start, value.end);
+        let value = Value {
+            e: E::Array(vec![value]),
+            start,
+            end,
+        };
+        self.merge_pair(pairs, last, value);
+    }
+
+    pub(crate) fn eat_whitespace(&mut self) -> Result<(), Error> {
+        self.tokens
+            .eat_whitespace()
+            .map_err(|e| self.token_error(e))
+    }
+
+    fn eat_comment(&mut self) -> Result<bool, Error> {
+        self.tokens.eat_comment().map_err(|e| self.token_error(e))
+    }
+
+    pub(crate) fn eat_newline_or_eof(&mut self) -> Result<(), Error> {
+        self.tokens
+            .eat_newline_or_eof()
+            .map_err(|e| self.token_error(e))
+    }
+
+    pub(crate) fn eat(&mut self, expected: Token<'a>) -> Result<bool, Error> {
+        self.tokens.eat(expected).map_err(|e| self.token_error(e))
+    }
+
+    fn eat_spanned(&mut self, expected: Token<'a>) -> Result<Option<Span>, Error> {
+        self.tokens
+            .eat_spanned(expected)
+            .map_err(|e| self.token_error(e))
+    }
+
+    pub(crate) fn expect(&mut self, expected: Token<'a>) -> Result<(), Error> {
+        self.tokens
+            .expect(expected)
+            .map_err(|e| self.token_error(e))
+    }
+
+    fn expect_spanned(&mut self, expected: Token<'a>) -> Result<Span, Error> {
+        self.tokens
+            .expect_spanned(expected)
+            .map_err(|e| self.token_error(e))
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Option<(Span, Token<'a>)>, Error> {
+        self.tokens.next().map_err(|e| self.token_error(e))
+    }
+
+    pub(crate) fn peek(&mut self) -> Result<Option<(Span, Token<'a>)>, Error> {
+        self.tokens.peek().map_err(|e| self.token_error(e))
+    }
+
+    /// Returns the offset the tokenizer is at.
+    pub(crate) fn current(&mut self) -> usize {
+        self.tokens.current()
+    }
+
+    /// Reads the next token if it is keylike, such as the next part of a
+    /// number or date, leaving any other token to be read later.
+    fn next_keylike(&mut self) -> Result<Option<(Span, &'a str)>, Error> {
+        match self.peek()? {
+            Some((span, Token::Keylike(s))) => {
+                self.next()?;
+                Ok(Some((span, s)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn peek_colon(&mut self) -> Result<bool, Error> {
+        Ok(matches!(self.peek()?, Some((_, Token::Colon))))
+    }
+
+    fn eof(&self) -> Error {
+        let end = self.input.len();
+        self.error(end..end, ErrorKind::UnexpectedEof)
+    }
+
+    pub(crate) fn token_error(&self, error: TokenError) -> Error {
+        // Errors about a single character span just that character.
+        let char_span = |at: usize, ch: char| at..at + ch.len_utf8();
+        match error {
+            TokenError::InvalidCharInString(at, ch) => {
+                self.error(char_span(at, ch), ErrorKind::InvalidCharInString(ch))
+            }
+            TokenError::InvalidEscape(at, ch) => {
+                self.error(char_span(at, ch), ErrorKind::InvalidEscape(ch))
+            }
+            TokenError::InvalidEscapeValue(at, v) => {
+                // The escape is a `u` followed by 4 digits or a `U` by 8.
+                let len = if self.input.as_bytes()[at] == b'U' {
+                    9
+                } else {
+                    5
+                };
+                self.error(at..at + len, ErrorKind::InvalidEscapeValue(v))
+            }
+            TokenError::InvalidHexEscape(at, ch) => {
+                self.error(char_span(at, ch), ErrorKind::InvalidHexEscape(ch))
+            }
+            TokenError::NewlineInString(at) => {
+                self.error(char_span(at, '\n'), ErrorKind::NewlineInString)
+            }
+            TokenError::Unexpected(at, ch) => {
+                self.error(char_span(at, ch), ErrorKind::Unexpected(ch))
+            }
+            // The tokenizer only gives up on a string at the end of the input.
+            TokenError::UnterminatedString(at) => {
+                self.error(at..self.input.len(), ErrorKind::UnterminatedString)
+            }
+            TokenError::NewlineInTableKey(at) => {
+                self.error(char_span(at, '\n'), ErrorKind::NewlineInTableKey)
+            }
+            TokenError::Wanted {
+                span,
+                expected,
+                found,
+            } => self.error(span.start..span.end, ErrorKind::Wanted { expected, found }),
+            TokenError::MultilineStringKey(span) => {
+                self.error(span.start..span.end, ErrorKind::MultilineStringKey)
+            }
+            TokenError::StringTooLong(span) => self.error(
+                span.start..span.end,
+                ErrorKind::LimitExceeded {
+                    limit: "max_string_len",
+                    max: self.limits.max_string_len,
+                },
+            ),
+        }
+    }
+
+    pub(crate) fn error(&self, span: Range<usize>, kind: ErrorKind) -> Error {
+        let mut err = Error::from_kind(Some(span), kind);
+        self.fix_error(&mut err);
+        err
+    }
+
+    /// Creates an error about the input read from `at` up to the current
+    /// position, such as a malformed number or date.
+    fn read_error(&self, at: usize, kind: ErrorKind) -> Error {
+        self.error(at..self.tokens.current().max(at), kind)
+    }
+
+    /// Creates an error about something defined at both `a` and `b`.
+    fn duplicate(&self, a: Range<usize>, b: Range<usize>, kind: ErrorKind) -> Error {
+        let mut err = Error::duplicate(a, b, kind);
+        self.fix_error(&mut err);
+        err
+    }
+
+    /// Fills in the line/column of an error from the start of its span.
+    fn fix_error(&self, err: &mut Error) {
+        err.fix_linecol(|at| self.to_linecol(at));
+    }
+
+    /// Converts a byte offset from an error message to a (line, column) pair
+    ///
+    /// All indexes are 0-based.
+    pub(crate) fn to_linecol(&self, offset: usize) -> (usize, usize) {
+        let mut cur = 0;
+        // Use split_terminator instead of lines so that if there is a `\r`,
+        // it is included in the offset calculation. The `+1` values below
+        // account for the `\n`.
+        for (i, line) in self.input.split_terminator('\n').enumerate() {
+            if cur + line.len() + 1 > offset {
+                return (i, offset - cur);
+            }
+            cur += line.len() + 1;
+        }
+        (self.input.lines().count(), 0)
+    }
+}
+
+/// A structural event produced by [`Parser`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event<'a> {
+    /// A `[table]` or `[[array.of.tables]]` header.
+    TableHeader {
+        /// The keys making up the name of the table.
+        path: Vec<Cow<'a, str>>,
+        /// Whether this is a `[[header]]` for an array of tables.
+        array: bool,
+        /// The span of the header, from the opening to the closing brackets.
+        span: Range<usize>,
+    },
+    /// The key of a key/value pair, which is followed by the events of the
+    /// value.
+    KeyValue {
+        /// The parts of the key, e.g. `["a", "b"]` for `a.b = 1`.
+        dotted_key: Vec<Cow<'a, str>>,
+        /// The span of the key.
+        span: Range<usize>,
+    },
+    /// The start of an inline table, which is followed by `KeyValue` events
+    /// for its contents and then an `End`.
+    BeginInlineTable,
+    /// The start of an array, which is followed by the events of its values
+    /// and then an `End`.
+    BeginArray,
+    /// A value other than an array or an inline table.
+    Scalar(Scalar<'a>),
+    /// The end of the innermost array or inline table.
+    End,
+    /// A comment, including the leading `#`.
+    Comment(&'a str),
+}
+
+/// A value produced by [`Event::Scalar`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Scalar<'a> {
+    /// A string, with escapes resolved.
+    String(Cow<'a, str>),
+    /// An integer.
+    Integer(i64),
+    /// An integer larger than `i64::MAX`, only produced when
+    /// `Options::unsigned_integers` is enabled.
+    UInteger(u64),
+    /// A float.
+    Float(f64),
+    /// A boolean.
+    Boolean(bool),
+    /// A datetime.
+    Datetime(datetime::Datetime),
+}
+
+/// A pull parser which reads TOML as a stream of [`Event`]s.
+///
+/// Unlike [`Deserializer`] this does not buffer up the document: only the
+/// nesting of the arrays and inline tables currently being read is tracked.
+/// As a consequence only the syntax of the input is checked, and semantic
+/// errors such as duplicate keys or tables are not detected.
+///
+/// Parsing stops at the first error, after which the iterator is exhausted.
+///
+/// ```rust
+/// use toml::de::{Event, Parser, Scalar};
+///
+/// let events = Parser::new("[a]\nb = [1] # c\n")
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(
+///     events,
+///     [
+///         Event::TableHeader {
+///             path: vec!["a".into()],
+///             array: false,
+///             span: 0..3,
+///         },
+///         Event::KeyValue {
+///             dotted_key: vec!["b".into()],
+///             span: 4..5,
+///         },
+///         Event::BeginArray,
+///         Event::Scalar(Scalar::Integer(1)),
+///         Event::End,
+///         Event::Comment("# c"),
+///     ]
+/// );
+/// ```
+pub struct Parser<'a> {
+    de: Deserializer<'a>,
+    state: ParserState,
+    // Whether each of the arrays and inline tables being read is an array.
+    stack: Vec<bool>,
+}
+
+#[derive(Clone, Copy)]
+enum ParserState {
+    Statement,
+    Value,
+    LineEnd,
+    ArrayValue,
+    ArrayComma,
+    // Whether the inline table may be closed instead.
+    InlineKey { close: bool },
+    InlineComma,
+    Done,
+}
+
+impl<'a> Parser<'a> {
+    /// Creates a parser reading the string provided.
+    pub fn new(input: &'a str) -> Parser<'a> {
+        Parser::with_options(input, &Options::default())
+    }
+
+    /// Creates a parser reading the string provided according to `options`.
+    pub fn with_options(input: &'a str, options: &Options) -> Parser<'a> {
+        let mut de = Deserializer::with_options(input, options);
+        de.raw_numbers = false;
+        de.arbitrary_precision = false;
+        Parser {
+            de,
+            state: ParserState::Statement,
+            stack: Vec::new(),
+        }
+    }
+
+    fn event(&mut self) -> Result<Option<Event<'a>>, Error> {
+        loop {
+            match self.state {
+                ParserState::Statement => {
+                    self.de.eat_whitespace()?;
+                    if let Some(comment) = self.comment()? {
+                        return Ok(Some(comment));
+                    }
+                    if self.de.eat(Token::Newline)? {
+                        continue;
+                    }
+                    return match self.de.peek()? {
+                        Some((_, Token::LeftBracket)) => self.table_header().map(Some),
+                        Some(_) => self.key(),
+                        None => {
+                            self.state = ParserState::Done;
+                            Ok(None)
+                        }
+                    };
+                }
+                ParserState::Value => {
+                    self.de.eat_whitespace()?;
+                    return self.value().map(Some);
+                }
+                ParserState::LineEnd => {
+                    self.de.eat_whitespace()?;
+                    let comment = self.comment()?;
+                    self.de.eat_newline_or_eof()?;
+                    self.state = ParserState::Statement;
+                    if comment.is_some() {
+                        return Ok(comment);
+                    }
+                }
+                ParserState::ArrayValue | ParserState::ArrayComma => {
+                    self.de.eat_whitespace()?;
+                    if let Some(comment) = self.comment()? {
+                        return Ok(Some(comment));
+                    }
+                    if self.de.eat(Token::Newline)? {
+                        continue;
+                    }
+                    if self.de.eat(Token::RightBracket)? {
+                        return Ok(Some(self.end()));
+                    }
+                    if let ParserState::ArrayComma = self.state {
+                        if !self.de.eat(Token::Comma)? {
+                            self.de.expect(Token::RightBracket)?;
+                        }
+                        self.state = ParserState::ArrayValue;
+                    } else {
+                        self.state = ParserState::Value;
+                    }
                 }
-            }
-            if self.eat(Token::Colon)? {
-                match self.next()? {
-                    Some((Span { end, .. }, Token::Keylike(_))) => {
-                        span.end = end;
+                ParserState::InlineKey { close } => {
+                    if let Some(comment) = self.inline_table_whitespace()? {
+                        return Ok(Some(comment));
+                    }
+                    if close && self.de.eat(Token::RightBrace)? {
+                        return Ok(Some(self.end()));
+                    }
+                    return self.key();
+                }
+                ParserState::InlineComma => {
+                    if let Some(comment) = self.inline_table_whitespace()? {
+                        return Ok(Some(comment));
                     }
-                    _ => return Err(self.error(start, ErrorKind::DateInvalid)),
+                    if self.de.eat(Token::RightBrace)? {
+                        return Ok(Some(self.end()));
+                    }
+                    self.de.expect(Token::Comma)?;
+                    self.state = ParserState::InlineKey {
+                        close: self.de.spec >= TomlVersion::V1_1,
+                    };
                 }
+                ParserState::Done => return Ok(None),
             }
         }
-
-        let end = self.tokens.current();
-        Ok((span, &self.tokens.input()[start..end]))
     }
 
-    // TODO(#140): shouldn't buffer up this entire table in memory, it'd be
-    // great to defer parsing everything until later.
-    fn inline_table(&mut self) -> Result<(Span, Vec<TablePair<'a>>), Error> {
-        let mut ret = Vec::new();
-        self.eat_whitespace()?;
-        if let Some(span) = self.eat_spanned(Token::RightBrace)? {
-            return Ok((span, ret));
-        }
-        loop {
-            let key = self.dotted_key()?;
-            self.eat_whitespace()?;
-            self.expect(Token::Equals)?;
-            self.eat_whitespace()?;
-            let value = self.value()?;
-            self.add_dotted_key(key, value, &mut ret)?;
-
-            self.eat_whitespace()?;
-            if let Some(span) = self.eat_spanned(Token::RightBrace)? {
-                return Ok((span, ret));
+    fn comment(&mut self) -> Result<Option<Event<'a>>, Error> {
+        match self.de.peek()? {
+            Some((_, Token::Comment(comment))) => {
+                self.de.next()?;
+                Ok(Some(Event::Comment(comment)))
             }
-            self.expect(Token::Comma)?;
-            self.eat_whitespace()?;
+            _ => Ok(None),
         }
     }
 
-    // TODO(#140): shouldn't buffer up this entire array in memory, it'd be
-    // great to defer parsing everything until later.
-    fn array(&mut self) -> Result<(Span, Vec<Value<'a>>), Error> {
-        let mut ret = Vec::new();
-
-        let intermediate = |me: &mut Deserializer<'_>| {
-            loop {
-                me.eat_whitespace()?;
-                if !me.eat(Token::Newline)? && !me.eat_comment()? {
-                    break;
-                }
+    /// Skips whitespace and, as of TOML 1.1, newlines between the entries
+    /// of an inline table, stopping at any comment.
+    fn inline_table_whitespace(&mut self) -> Result<Option<Event<'a>>, Error> {
+        self.de.eat_whitespace()?;
+        if self.de.spec >= TomlVersion::V1_1 {
+            while self.de.eat(Token::Newline)? {
+                self.de.eat_whitespace()?;
             }
-            Ok(())
-        };
+            return self.comment();
+        }
+        Ok(None)
+    }
 
+    fn table_header(&mut self) -> Result<Event<'a>, Error> {
+        let start = self.de.tokens.current();
+        self.de.expect(Token::LeftBracket)?;
+        let array = self.de.eat(Token::LeftBracket)?;
+        let mut path = Vec::new();
         loop {
-            intermediate(self)?;
-            if let Some(span) = self.eat_spanned(Token::RightBracket)? {
-                return Ok((span, ret));
-            }
-            let value = self.value()?;
-            ret.push(value);
-            intermediate(self)?;
-            if !self.eat(Token::Comma)? {
+            self.de.eat_whitespace()?;
+            path.push(self.de.table_key()?.1);
+            self.de.eat_whitespace()?;
+            if !self.de.eat(Token::Period)? {
                 break;
             }
         }
-        intermediate(self)?;
-        let span = self.expect_spanned(Token::RightBracket)?;
-        Ok((span, ret))
+        self.de.expect(Token::RightBracket)?;
+        if array {
+            self.de.expect(Token::RightBracket)?;
+        }
+        self.state = ParserState::LineEnd;
+        Ok(Event::TableHeader {
+            path,
+            array,
+            span: start..self.de.tokens.current(),
+        })
     }
 
-    fn table_key(&mut self) -> Result<(Span, Cow<'a, str>), Error> {
-        self.tokens.table_key().map_err(|e| self.token_error(e))
+    fn key(&mut self) -> Result<Option<Event<'a>>, Error> {
+        let key = self.de.dotted_key()?;
+        self.de.eat_whitespace()?;
+        self.de.expect(Token::Equals)?;
+        self.state = ParserState::Value;
+        let span = key[0].0.start..key[key.len() - 1].0.end;
+        Ok(Some(Event::KeyValue {
+            dotted_key: key.into_iter().map(|(_, part)| part).collect(),
+            span,
+        }))
     }
 
-    fn dotted_key(&mut self) -> Result<Vec<(Span, Cow<'a, str>)>, Error> {
-        let mut result = Vec::new();
-        result.push(self.table_key()?);
-        self.eat_whitespace()?;
-        while self.eat(Token::Period)? {
-            self.eat_whitespace()?;
-            result.push(self.table_key()?);
-            self.eat_whitespace()?;
+    fn value(&mut self) -> Result<Event<'a>, Error> {
+        match self.de.peek()? {
+            Some((_, Token::LeftBrace)) => {
+                self.de.next()?;
+                self.stack.push(false);
+                self.state = ParserState::InlineKey { close: true };
+                return Ok(Event::BeginInlineTable);
+            }
+            Some((_, Token::LeftBracket)) => {
+                self.de.next()?;
+                self.stack.push(true);
+                self.state = ParserState::ArrayValue;
+                return Ok(Event::BeginArray);
+            }
+            _ => {}
         }
-        Ok(result)
+        let (_, scalar) = self.de.scalar()?;
+        self.state = self.after_value();
+        Ok(Event::Scalar(scalar))
     }
 
-    /// Stores a value in the appropriate hierarchical structure positioned based on the dotted key.
-    ///
-    /// Given the following definition: `multi.part.key = "value"`, `multi` and `part` are
-    /// intermediate parts which are mapped to the relevant fields in the deserialized type's data
-    /// hierarchy.
-    ///
-    /// # Parameters
-    ///
-    /// * `key_parts`: Each segment of the dotted key, e.g. `part.one` maps to
-    ///                `vec![Cow::Borrowed("part"), Cow::Borrowed("one")].`
-    /// * `value`: The parsed value.
-    /// * `values`: The `Vec` to store the value in.
-    fn add_dotted_key(
-        &self,
-        mut key_parts: Vec<(Span, Cow<'a, str>)>,
-        value: Value<'a>,
-        values: &mut Vec<TablePair<'a>>,
-    ) -> Result<(), Error> {
-        let key = key_parts.remove(0);
-        if key_parts.is_empty() {
-            values.push((key, value));
-            return Ok(());
+    fn end(&mut self) -> Event<'a> {
+        self.stack.pop();
+        self.state = self.after_value();
+        Event::End
+    }
+
+    fn after_value(&self) -> ParserState {
+        match self.stack.last() {
+            None => ParserState::LineEnd,
+            Some(true) => ParserState::ArrayComma,
+            Some(false) => ParserState::InlineComma,
         }
-        match values.iter_mut().find(|&&mut (ref k, _)| *k.1 == key.1) {
-            Some(&mut (
-                _,
-                Value {
-                    e: E::DottedTable(ref mut v),
-                    ..
-                },
-            )) => {
-                return self.add_dotted_key(key_parts, value, v);
-            }
-            Some(&mut (_, Value { start, .. })) => {
-                return Err(self.error(start, ErrorKind::DottedKeyInvalidType));
+    }
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Result<Event<'a>, Error>> {
+        match self.event() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.state = ParserState::Done;
+                Some(Err(e))
             }
-            None => {}
-        }
-        // The start/end value is somewhat misleading here.
-        let table_values = Value {
-            e: E::DottedTable(Vec::new()),
-            start: value.start,
-            end: value.end,
-        };
-        values.push((key, table_values));
-        let last_i = values.len() - 1;
-        if let (
-            _,
-            Value {
-                e: E::DottedTable(ref mut v),
-                ..
-            },
-        ) = values[last_i]
-        {
-            self.add_dotted_key(key_parts, value, v)?;
         }
-        Ok(())
     }
+}
 
-    fn eat_whitespace(&mut self) -> Result<(), Error> {
-        self.tokens
-            .eat_whitespace()
-            .map_err(|e| self.token_error(e))
+impl Error {
+    /// Produces a (line, column) pair of the position of the error if available
+    ///
+    /// All indexes are 0-based.
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        self.inner.line.map(|line| (line, self.inner.col))
     }
 
-    fn eat_comment(&mut self) -> Result<bool, Error> {
-        self.tokens.eat_comment().map_err(|e| self.token_error(e))
+    /// Returns the kind of this error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use toml::de::ErrorKind;
+    ///
+    /// let err = toml::from_str::<toml::Value>("a = 1_").unwrap_err();
+    /// assert_eq!(*err.kind(), ErrorKind::NumberInvalid);
+    /// ```
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner.kind
     }
 
-    fn eat_newline_or_eof(&mut self) -> Result<(), Error> {
-        self.tokens
-            .eat_newline_or_eof()
-            .map_err(|e| self.token_error(e))
+    /// Returns the path of keys leading to the value this error occurred in,
+    /// which is empty if the error is not about a particular key.
+    ///
+    /// This is the path included in the error message, e.g. `["a", "b"]` for
+    /// an error reported "for key `a.b`".
+    pub fn key_path(&self) -> &[String] {
+        &self.inner.key
     }
 
-    fn eat(&mut self, expected: Token<'a>) -> Result<bool, Error> {
-        self.tokens.eat(expected).map_err(|e| self.token_error(e))
+    /// Returns the byte offset into the input at which this error occurred,
+    /// if it is known.
+    pub fn offset(&self) -> Option<usize> {
+        self.inner.span.as_ref().map(|span| span.start)
     }
 
-    fn eat_spanned(&mut self, expected: Token<'a>) -> Result<Option<Span>, Error> {
-        self.tokens
-            .eat_spanned(expected)
-            .map_err(|e| self.token_error(e))
+    /// Returns the byte range of the input this error refers to, if it is
+    /// known.
+    ///
+    /// This covers the whole offending token or value where possible, e.g.
+    /// the full header of a duplicate table or the name of an unknown key.
+    /// Errors about the end of the input have an empty span.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let input = "a = 1\n[b]\n[b]\n";
+    /// let err = toml::from_str::<toml::Value>(input).unwrap_err();
+    /// assert_eq!(err.span(), Some(10..13));
+    /// assert_eq!(&input[err.span().unwrap()], "[b]");
+    /// ```
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.inner.span.clone()
     }
 
-    fn expect(&mut self, expected: Token<'a>) -> Result<(), Error> {
-        self.tokens
-            .expect(expected)
-            .map_err(|e| self.token_error(e))
+    /// Returns the span of the original definition of a key or table if
+    /// this error is about one being defined again, as for an
+    /// `ErrorKind::DuplicateKey` or `ErrorKind::DuplicateTable` error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let input = "a = 1\nb = 2\na = 3\n";
+    /// let err = toml::from_str::<toml::Value>(input).unwrap_err();
+    /// assert_eq!(err.span(), Some(12..13));
+    /// assert_eq!(err.original_span(), Some(0..1));
+    /// ```
+    pub fn original_span(&self) -> Option<Range<usize>> {
+        self.inner.original.clone()
     }
 
-    fn expect_spanned(&mut self, expected: Token<'a>) -> Result<Span, Error> {
-        self.tokens
-            .expect_spanned(expected)
-            .map_err(|e| self.token_error(e))
+    /// Returns the unexpected keys and the keys that were expected instead
+    /// if this is an `ErrorKind::UnexpectedKeys` error.
+    pub fn unexpected_keys(&self) -> Option<(&[String], &'static [&'static str])> {
+        match self.inner.kind {
+            ErrorKind::UnexpectedKeys {
+                ref keys,
+                available,
+            } => Some((keys, available)),
+            _ => None,
+        }
     }
 
-    fn next(&mut self) -> Result<Option<(Span, Token<'a>)>, Error> {
-        self.tokens.next().map_err(|e| self.token_error(e))
+    /// Returns the closest match for a misspelled field, variant or key, if
+    /// this error is about one and a similar enough name was expected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde_derive::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// #[serde(deny_unknown_fields)]
+    /// struct Config {
+    ///     database_url: String,
+    /// }
+    ///
+    /// let err = toml::from_str::<Config>("databse_url = 'x'").unwrap_err();
+    /// assert_eq!(err.suggestion(), Some("database_url"));
+    /// ```
+    pub fn suggestion(&self) -> Option<&'static str> {
+        self.inner.suggestion
     }
 
-    fn peek(&mut self) -> Result<Option<(Span, Token<'a>)>, Error> {
-        self.tokens.peek().map_err(|e| self.token_error(e))
+    /// Returns a description of what was expected and what was found instead
+    /// if this is an `ErrorKind::Wanted` error.
+    pub fn wanted(&self) -> Option<(&'static str, &'static str)> {
+        match self.inner.kind {
+            ErrorKind::Wanted { expected, found } => Some((expected, found)),
+            _ => None,
+        }
     }
 
-    fn eof(&self) -> Error {
-        self.error(self.input.len(), ErrorKind::UnexpectedEof)
-    }
+    /// Renders this error together with the part of `src` it refers to, in
+    /// the style of rustc diagnostics.
+    ///
+    /// `src` must be the input this error was produced from, and `filename`
+    /// is shown in front of the line and column if given. Where it helps, a
+    /// note is added below the snippet, such as the keys that were expected
+    /// for an `ErrorKind::UnexpectedKeys` error. The original definition of
+    /// a duplicate key or table is pointed out as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let input = "a = 1\nb = tru\n";
+    /// let err = toml::from_str::<toml::Value>(input).unwrap_err();
+    /// assert_eq!(
+    ///     err.display_with_source(input, Some("config.toml")),
+    ///     "\
+    /// error: invalid TOML value, did you mean to use a quoted string?
+    ///  --> config.toml:2:5
+    ///   |
+    /// 2 | b = tru
+    ///   |     ^^^
+    ///   |
+    ///   = help: strings must be surrounded by quotes
+    /// "
+    /// );
+    /// ```
+    pub fn display_with_source(&self, src: &str, filename: Option<&str>) -> String {
+        struct Message<'a>(&'a Error);
+
+        impl fmt::Display for Message<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_message(f)?;
+                self.0.fmt_suggestion(f)
+            }
+        }
 
-    fn token_error(&self, error: TokenError) -> Error {
-        match error {
-            TokenError::InvalidCharInString(at, ch) => {
-                self.error(at, ErrorKind::InvalidCharInString(ch))
+        let mut out = format!("error: {}\n", Message(self));
+
+        let span = self.inner.span.clone().map(|span| clamp_span(src, span));
+        let lines = span
+            .as_ref()
+            .map_or_else(Vec::new, |span| snippet_lines(src, span));
+        // Where a key or table was first defined, if this error is about it
+        // being defined again.
+        let original = self
+            .inner
+            .original
+            .clone()
+            .map(|span| clamp_span(src, span));
+        let original_lines = original
+            .as_ref()
+            .map_or_else(Vec::new, |span| snippet_lines(src, span));
+
+        let width = lines
+            .iter()
+            .chain(&original_lines)
+            .map(|l| l.0.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(width);
+        if let (Some(span), Some(&(line, offset, text))) = (&span, lines.first()) {
+            let col = text[..span.start - offset].chars().count() + 1;
+            out.push_str(&format!("{}--> ", gutter));
+            if let Some(filename) = filename {
+                out.push_str(&format!("{}:", filename));
             }
-            TokenError::InvalidEscape(at, ch) => self.error(at, ErrorKind::InvalidEscape(ch)),
-            TokenError::InvalidEscapeValue(at, v) => {
-                self.error(at, ErrorKind::InvalidEscapeValue(v))
+            out.push_str(&format!("{}:{}\n", line, col));
+            out.push_str(&format!("{} |\n", gutter));
+            push_snippet(&mut out, &lines, span, width, '^', "");
+        } else if let Some(filename) = filename {
+            out.push_str(&format!(" --> {}\n", filename));
+        }
+        if let Some(ref original) = original {
+            if !original_lines.is_empty() {
+                out.push_str(&format!("{} |\n", gutter));
+                push_snippet(
+                    &mut out,
+                    &original_lines,
+                    original,
+                    width,
+                    '-',
+                    " first defined here",
+                );
             }
-            TokenError::InvalidHexEscape(at, ch) => self.error(at, ErrorKind::InvalidHexEscape(ch)),
-            TokenError::NewlineInString(at) => self.error(at, ErrorKind::NewlineInString),
-            TokenError::Unexpected(at, ch) => self.error(at, ErrorKind::Unexpected(ch)),
-            TokenError::UnterminatedString(at) => self.error(at, ErrorKind::UnterminatedString),
-            TokenError::NewlineInTableKey(at) => self.error(at, ErrorKind::NewlineInTableKey),
-            TokenError::Wanted {
-                at,
-                expected,
-                found,
-            } => self.error(at, ErrorKind::Wanted { expected, found }),
-            TokenError::MultilineStringKey(at) => self.error(at, ErrorKind::MultilineStringKey),
         }
-    }
 
-    fn error(&self, at: usize, kind: ErrorKind) -> Error {
-        let mut err = Error::from_kind(Some(at), kind);
-        err.fix_linecol(|at| self.to_linecol(at));
-        err
+        if let Some(help) = self.help() {
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{} = help: {}\n", gutter, help));
+        }
+        out
     }
 
-    /// Converts a byte offset from an error message to a (line, column) pair
-    ///
-    /// All indexes are 0-based.
-    fn to_linecol(&self, offset: usize) -> (usize, usize) {
-        let mut cur = 0;
-        // Use split_terminator instead of lines so that if there is a `\r`,
-        // it is included in the offset calculation. The `+1` values below
-        // account for the `\n`.
-        for (i, line) in self.input.split_terminator('\n').enumerate() {
-            if cur + line.len() + 1 > offset {
-                return (i, offset - cur);
+    /// A hint on how to fix this error, if there is a useful one.
+    fn help(&self) -> Option<String> {
+        match self.inner.kind {
+            ErrorKind::UnexpectedKeys { available, .. } if !available.is_empty() => Some(format!(
+                "available keys: {}",
+                available
+                    .iter()
+                    .map(|k| format!("`{}`", k))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            ErrorKind::DuplicateTable(_) => {
+                Some("tables must be defined before values".to_string())
             }
-            cur += line.len() + 1;
+            ErrorKind::UnquotedString => Some("strings must be surrounded by quotes".to_string()),
+            _ => None,
         }
-        (self.input.lines().count(), 0)
-    }
-}
-
-impl Error {
-    /// Produces a (line, column) pair of the position of the error if available
-    ///
-    /// All indexes are 0-based.
-    pub fn line_col(&self) -> Option<(usize, usize)> {
-        self.inner.line.map(|line| (line, self.inner.col))
     }
 
-    fn from_kind(at: Option<usize>, kind: ErrorKind) -> Error {
+    fn from_kind(span: Option<Range<usize>>, kind: ErrorKind) -> Error {
         Error {
             inner: Box::new(ErrorInner {
                 kind,
                 line: None,
                 col: 0,
-                at,
+                span,
                 message: String::new(),
                 key: Vec::new(),
+                suggestion: None,
+                original: None,
             }),
         }
     }
 
-    fn custom(at: Option<usize>, s: String) -> Error {
+    fn from_kind_spanned(span: Range<usize>, kind: ErrorKind) -> Error {
+        Error::from_kind(Some(span), kind)
+    }
+
+    /// Creates an error about something defined at both `a` and `b`, which
+    /// points at whichever comes later in the input.
+    fn duplicate(a: Range<usize>, b: Range<usize>, kind: ErrorKind) -> Error {
+        let (original, span) = if a.start <= b.start { (a, b) } else { (b, a) };
+        let mut err = Error::from_kind_spanned(span, kind);
+        err.inner.original = Some(original);
+        err
+    }
+
+    fn custom(span: Option<Range<usize>>, s: String) -> Error {
         Error {
             inner: Box::new(ErrorInner {
                 kind: ErrorKind::Custom,
                 line: None,
                 col: 0,
-                at,
+                span,
                 message: s,
                 key: Vec::new(),
+                suggestion: None,
+                original: None,
             }),
         }
     }
 
+    fn io(e: io::Error) -> Error {
+        let mut err = Error::from_kind(None, ErrorKind::Io(e.kind()));
+        err.inner.message = e.to_string();
+        err
+    }
+
     pub(crate) fn add_key_context(&mut self, key: &str) {
         self.inner.key.insert(0, key.to_string());
     }
 
-    fn fix_offset<F>(&mut self, f: F)
+    fn fix_span<F>(&mut self, f: F)
     where
-        F: FnOnce() -> Option<usize>,
+        F: FnOnce() -> Option<Range<usize>>,
     {
         // An existing offset is always better positioned than anything we
         // might want to add later.
-        if self.inner.at.is_none() {
-            self.inner.at = f();
+        if self.inner.span.is_none() {
+            self.inner.span = f();
         }
     }
 
@@ -2074,22 +4307,89 @@ impl Error {
     where
         F: FnOnce(usize) -> (usize, usize),
     {
-        if let Some(at) = self.inner.at {
-            let (line, col) = f(at);
+        if let Some(ref span) = self.inner.span {
+            let (line, col) = f(span.start);
             self.inner.line = Some(line);
             self.inner.col = col;
         }
     }
 }
 
+/// Clamps `span` to `src`, in case an error came from another input.
+fn clamp_span(src: &str, span: Range<usize>) -> Range<usize> {
+    let mut start = span.start.min(src.len());
+    while !src.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = span.end.max(start).min(src.len());
+    while !src.is_char_boundary(end) {
+        end += 1;
+    }
+    start..end
+}
+
+/// Returns the lines of `src` covered by `span`, as (number, start offset,
+/// text).
+fn snippet_lines<'s>(src: &'s str, span: &Range<usize>) -> Vec<(usize, usize, &'s str)> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for (i, line) in src.split('\n').enumerate() {
+        let end = offset + line.len();
+        if end >= span.start && (offset < span.end || lines.is_empty()) {
+            lines.push((i + 1, offset, line.strip_suffix('\r').unwrap_or(line)));
+        }
+        if end >= span.end && !lines.is_empty() {
+            break;
+        }
+        offset = end + 1;
+    }
+    lines
+}
+
+/// Writes out `lines`, underlining `span` with `mark` and following the
+/// last underline with `label`.
+fn push_snippet(
+    out: &mut String,
+    lines: &[(usize, usize, &str)],
+    span: &Range<usize>,
+    width: usize,
+    mark: char,
+    label: &str,
+) {
+    let gutter = " ".repeat(width);
+    for (i, &(line, offset, text)) in lines.iter().enumerate() {
+        let from = span.start.max(offset) - offset;
+        let to = (span.end - offset).min(text.len()).max(from);
+        // Keep tabs so the underline lines up with the source.
+        let indent = text[..from]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let marks = mark
+            .to_string()
+            .repeat(text[from..to].chars().count().max(1));
+        let label = if i + 1 == lines.len() { label } else { "" };
+        out.push_str(&format!("{:>w$} | {}\n", line, text, w = width));
+        out.push_str(&format!("{} | {}{}{}\n", gutter, indent, marks, label));
+    }
+}
+
 impl std::convert::From<Error> for std::io::Error {
     fn from(e: Error) -> Self {
-        std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        let kind = match e.inner.kind {
+            ErrorKind::Io(kind) => kind,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, e.to_string())
     }
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Error {
+    /// Writes the error message, including the key it relates to, without
+    /// any location information or suggestion.
+    fn fmt_message(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use std::fmt::Display;
+
         match &self.inner.kind {
             ErrorKind::UnexpectedEof => "unexpected eof encountered".fmt(f)?,
             ErrorKind::InvalidCharInString(c) => write!(
@@ -2120,10 +4420,16 @@ impl fmt::Display for Error {
                 write!(f, "expected {}, found {}", expected, found)?
             }
             ErrorKind::NumberInvalid => "invalid number".fmt(f)?,
-            ErrorKind::DateInvalid => "invalid date".fmt(f)?,
+            ErrorKind::DateInvalid => {
+                "invalid date".fmt(f)?;
+                if !self.inner.message.is_empty() {
+                    write!(f, ": {}", self.inner.message)?;
+                }
+            }
             ErrorKind::DuplicateTable(ref s) => {
                 write!(f, "redefinition of table `{}`", s)?;
             }
+            ErrorKind::DuplicateKey(ref s) => write!(f, "duplicate key: `{}`", s)?,
             ErrorKind::RedefineAsArray => "table redefined as array".fmt(f)?,
             ErrorKind::EmptyTableKey => "empty table key found".fmt(f)?,
             ErrorKind::MultilineStringKey => "multiline strings are not allowed for key".fmt(f)?,
@@ -2149,7 +4455,11 @@ impl fmt::Display for Error {
                 f,
                 "invalid TOML value, did you mean to use a quoted string?"
             )?,
-            ErrorKind::__Nonexhaustive => panic!(),
+            ErrorKind::Io(_) => write!(f, "I/O error: {}", self.inner.message)?,
+            ErrorKind::MixedArrayType => "mixed types in an array".fmt(f)?,
+            ErrorKind::LimitExceeded { limit, max } => {
+                write!(f, "exceeded the `{}` limit of {}", limit, max)?
+            }
         }
 
         if !self.inner.key.is_empty() {
@@ -2163,11 +4473,27 @@ impl fmt::Display for Error {
             write!(f, "`")?;
         }
 
+        Ok(())
+    }
+
+    /// Writes the suggested replacement for a misspelled key, if there is one.
+    fn fmt_suggestion(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(suggestion) = self.inner.suggestion {
+            write!(f, ", did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_message(f)?;
+
         if let Some(line) = self.inner.line {
             write!(f, " at line {} column {}", line + 1, self.inner.col + 1)?;
         }
 
-        Ok(())
+        self.fmt_suggestion(f)
     }
 }
 
@@ -2177,6 +4503,86 @@ impl de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Error {
         Error::custom(None, msg.to_string())
     }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Error {
+        // Keep serde's wording, only adding the suggestion on top.
+        let msg = de::value::Error::unknown_variant(variant, expected);
+        let mut err = Error::custom(None, msg.to_string());
+        err.inner.suggestion = suggest(variant, expected);
+        err
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Error {
+        let msg = de::value::Error::unknown_field(field, expected);
+        let mut err = Error::custom(None, msg.to_string());
+        err.inner.suggestion = suggest(field, expected);
+        err
+    }
+}
+
+/// Returns whether the time in a datetime, if it has one, includes seconds.
+fn time_has_seconds(date: &str) -> bool {
+    let time = match date.find(['T', 't', ' ']) {
+        Some(i) => &date[i + 1..],
+        None if date.as_bytes().get(2) == Some(&b':') => date,
+        None => return true,
+    };
+    time.as_bytes().get(5) == Some(&b':')
+}
+
+/// Finds the name in `candidates` closest to the misspelled `name`, if one is
+/// close enough to be worth suggesting and there is no other equally close.
+fn suggest(name: &str, candidates: &[&'static str]) -> Option<&'static str> {
+    if let Some(c) = candidates.iter().find(|c| c.eq_ignore_ascii_case(name)) {
+        return Some(c);
+    }
+    let max = name.chars().count().max(3) / 3;
+    let mut best = None;
+    let mut ambiguous = false;
+    for c in candidates {
+        let distance = edit_distance(name, c);
+        match best {
+            _ if distance > max => {}
+            Some((d, _)) if distance > d => {}
+            Some((d, _)) if distance == d => ambiguous = true,
+            _ => {
+                best = Some((distance, *c));
+                ambiguous = false;
+            }
+        }
+    }
+    best.filter(|_| !ambiguous).map(|(_, c)| c)
+}
+
+/// The Levenshtein distance between two strings, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = prev[j] + if ca == cb { 0 } else { 1 };
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// An array or inline table whose contents are being parsed.
+enum Nested<'a> {
+    Array {
+        start: usize,
+        values: Vec<Value<'a>>,
+    },
+    InlineTable {
+        start: usize,
+        pairs: Vec<TablePair<'a>>,
+        // The key of the value being parsed, and the depth of the table.
+        key: Vec<(Span, Cow<'a, str>)>,
+        depth: usize,
+    },
 }
 
 enum Line<'a> {
@@ -2190,6 +4596,8 @@ enum Line<'a> {
 
 struct Header<'a> {
     first: bool,
+    // Where the header ends, once its closing brackets have been read.
+    end: usize,
     array: bool,
     require_newline_after_table: bool,
     tokens: Tokenizer<'a>,
@@ -2199,6 +4607,7 @@ impl<'a> Header<'a> {
     fn new(tokens: Tokenizer<'a>, array: bool, require_newline_after_table: bool) -> Header<'a> {
         Header {
             first: true,
+            end: 0,
             array,
             tokens,
             require_newline_after_table,
@@ -2217,6 +4626,7 @@ impl<'a> Header<'a> {
             if self.array {
                 self.tokens.expect(Token::RightBracket)?;
             }
+            self.end = self.tokens.current();
 
             self.tokens.eat_whitespace()?;
             if self.require_newline_after_table && !self.tokens.eat_comment()? {
@@ -2228,7 +4638,7 @@ impl<'a> Header<'a> {
 }
 
 #[derive(Debug)]
-struct Value<'a> {
+pub(crate) struct Value<'a> {
     e: E<'a>,
     start: usize,
     end: usize,
@@ -2237,7 +4647,12 @@ struct Value<'a> {
 #[derive(Debug)]
 enum E<'a> {
     Integer(i64),
+    UInteger(u64),
     Float(f64),
+    // The text of an integer or float, see `Deserializer::raw_numbers`, and
+    // whether a `Value` keeps it as a `Number` rather than as the `Integer`
+    // or `Float` it is exactly equal to.
+    Number(&'a str, bool),
     Boolean(bool),
     String(Cow<'a, str>),
     Datetime(&'a str),
@@ -2246,12 +4661,59 @@ enum E<'a> {
     DottedTable(Vec<TablePair<'a>>),
 }
 
+impl<'a> Value<'a> {
+    /// Creates an empty table implied by the key at `span`.
+    fn dotted_table(span: Span) -> Value<'a> {
+        Value {
+            e: E::DottedTable(Vec::new()),
+            start: span.start,
+            end: span.end,
+        }
+    }
+
+    /// Creates an array of `values` spanning `start..end`.
+    pub(crate) fn array(values: Vec<Value<'a>>, start: usize, end: usize) -> Value<'a> {
+        Value {
+            e: E::Array(values),
+            start,
+            end,
+        }
+    }
+
+    /// Creates an inline table of `pairs` spanning `start..end`.
+    pub(crate) fn inline_table(pairs: Vec<TablePair<'a>>, start: usize, end: usize) -> Value<'a> {
+        Value {
+            e: E::InlineTable(pairs),
+            start,
+            end,
+        }
+    }
+
+    fn is_table(&self) -> bool {
+        matches!(self.e, E::InlineTable(_) | E::DottedTable(_))
+    }
+}
+
+/// Returns the pairs of a table, or of the last table in an array of
+/// tables, or `None` if `value` is neither.
+fn table_pairs<'v, 'a>(value: &'v mut Value<'a>) -> Option<&'v mut Vec<TablePair<'a>>> {
+    match value.e {
+        E::InlineTable(ref mut pairs) | E::DottedTable(ref mut pairs) => Some(pairs),
+        E::Array(ref mut values) if matches!(values.last(), Some(v) if v.is_table()) => {
+            values.last_mut().and_then(table_pairs)
+        }
+        _ => None,
+    }
+}
+
 impl<'a> E<'a> {
     fn type_name(&self) -> &'static str {
         match *self {
             E::String(..) => "string",
-            E::Integer(..) => "integer",
+            E::Integer(..) | E::UInteger(..) => "integer",
             E::Float(..) => "float",
+            E::Number(s, _) if number::is_float(s) => "float",
+            E::Number(..) => "integer",
             E::Boolean(..) => "boolean",
             E::Datetime(..) => "datetime",
             E::Array(..) => "array",
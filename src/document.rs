@@ -0,0 +1,1081 @@
+//! A format-preserving TOML document.
+//!
+//! The [`Document`] type parses a TOML document while keeping track of all of
+//! the formatting in the original input: comments, whitespace, key order, the
+//! way keys are quoted and the exact spelling of every value. Printing an
+//! unmodified document with `Display` reproduces the input byte for byte, and
+//! only the parts of a document which are edited are reformatted.
+//!
+//! ```rust
+//! use toml::document::Document;
+//!
+//! let input = r#"
+//! name = "toml"   # keep this comment
+//!
+//! [dependencies]
+//! serde = "1.0"
+//! "#;
+//!
+//! let mut doc = input.parse::<Document>().unwrap();
+//! assert_eq!(doc.to_string(), input);
+//!
+//! doc.root_mut().insert("version", "0.5.9");
+//! doc.table_mut(&["dependencies"]).unwrap().insert("indexmap", "1.0");
+//! assert_eq!(
+//!     doc.to_string(),
+//!     r#"
+//! name = "toml"   # keep this comment
+//! version = "0.5.9"
+//!
+//! [dependencies]
+//! serde = "1.0"
+//! indexmap = "1.0"
+//! "#
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fmt::Write;
+use std::mem;
+use std::slice;
+use std::str::FromStr;
+
+use crate::datetime::Datetime;
+use crate::de::{self, DocumentTables, Error, ErrorKind, Scalar, TomlVersion};
+use crate::tokens::{Span, Token};
+
+/// A TOML document which remembers how it was formatted.
+///
+/// A document is made up of a root table, holding the key/value pairs which
+/// appear before the first table header, followed by the tables and arrays of
+/// tables defined with `[header]` and `[[header]]` lines, in the order they
+/// appear in the input.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    bom: bool,
+    root: Table,
+    sections: Vec<Section>,
+    trailing: String,
+}
+
+/// A table defined by a `[header]` or `[[header]]` line.
+#[derive(Clone, Debug)]
+struct Section {
+    /// Blank lines and comments before the header line.
+    prefix: String,
+    /// The header exactly as written, brackets included.
+    repr: String,
+    path: Vec<String>,
+    array: bool,
+    /// Whitespace, comment and newline following the header.
+    suffix: String,
+    body: Table,
+}
+
+/// A table in a [`Document`].
+///
+/// This is used both for the tables defined with headers (and the root table)
+/// and for inline tables. Entries are kept in the order they were defined.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    entries: Vec<Entry>,
+    inline: bool,
+    trailing_comma: bool,
+    /// Whitespace before the closing `}` of an inline table.
+    trailing: String,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    /// Blank lines, comments and indentation before the key.
+    prefix: String,
+    key: Vec<String>,
+    /// The key exactly as written, including whitespace up to the `=`.
+    repr: String,
+    /// Whitespace between the `=` and the value.
+    value_prefix: String,
+    value: Value,
+    /// Whitespace and comments after the value.
+    suffix: String,
+}
+
+/// An array in a [`Document`].
+#[derive(Clone, Debug, Default)]
+pub struct Array {
+    items: Vec<Item>,
+    trailing_comma: bool,
+    /// Whitespace and comments before the closing `]`.
+    trailing: String,
+}
+
+#[derive(Clone, Debug)]
+struct Item {
+    prefix: String,
+    value: Value,
+    suffix: String,
+}
+
+/// A value along with the text it was parsed from, if any.
+///
+/// Values which were parsed from a document are printed back exactly as they
+/// were written, while newly created values are printed in a canonical form.
+#[derive(Clone, Debug)]
+pub struct Formatted<T> {
+    value: T,
+    repr: Option<String>,
+}
+
+/// A value in a [`Document`].
+#[derive(Clone, Debug)]
+pub enum Value {
+    /// Represents a TOML string
+    String(Formatted<String>),
+    /// Represents a TOML integer
+    Integer(Formatted<i64>),
+    /// Represents a TOML float
+    Float(Formatted<f64>),
+    /// Represents a TOML boolean
+    Boolean(Formatted<bool>),
+    /// Represents a TOML datetime
+    Datetime(Formatted<Datetime>),
+    /// Represents a TOML array
+    Array(Array),
+    /// Represents a TOML inline table
+    InlineTable(Table),
+}
+
+impl Document {
+    /// Creates a new, empty document.
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    /// Returns the root table of this document, containing the key/value
+    /// pairs defined before the first table header.
+    pub fn root(&self) -> &Table {
+        &self.root
+    }
+
+    /// Returns a mutable reference to the root table of this document.
+    pub fn root_mut(&mut self) -> &mut Table {
+        &mut self.root
+    }
+
+    /// Returns the table defined with the `[header]` matching `path`, if any.
+    pub fn table(&self, path: &[&str]) -> Option<&Table> {
+        self.sections
+            .iter()
+            .find(|s| !s.array && path_eq(&s.path, path))
+            .map(|s| &s.body)
+    }
+
+    /// Returns a mutable reference to the table defined with the `[header]`
+    /// matching `path`, if any.
+    pub fn table_mut(&mut self, path: &[&str]) -> Option<&mut Table> {
+        self.sections
+            .iter_mut()
+            .find(|s| !s.array && path_eq(&s.path, path))
+            .map(|s| &mut s.body)
+    }
+
+    /// Returns the tables of the array of tables defined with `[[header]]`
+    /// lines matching `path`, in order.
+    pub fn array_of_tables<'a>(&'a self, path: &'a [&'a str]) -> impl Iterator<Item = &'a Table> {
+        self.sections
+            .iter()
+            .filter(move |s| s.array && path_eq(&s.path, path))
+            .map(|s| &s.body)
+    }
+
+    /// Returns mutable references to the tables of the array of tables
+    /// defined with `[[header]]` lines matching `path`, in order.
+    pub fn array_of_tables_mut<'a>(
+        &'a mut self,
+        path: &'a [&'a str],
+    ) -> impl Iterator<Item = &'a mut Table> {
+        self.sections
+            .iter_mut()
+            .filter(move |s| s.array && path_eq(&s.path, path))
+            .map(|s| &mut s.body)
+    }
+
+    /// Returns an iterator over the headers of this document, yielding the
+    /// path of each header and whether it is an array of tables header.
+    pub fn headers(&self) -> impl Iterator<Item = (&[String], bool)> {
+        self.sections.iter().map(|s| (&s.path[..], s.array))
+    }
+
+    /// Returns the table defined with the `[header]` matching `path`, adding
+    /// a new header at the end of the document if there is none yet.
+    pub fn insert_table(&mut self, path: &[&str]) -> &mut Table {
+        let idx = match self
+            .sections
+            .iter()
+            .position(|s| !s.array && path_eq(&s.path, path))
+        {
+            Some(idx) => idx,
+            None => self.push_section(path, false),
+        };
+        &mut self.sections[idx].body
+    }
+
+    /// Appends a new table to the array of tables at `path` by adding a
+    /// `[[header]]` at the end of the document, returning the new table.
+    pub fn push_array_of_tables(&mut self, path: &[&str]) -> &mut Table {
+        let idx = self.push_section(path, true);
+        &mut self.sections[idx].body
+    }
+
+    /// Removes the table defined with the `[header]` matching `path` along
+    /// with the comments preceding its header, returning its contents.
+    pub fn remove_table(&mut self, path: &[&str]) -> Option<Table> {
+        let idx = self
+            .sections
+            .iter()
+            .position(|s| !s.array && path_eq(&s.path, path))?;
+        Some(self.sections.remove(idx).body)
+    }
+
+    fn push_section(&mut self, path: &[&str], array: bool) -> usize {
+        let key = path.iter().map(|k| encode_key(k)).collect::<Vec<_>>();
+        let repr = if array {
+            format!("[[{}]]", key.join("."))
+        } else {
+            format!("[{}]", key.join("."))
+        };
+        // Comments at the end of the document stay above the new header, and
+        // the header is separated from whatever precedes it by a blank line.
+        let mut prefix = mem::take(&mut self.trailing);
+        let empty = !self.bom && self.root.entries.is_empty() && self.sections.is_empty();
+        if !(empty && prefix.is_empty()) {
+            prefix.push('\n');
+        }
+        self.sections.push(Section {
+            prefix,
+            repr,
+            path: path.iter().map(|k| k.to_string()).collect(),
+            array,
+            suffix: "\n".to_string(),
+            body: Table::new(),
+        });
+        self.sections.len() - 1
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Document, Error> {
+        Parser::new(s).document()
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dst = String::new();
+        if self.bom {
+            dst.push('\u{feff}');
+        }
+        self.root.emit_body(&mut dst)?;
+        for section in &self.sections {
+            start_line(&mut dst);
+            dst.push_str(&section.prefix);
+            dst.push_str(&section.repr);
+            dst.push_str(&section.suffix);
+            section.body.emit_body(&mut dst)?;
+        }
+        dst.push_str(&self.trailing);
+        f.write_str(&dst)
+    }
+}
+
+impl Table {
+    /// Creates a new, empty table.
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    /// Returns the number of entries in this table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if this table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if this table contains an entry for `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the value of the entry defined with the single key `key`.
+    ///
+    /// Entries defined with dotted keys can be looked up with
+    /// [`Table::get_dotted`].
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.get_dotted(&[key])
+    }
+
+    /// Returns a mutable reference to the value of the entry defined with the
+    /// single key `key`.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.get_dotted_mut(&[key])
+    }
+
+    /// Returns the value of the entry defined with the dotted key `path`.
+    pub fn get_dotted(&self, path: &[&str]) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|e| path_eq(&e.key, path))
+            .map(|e| &e.value)
+    }
+
+    /// Returns a mutable reference to the value of the entry defined with the
+    /// dotted key `path`.
+    pub fn get_dotted_mut(&mut self, path: &[&str]) -> Option<&mut Value> {
+        self.entries
+            .iter_mut()
+            .find(|e| path_eq(&e.key, path))
+            .map(|e| &mut e.value)
+    }
+
+    /// Sets the value of `key`, returning the previous value if there was one.
+    ///
+    /// An existing entry keeps its position, comments and whitespace, only
+    /// the value itself is replaced. New entries are appended to the table.
+    pub fn insert<V: Into<Value>>(&mut self, key: &str, value: V) -> Option<Value> {
+        let value = value.into();
+        if let Some(prev) = self.get_mut(key) {
+            return Some(mem::replace(prev, value));
+        }
+
+        let mut entry = Entry {
+            prefix: String::new(),
+            key: vec![key.to_string()],
+            repr: format!("{} ", encode_key(key)),
+            value_prefix: " ".to_string(),
+            value,
+            suffix: String::new(),
+        };
+        if self.inline {
+            entry.prefix.push(' ');
+            match self.entries.last_mut() {
+                Some(last) => entry.suffix = mem::take(&mut last.suffix),
+                None => {
+                    entry.suffix.push(' ');
+                    self.trailing.clear();
+                }
+            }
+        } else {
+            entry.suffix.push('\n');
+        }
+        self.entries.push(entry);
+        None
+    }
+
+    /// Removes the entry defined with the single key `key` along with the
+    /// comments preceding it, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let idx = self.entries.iter().position(|e| path_eq(&e.key, &[key]))?;
+        let entry = self.entries.remove(idx);
+        if self.inline && idx == self.entries.len() {
+            if let Some(last) = self.entries.last_mut() {
+                last.suffix = entry.suffix;
+            }
+        }
+        Some(entry.value)
+    }
+
+    /// Returns an iterator over the entries of this table, yielding the
+    /// (possibly dotted) key of each entry along with its value.
+    pub fn iter(&self) -> TableIter<'_> {
+        TableIter {
+            iter: self.entries.iter(),
+        }
+    }
+
+    fn emit_body(&self, dst: &mut String) -> fmt::Result {
+        for entry in &self.entries {
+            start_line(dst);
+            entry.emit(dst)?;
+        }
+        Ok(())
+    }
+
+    fn emit_inline(&self, dst: &mut String) -> fmt::Result {
+        dst.push('{');
+        for (i, entry) in self.entries.iter().enumerate() {
+            entry.emit(dst)?;
+            if i + 1 < self.entries.len() || self.trailing_comma {
+                dst.push(',');
+            }
+        }
+        dst.push_str(&self.trailing);
+        dst.push('}');
+        Ok(())
+    }
+}
+
+impl Entry {
+    fn emit(&self, dst: &mut String) -> fmt::Result {
+        dst.push_str(&self.prefix);
+        dst.push_str(&self.repr);
+        dst.push('=');
+        dst.push_str(&self.value_prefix);
+        self.value.emit(dst)?;
+        dst.push_str(&self.suffix);
+        Ok(())
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dst = String::new();
+        if self.inline {
+            self.emit_inline(&mut dst)?;
+        } else {
+            self.emit_body(&mut dst)?;
+        }
+        f.write_str(&dst)
+    }
+}
+
+/// Iterator over the entries of a [`Table`], created by [`Table::iter`].
+pub struct TableIter<'a> {
+    iter: slice::Iter<'a, Entry>,
+}
+
+impl<'a> Iterator for TableIter<'a> {
+    type Item = (&'a [String], &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|e| (&e.key[..], &e.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl Array {
+    /// Creates a new, empty array.
+    pub fn new() -> Array {
+        Array::default()
+    }
+
+    /// Returns the number of values in this array.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if this array has no values.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the value at `index`, if any.
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.items.get(index).map(|i| &i.value)
+    }
+
+    /// Returns a mutable reference to the value at `index`, if any.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Value> {
+        self.items.get_mut(index).map(|i| &mut i.value)
+    }
+
+    /// Appends a value to the end of this array.
+    ///
+    /// The new value is laid out like the values already in the array, so
+    /// that appending to an array written one value per line adds a new line.
+    pub fn push<V: Into<Value>>(&mut self, value: V) {
+        let mut item = Item {
+            prefix: String::new(),
+            value: value.into(),
+            suffix: String::new(),
+        };
+        match self.items.last_mut() {
+            Some(last) => {
+                item.prefix = if last.prefix.contains('\n') {
+                    last.prefix.clone()
+                } else {
+                    " ".to_string()
+                };
+                item.suffix = mem::take(&mut last.suffix);
+            }
+            None => self.trailing.clear(),
+        }
+        self.items.push(item);
+    }
+
+    /// Removes and returns the value at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Value {
+        let item = self.items.remove(index);
+        if index == self.items.len() {
+            if let Some(last) = self.items.last_mut() {
+                last.suffix = item.suffix;
+            }
+        }
+        item.value
+    }
+
+    /// Returns an iterator over the values of this array.
+    pub fn iter(&self) -> ArrayIter<'_> {
+        ArrayIter {
+            iter: self.items.iter(),
+        }
+    }
+
+    fn emit(&self, dst: &mut String) -> fmt::Result {
+        dst.push('[');
+        for (i, item) in self.items.iter().enumerate() {
+            dst.push_str(&item.prefix);
+            item.value.emit(dst)?;
+            dst.push_str(&item.suffix);
+            if i + 1 < self.items.len() || self.trailing_comma {
+                dst.push(',');
+            }
+        }
+        dst.push_str(&self.trailing);
+        dst.push(']');
+        Ok(())
+    }
+}
+
+impl fmt::Display for Array {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dst = String::new();
+        self.emit(&mut dst)?;
+        f.write_str(&dst)
+    }
+}
+
+/// Iterator over the values of an [`Array`], created by [`Array::iter`].
+pub struct ArrayIter<'a> {
+    iter: slice::Iter<'a, Item>,
+}
+
+impl<'a> Iterator for ArrayIter<'a> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        self.iter.next().map(|i| &i.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> Formatted<T> {
+    /// Creates a new value without any original formatting.
+    pub fn new(value: T) -> Formatted<T> {
+        Formatted { value, repr: None }
+    }
+
+    /// Returns the value itself.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the text this value was parsed from, or `None` if the value
+    /// was not parsed from a document.
+    pub fn repr(&self) -> Option<&str> {
+        self.repr.as_deref()
+    }
+}
+
+impl Value {
+    /// Extracts the string of this value if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(&s.value),
+            _ => None,
+        }
+    }
+
+    /// Extracts the integer value if it is an integer.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(i.value),
+            _ => None,
+        }
+    }
+
+    /// Extracts the float value if it is a float.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(f.value),
+            _ => None,
+        }
+    }
+
+    /// Extracts the boolean value if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(b.value),
+            _ => None,
+        }
+    }
+
+    /// Extracts the datetime value if it is a datetime.
+    pub fn as_datetime(&self) -> Option<&Datetime> {
+        match self {
+            Value::Datetime(d) => Some(&d.value),
+            _ => None,
+        }
+    }
+
+    /// Extracts the array value if it is an array.
+    pub fn as_array(&self) -> Option<&Array> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Extracts the array value if it is an array.
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Extracts the table value if it is an inline table.
+    pub fn as_inline_table(&self) -> Option<&Table> {
+        match self {
+            Value::InlineTable(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Extracts the table value if it is an inline table.
+    pub fn as_inline_table_mut(&mut self) -> Option<&mut Table> {
+        match self {
+            Value::InlineTable(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this and another value have the same type.
+    pub fn same_type(&self, other: &Value) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+
+    /// Returns a human-readable representation of the type of this value.
+    pub fn type_str(&self) -> &'static str {
+        match self {
+            Value::String(..) => "string",
+            Value::Integer(..) => "integer",
+            Value::Float(..) => "float",
+            Value::Boolean(..) => "boolean",
+            Value::Datetime(..) => "datetime",
+            Value::Array(..) => "array",
+            Value::InlineTable(..) => "table",
+        }
+    }
+
+    fn emit(&self, dst: &mut String) -> fmt::Result {
+        match self {
+            Value::String(s) => match &s.repr {
+                Some(repr) => dst.push_str(repr),
+                None => encode_str(&s.value, dst)?,
+            },
+            Value::Integer(i) => match &i.repr {
+                Some(repr) => dst.push_str(repr),
+                None => write!(dst, "{}", i.value)?,
+            },
+            Value::Float(f) => match &f.repr {
+                Some(repr) => dst.push_str(repr),
+                None => encode_float(f.value, dst)?,
+            },
+            Value::Boolean(b) => match &b.repr {
+                Some(repr) => dst.push_str(repr),
+                None => write!(dst, "{}", b.value)?,
+            },
+            Value::Datetime(d) => match &d.repr {
+                Some(repr) => dst.push_str(repr),
+                None => write!(dst, "{}", d.value)?,
+            },
+            Value::Array(a) => a.emit(dst)?,
+            Value::InlineTable(t) => t.emit_inline(dst)?,
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dst = String::new();
+        self.emit(&mut dst)?;
+        f.write_str(&dst)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(val: &'a str) -> Value {
+        Value::String(Formatted::new(val.to_string()))
+    }
+}
+
+impl From<String> for Value {
+    fn from(val: String) -> Value {
+        Value::String(Formatted::new(val))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(val: i64) -> Value {
+        Value::Integer(Formatted::new(val))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Value {
+        Value::Float(Formatted::new(val))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Value {
+        Value::Boolean(Formatted::new(val))
+    }
+}
+
+impl From<Datetime> for Value {
+    fn from(val: Datetime) -> Value {
+        Value::Datetime(Formatted::new(val))
+    }
+}
+
+impl From<Array> for Value {
+    fn from(val: Array) -> Value {
+        Value::Array(val)
+    }
+}
+
+impl From<Table> for Value {
+    fn from(mut val: Table) -> Value {
+        val.inline = true;
+        for entry in val.entries.iter_mut() {
+            // Entries of a table which was previously laid out one per line
+            // are moved onto a single line.
+            if entry.suffix.contains('\n') || entry.prefix.contains('\n') {
+                entry.prefix = " ".to_string();
+                entry.suffix = String::new();
+            }
+        }
+        if let Some(last) = val.entries.last_mut() {
+            if last.suffix.is_empty() {
+                last.suffix.push(' ');
+            }
+        }
+        Value::InlineTable(val)
+    }
+}
+
+fn path_eq(path: &[String], other: &[&str]) -> bool {
+    path.len() == other.len() && path.iter().zip(other).all(|(a, b)| a == b)
+}
+
+/// Ensures that the next thing written to `dst` starts on a new line.
+fn start_line(dst: &mut String) {
+    if !dst.is_empty() && dst != "\u{feff}" && !dst.ends_with('\n') {
+        dst.push('\n');
+    }
+}
+
+fn encode_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_'));
+    if bare {
+        key.to_string()
+    } else {
+        let mut dst = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = encode_str(key, &mut dst);
+        dst
+    }
+}
+
+fn encode_str(value: &str, dst: &mut String) -> fmt::Result {
+    dst.push('"');
+    for ch in value.chars() {
+        match ch {
+            '\u{8}' => dst.push_str("\\b"),
+            '\u{9}' => dst.push_str("\\t"),
+            '\u{a}' => dst.push_str("\\n"),
+            '\u{c}' => dst.push_str("\\f"),
+            '\u{d}' => dst.push_str("\\r"),
+            '\u{22}' => dst.push_str("\\\""),
+            '\u{5c}' => dst.push_str("\\\\"),
+            c if c <= '\u{1f}' || c == '\u{7f}' => write!(dst, "\\u{:04X}", ch as u32)?,
+            ch => dst.push(ch),
+        }
+    }
+    dst.push('"');
+    Ok(())
+}
+
+fn encode_float(value: f64, dst: &mut String) -> fmt::Result {
+    match (value.is_sign_negative(), value.is_nan(), value == 0.0) {
+        (true, true, _) => write!(dst, "-nan"),
+        (false, true, _) => write!(dst, "nan"),
+        (true, false, true) => write!(dst, "-0.0"),
+        (false, false, true) => write!(dst, "0.0"),
+        (_, false, false) => write!(dst, "{}", value).and_then(|_| {
+            if value % 1.0 == 0.0 {
+                write!(dst, ".0")
+            } else {
+                Ok(())
+            }
+        }),
+    }
+}
+
+/// A dotted key along with the span of each of its parts.
+type Key<'a> = Vec<(Span, Cow<'a, str>)>;
+
+/// Parser recording the layout of a document.
+///
+/// Keys and values are read through a deserializer over the same input,
+/// which also collects the statements so that semantic errors such as
+/// duplicate keys are diagnosed just as when deserializing.
+struct Parser<'a> {
+    input: &'a str,
+    de: de::Deserializer<'a>,
+    tables: DocumentTables<'a>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            input,
+            de: DocumentTables::deserializer(input),
+            tables: DocumentTables::new(),
+        }
+    }
+
+    fn document(mut self) -> Result<Document, Error> {
+        let mut doc = Document {
+            bom: self.input.starts_with('\u{feff}'),
+            ..Document::default()
+        };
+        loop {
+            let prefix = self.decor(true)?;
+            match self.de.peek()? {
+                None => {
+                    doc.trailing = prefix;
+                    break;
+                }
+                Some((_, Token::LeftBracket)) => {
+                    let section = self.section(prefix)?;
+                    doc.sections.push(section);
+                }
+                Some(_) => {
+                    let (mut entry, key, value) = self.entry(prefix)?;
+                    self.tables.entry(&self.de, key, value)?;
+                    entry.suffix = self.line_end()?;
+                    match doc.sections.last_mut() {
+                        Some(section) => section.body.entries.push(entry),
+                        None => doc.root.entries.push(entry),
+                    }
+                }
+            }
+        }
+        self.tables.check(&mut self.de)?;
+        Ok(doc)
+    }
+
+    fn section(&mut self, prefix: String) -> Result<Section, Error> {
+        let start = self.de.current();
+        self.de.expect(Token::LeftBracket)?;
+        let array = self.de.eat(Token::LeftBracket)?;
+        self.de.set_depth(start..start + 1, array as usize)?;
+        let key = self.key()?;
+        self.de.expect(Token::RightBracket)?;
+        if array {
+            self.de.expect(Token::RightBracket)?;
+        }
+        // Values in the table are nested in it, and in the array if any.
+        let span = start..self.de.current();
+        self.de
+            .set_depth(span.clone(), key.len() + array as usize)?;
+        let repr = self.input[span.clone()].to_string();
+        let path = key.iter().map(|(_, k)| k.to_string()).collect();
+        self.tables.header(span, key, array);
+        Ok(Section {
+            prefix,
+            repr,
+            path,
+            array,
+            suffix: self.line_end()?,
+            body: Table::new(),
+        })
+    }
+
+    fn entry(&mut self, prefix: String) -> Result<(Entry, Key<'a>, de::Value<'a>), Error> {
+        let start = self.de.current();
+        let key = self.key()?;
+        let repr = self.input[start..self.de.current()].to_string();
+        self.de.expect(Token::Equals)?;
+        let value_prefix = self.decor(false)?;
+        let depth = self.de.depth();
+        let span = key[0].0.start..key[key.len() - 1].0.end;
+        self.de.set_depth(span.clone(), depth + key.len())?;
+        let (value, de_value) = self.value()?;
+        self.de.set_depth(span, depth)?;
+        let entry = Entry {
+            prefix,
+            key: key.iter().map(|(_, k)| k.to_string()).collect(),
+            repr,
+            value_prefix,
+            value,
+            suffix: String::new(),
+        };
+        Ok((entry, key, de_value))
+    }
+
+    /// Reads a possibly dotted key, along with any whitespace around it.
+    fn key(&mut self) -> Result<Key<'a>, Error> {
+        self.de.eat_whitespace()?;
+        self.de.dotted_key()
+    }
+
+    fn value(&mut self) -> Result<(Value, de::Value<'a>), Error> {
+        match self.de.peek()? {
+            Some((_, Token::LeftBracket)) => {
+                let (array, value) = self.array()?;
+                Ok((Value::Array(array), value))
+            }
+            Some((_, Token::LeftBrace)) => {
+                let (table, value) = self.inline_table()?;
+                Ok((Value::InlineTable(table), value))
+            }
+            _ => self.scalar(),
+        }
+    }
+
+    fn scalar(&mut self) -> Result<(Value, de::Value<'a>), Error> {
+        let start = self.de.current();
+        let (value, scalar) = self.de.scalar()?;
+        let repr = Some(self.input[start..self.de.current()].to_string());
+        let formatted = match scalar {
+            Scalar::String(value) => Value::String(Formatted {
+                value: value.into_owned(),
+                repr,
+            }),
+            Scalar::Integer(value) => Value::Integer(Formatted { value, repr }),
+            Scalar::Float(value) => Value::Float(Formatted { value, repr }),
+            Scalar::Boolean(value) => Value::Boolean(Formatted { value, repr }),
+            Scalar::Datetime(value) => Value::Datetime(Formatted { value, repr }),
+            Scalar::UInteger(_) => {
+                let span = start..self.de.current();
+                return Err(self.de.error(span, ErrorKind::NumberInvalid));
+            }
+        };
+        Ok((formatted, value))
+    }
+
+    fn array(&mut self) -> Result<(Array, de::Value<'a>), Error> {
+        let start = self.de.current();
+        self.de.expect(Token::LeftBracket)?;
+        let depth = self.de.depth();
+        self.de.set_depth(start..start + 1, depth + 1)?;
+        let mut array = Array::new();
+        let mut values = Vec::new();
+        loop {
+            let prefix = self.decor(true)?;
+            if self.de.eat(Token::RightBracket)? {
+                array.trailing = prefix;
+                break;
+            }
+            let (value, de_value) = self.value()?;
+            values.push(de_value);
+            let suffix = self.decor(true)?;
+            array.items.push(Item {
+                prefix,
+                value,
+                suffix,
+            });
+            array.trailing_comma = self.de.eat(Token::Comma)?;
+            if !array.trailing_comma {
+                self.de.expect(Token::RightBracket)?;
+                break;
+            }
+        }
+        self.de.set_depth(start..start + 1, depth)?;
+        let value = de::Value::array(values, start, self.de.current());
+        Ok((array, value))
+    }
+
+    fn inline_table(&mut self) -> Result<(Table, de::Value<'a>), Error> {
+        let start = self.de.current();
+        self.de.expect(Token::LeftBrace)?;
+        let mut table = Table {
+            inline: true,
+            ..Table::default()
+        };
+        let mut pairs = Vec::new();
+        loop {
+            let prefix = self.inline_decor()?;
+            // A trailing comma is only allowed as of TOML 1.1.
+            let end = table.entries.is_empty() || self.de.spec() >= TomlVersion::V1_1;
+            if end && self.de.eat(Token::RightBrace)? {
+                table.trailing = prefix;
+                break;
+            }
+            let (mut entry, key, value) = self.entry(prefix)?;
+            self.de.add_dotted_key(key, value, &mut pairs)?;
+            entry.suffix = self.inline_decor()?;
+            table.entries.push(entry);
+            table.trailing_comma = !self.de.eat(Token::RightBrace)?;
+            if !table.trailing_comma {
+                break;
+            }
+            self.de.expect(Token::Comma)?;
+        }
+        let value = de::Value::inline_table(pairs, start, self.de.current());
+        Ok((table, value))
+    }
+
+    /// Reads whitespace and comments, and newlines if `newlines` is set.
+    fn decor(&mut self, newlines: bool) -> Result<String, Error> {
+        let start = self.de.current();
+        loop {
+            match self.de.peek()? {
+                Some((_, Token::Whitespace(_))) | Some((_, Token::Comment(_))) => {}
+                Some((_, Token::Newline)) if newlines => {}
+                _ => break,
+            }
+            self.de.next()?;
+        }
+        Ok(self.input[start..self.de.current()].to_string())
+    }
+
+    /// Reads the whitespace between the entries of an inline table, which
+    /// as of TOML 1.1 includes newlines and comments.
+    fn inline_decor(&mut self) -> Result<String, Error> {
+        let start = self.de.current();
+        self.de.eat_inline_table_whitespace()?;
+        Ok(self.input[start..self.de.current()].to_string())
+    }
+
+    /// Reads the rest of the current line, up to and including the newline,
+    /// which must follow each key/value pair and table header.
+    fn line_end(&mut self) -> Result<String, Error> {
+        let start = self.de.current();
+        self.decor(false)?;
+        self.de.eat_newline_or_eof()?;
+        Ok(self.input[start..self.de.current()].to_string())
+    }
+}
@@ -0,0 +1,245 @@
+//! Overriding values with environment variables.
+//!
+//! Services configured by TOML files often let their settings be overridden
+//! by environment variables, such as `APP__DATABASE__POOL_SIZE=20` for the
+//! `pool_size` key in the `[database]` table. The [`overlay`] function
+//! applies such variables to a `Value`.
+//!
+//! ```rust
+//! let mut config: toml::Value = toml::toml! {
+//!     [database]
+//!     url = "postgres://localhost"
+//!     pool_size = 10
+//! };
+//!
+//! let vars = vec![
+//!     ("APP__DATABASE__POOL_SIZE", "20"),
+//!     ("APP__DATABASE__REPLICAS", "['db1', 'db2']"),
+//!     ("APP__LOG__LEVEL", "debug"),
+//!     ("PATH", "/usr/bin"),
+//! ];
+//! toml::env::overlay_from(&mut config, vars, "APP", "__").unwrap();
+//! assert_eq!(config["database"]["pool_size"].as_integer(), Some(20));
+//! assert_eq!(config["database"]["replicas"], toml::Value::from(vec!["db1", "db2"]));
+//! assert_eq!(config["log"]["level"].as_str(), Some("debug"));
+//! assert!(config.get("path").is_none());
+//! ```
+
+use std::error;
+use std::ffi::OsStr;
+use std::fmt;
+
+use crate::value::{Table, Value};
+
+/// Overrides values in `value` with the environment variables named with
+/// `prefix`, using `separator` between the keys of their path.
+///
+/// A variable named `{prefix}{separator}{key}{separator}{key}...` sets the
+/// value at the path made of its keys, which are converted to lowercase.
+/// Tables along the path are created as needed. The variable's value is
+/// parsed as a TOML value, such as an integer, boolean or array, falling back
+/// to a string if it isn't one.
+///
+/// If there is already a value at the path which isn't a table, the
+/// variable's value must be of the same type, or an integer for a float.
+/// Existing strings are always replaced by the variable's value as it is
+/// written, unless it is a quoted TOML string. Variables are applied in order
+/// of their names, and `value` is left unchanged if any of them fails to
+/// convert.
+///
+/// See [`overlay_from`] for the variables to be passed in explicitly.
+pub fn overlay(value: &mut Value, prefix: &str, separator: &str) -> Result<(), Error> {
+    overlay_from(value, std::env::vars_os(), prefix, separator)
+}
+
+/// Overrides values in `value` as with [`overlay`], but with the variables
+/// in `vars` rather than those of the environment.
+///
+/// # Examples
+///
+/// ```
+/// let mut config: toml::Value = "[server]\nport = 80".parse().unwrap();
+///
+/// let vars = vec![("APP__SERVER__PORT", "eighty")];
+/// let err = toml::env::overlay_from(&mut config, vars, "APP", "__").unwrap_err();
+/// assert_eq!(err.var(), "APP__SERVER__PORT");
+/// assert_eq!(
+///     err.to_string(),
+///     "invalid value for environment variable `APP__SERVER__PORT`: \
+///      expected integer for key `server.port`"
+/// );
+/// ```
+pub fn overlay_from<I, K, V>(
+    value: &mut Value,
+    vars: I,
+    prefix: &str,
+    separator: &str,
+) -> Result<(), Error>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let start = format!("{}{}", prefix, separator);
+    let mut matching = Vec::new();
+    for (name, raw) in vars {
+        let name = match name.as_ref().to_str() {
+            Some(name) if name.starts_with(&start) => name.to_string(),
+            _ => continue,
+        };
+        let raw = match raw.as_ref().to_str() {
+            Some(raw) => raw.to_string(),
+            None => return Err(Error::new(name, Vec::new(), ErrorKind::NotUnicode)),
+        };
+        matching.push((name, raw));
+    }
+    matching.sort();
+
+    let mut overlaid = value.clone();
+    for (name, raw) in matching {
+        let path = name[start.len()..]
+            .split(separator)
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if path.iter().any(|key| key.is_empty()) {
+            return Err(Error::new(name, Vec::new(), ErrorKind::InvalidName));
+        }
+        set(&mut overlaid, &path, &raw).map_err(|(path, expected)| {
+            Error::new(name, path, ErrorKind::InvalidType { expected })
+        })?;
+    }
+    *value = overlaid;
+    Ok(())
+}
+
+/// Sets the value at `path` to `raw`, converted to the type of the value it
+/// replaces. On failure returns the path of the value which has the wrong
+/// type, along with the type expected.
+fn set(root: &mut Value, path: &[String], raw: &str) -> Result<(), (Vec<String>, &'static str)> {
+    let (last, parents) = path.split_last().expect("paths are never empty");
+    let mut cur = root;
+    for (i, key) in parents.iter().enumerate() {
+        let table = match *cur {
+            Value::Table(ref mut table) => table,
+            _ => return Err((path[..i].to_vec(), "table")),
+        };
+        cur = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+    }
+    let table = match *cur {
+        Value::Table(ref mut table) => table,
+        _ => return Err((parents.to_vec(), "table")),
+    };
+
+    let parsed = parse(raw);
+    let new = match (table.get(last), parsed) {
+        (None, Some(parsed)) => parsed,
+        (None, None) => Value::String(raw.to_string()),
+        (Some(&Value::String(_)), Some(parsed)) if parsed.is_str() => parsed,
+        (Some(&Value::String(_)), _) => Value::String(raw.to_string()),
+        (Some(existing), Some(parsed)) if existing.same_type(&parsed) => parsed,
+        (Some(existing), Some(parsed))
+            if existing.type_str() == "float" && parsed.type_str() == "integer" =>
+        {
+            match parsed.as_integer() {
+                Some(i) => Value::Float(i as f64),
+                None => return Err((path.to_vec(), "float")),
+            }
+        }
+        (Some(existing), _) => return Err((path.to_vec(), existing.type_str())),
+    };
+    table.insert(last.clone(), new);
+    Ok(())
+}
+
+/// Parses `raw` as a single TOML value.
+fn parse(raw: &str) -> Option<Value> {
+    let mut value = format!("value = {}", raw).parse::<Value>().ok()?;
+    let table = value.as_table_mut()?;
+    if table.len() != 1 {
+        return None;
+    }
+    table.remove("value")
+}
+
+/// An error overriding a value with an environment variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    var: String,
+    key: Vec<String>,
+    kind: ErrorKind,
+}
+
+/// The kinds of error returned by [`overlay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The value of the variable is not valid unicode.
+    NotUnicode,
+    /// The name of the variable has an empty key, such as `APP____PORT`
+    /// with a separator of `__`.
+    InvalidName,
+    /// The variable's value, or a value along its path, is of the wrong type
+    /// for the value already there.
+    InvalidType {
+        /// The type of the value already there, as given by
+        /// `Value::type_str`.
+        expected: &'static str,
+    },
+}
+
+impl Error {
+    fn new(var: String, key: Vec<String>, kind: ErrorKind) -> Error {
+        Error { var, key, kind }
+    }
+
+    /// Returns the name of the variable this error is about.
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    /// Returns the path of keys leading to the value which has the wrong
+    /// type, for an `ErrorKind::InvalidType` error.
+    ///
+    /// This is the path of the value being set, or of a value along the way
+    /// which isn't a table.
+    pub fn key_path(&self) -> &[String] {
+        &self.key
+    }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::NotUnicode => write!(
+                f,
+                "environment variable `{}` is not valid unicode",
+                self.var
+            ),
+            ErrorKind::InvalidName => write!(
+                f,
+                "environment variable `{}` has an empty key in its name",
+                self.var
+            ),
+            ErrorKind::InvalidType { expected } => {
+                write!(
+                    f,
+                    "invalid value for environment variable `{}`: expected {}",
+                    self.var, expected
+                )?;
+                if !self.key.is_empty() {
+                    write!(f, " for key `{}`", self.key.join("."))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,442 @@
+//! Expanding references to other values within strings.
+//!
+//! Configurations often repeat base paths and hostnames. With
+//! [`interpolate`], strings can instead refer to other values in the same
+//! document with `${key.path}`, and to values from elsewhere, such as the
+//! environment, with `${scheme:name}` where `scheme` is registered with a
+//! [`Resolver`]. `$${` is written for a literal `${`.
+//!
+//! ```rust
+//! use serde_derive::Deserialize;
+//! use toml::interpolate::Resolver;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     root: String,
+//!     logs: String,
+//!     port: u16,
+//!     price: String,
+//! }
+//!
+//! let mut value: toml::Value = toml::from_str(
+//!     r#"
+//!     root = "/srv/${env:APP}"
+//!     logs = "${root}/logs"
+//!     port = "${defaults.port}"
+//!     price = "$${PRICE}"
+//!
+//!     [defaults]
+//!     port = 8080
+//!     "#,
+//! )
+//! .unwrap();
+//!
+//! let resolver = Resolver::new().scheme("env", |name| match name {
+//!     "APP" => Some("shop".to_string()),
+//!     _ => None,
+//! });
+//! toml::interpolate(&mut value, &resolver).unwrap();
+//!
+//! let config: Config = value.try_into().unwrap();
+//! assert_eq!(config.logs, "/srv/shop/logs");
+//! assert_eq!(config.port, 8080);
+//! assert_eq!(config.price, "${PRICE}");
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::error;
+use std::fmt;
+
+use crate::value::Value;
+
+/// Resolves `${scheme:name}` references for [`interpolate`].
+///
+/// A resolver has no schemes to start with, so only references to other
+/// values in the document can be expanded. Schemes are added with `scheme`,
+/// or `env` for the environment variables of the process.
+#[derive(Default)]
+pub struct Resolver {
+    schemes: Vec<(String, Scheme)>,
+}
+
+type Scheme = Box<dyn Fn(&str) -> Option<String>>;
+
+impl Resolver {
+    /// Creates a resolver with no schemes.
+    pub fn new() -> Resolver {
+        Resolver::default()
+    }
+
+    /// Resolves `${name:...}` references with `f`, which returns `None` for
+    /// names which are undefined.
+    ///
+    /// Adding a scheme with the same name again replaces the earlier one.
+    pub fn scheme<F>(mut self, name: &str, f: F) -> Resolver
+    where
+        F: Fn(&str) -> Option<String> + 'static,
+    {
+        self.schemes.retain(|(n, _)| n != name);
+        self.schemes.push((name.to_string(), Box::new(f)));
+        self
+    }
+
+    /// Resolves `${env:NAME}` references to the environment variables of the
+    /// process, treating variables which aren't valid unicode as undefined.
+    pub fn env(self) -> Resolver {
+        self.scheme("env", |name| env::var(name).ok())
+    }
+
+    fn resolve(&self, scheme: &str, name: &str) -> Option<String> {
+        self.schemes
+            .iter()
+            .find(|(n, _)| n == scheme)
+            .and_then(|(_, f)| f(name))
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resolver")
+            .field(
+                "schemes",
+                &self.schemes.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Expands the references in all of the strings within `value`.
+///
+/// `${key.path}` refers to the value at `key.path`, written as a dotted TOML
+/// key, starting at the root of `value`. A string which is nothing but a
+/// single reference is replaced by the value it refers to, keeping its type,
+/// so that `port = "${defaults.port}"` gives an integer if `defaults.port`
+/// is one. Elsewhere in a string only strings, numbers, booleans and
+/// datetimes can be referred to, and are written out as in TOML without
+/// quotes.
+///
+/// `${scheme:name}` is expanded to the string returned for `name` by the
+/// scheme registered with `resolver`. `$${` is written for a literal `${`,
+/// and any other `$` is left as it is.
+///
+/// Referenced values are expanded first, so references can be chained, but
+/// not in a cycle. Fails if a reference is malformed, undefined or cyclic,
+/// in which case `value` is left unchanged.
+pub fn interpolate(value: &mut Value, resolver: &Resolver) -> Result<(), Error> {
+    let mut pending = Vec::new();
+    find_strings(value, &mut Vec::new(), &mut pending);
+    let mut interpolator = Interpolator {
+        value: value.clone(),
+        resolver,
+        states: pending
+            .iter()
+            .map(|path| (path.clone(), State::Pending))
+            .collect(),
+        pending,
+    };
+    for i in 0..interpolator.pending.len() {
+        let path = interpolator.pending[i].clone();
+        interpolator.expand(&path)?;
+    }
+    *value = interpolator.value;
+    Ok(())
+}
+
+/// A key into a table or an index into an array.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Table(String),
+    Array(usize),
+}
+
+/// Finds the paths of all the strings within `value` which might need
+/// expanding.
+fn find_strings(value: &Value, path: &mut Vec<Key>, found: &mut Vec<Vec<Key>>) {
+    match *value {
+        Value::String(ref s) if s.contains("${") => found.push(path.clone()),
+        Value::Array(ref array) => {
+            for (i, value) in array.iter().enumerate() {
+                path.push(Key::Array(i));
+                find_strings(value, path, found);
+                path.pop();
+            }
+        }
+        Value::Table(ref table) => {
+            for (key, value) in table {
+                path.push(Key::Table(key.clone()));
+                find_strings(value, path, found);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn get<'a>(mut value: &'a Value, path: &[Key]) -> Option<&'a Value> {
+    for key in path {
+        value = match *key {
+            Key::Table(ref key) => value.get(key.as_str())?,
+            Key::Array(i) => value.get(i)?,
+        };
+    }
+    Some(value)
+}
+
+fn get_mut<'a>(mut value: &'a mut Value, path: &[Key]) -> Option<&'a mut Value> {
+    for key in path {
+        value = match *key {
+            Key::Table(ref key) => value.get_mut(key.as_str())?,
+            Key::Array(i) => value.get_mut(i)?,
+        };
+    }
+    Some(value)
+}
+
+fn key_path(path: &[Key]) -> Vec<String> {
+    path.iter()
+        .map(|key| match *key {
+            Key::Table(ref key) => key.clone(),
+            Key::Array(i) => i.to_string(),
+        })
+        .collect()
+}
+
+/// A part of a string being expanded.
+enum Part<'a> {
+    Text(&'a str),
+    Key(&'a str, Vec<String>),
+    Scheme(&'a str, &'a str, &'a str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Pending,
+    Expanding,
+    Done,
+}
+
+struct Interpolator<'r> {
+    value: Value,
+    resolver: &'r Resolver,
+    /// The paths of the strings which might need expanding.
+    pending: Vec<Vec<Key>>,
+    states: HashMap<Vec<Key>, State>,
+}
+
+impl Interpolator<'_> {
+    /// Expands the string at `path`, if it hasn't been already.
+    fn expand(&mut self, path: &[Key]) -> Result<(), Error> {
+        if self.states[path] == State::Done {
+            return Ok(());
+        }
+        self.states.insert(path.to_vec(), State::Expanding);
+
+        let s = match get(&self.value, path).and_then(Value::as_str) {
+            Some(s) => s.to_string(),
+            None => unreachable!("pending values are strings"),
+        };
+        let error = |reference: &str, kind| Error {
+            key: key_path(path),
+            reference: reference.to_string(),
+            kind,
+        };
+        let parts = parse(&s).map_err(|reference| error(reference, ErrorKind::Invalid))?;
+
+        let mut expanded = String::new();
+        let single = parts.len() == 1;
+        for part in parts {
+            let (reference, value) = match part {
+                Part::Text(text) => {
+                    expanded.push_str(text);
+                    continue;
+                }
+                Part::Scheme(reference, scheme, name) => {
+                    match self.resolver.resolve(scheme, name) {
+                        Some(value) => {
+                            expanded.push_str(&value);
+                            continue;
+                        }
+                        None => return Err(error(reference, ErrorKind::Undefined)),
+                    }
+                }
+                Part::Key(reference, target) => {
+                    // Expand the strings the value refers to first, which is
+                    // a cycle if one of them is already being expanded.
+                    let dependencies = self
+                        .pending
+                        .iter()
+                        .filter(|path| overlaps(&target, path))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for dependency in dependencies {
+                        match self.states[&dependency] {
+                            State::Pending => self.expand(&dependency)?,
+                            State::Expanding => return Err(error(reference, ErrorKind::Cycle)),
+                            State::Done => {}
+                        }
+                    }
+                    let target = target.into_iter().map(Key::Table).collect::<Vec<_>>();
+                    match get(&self.value, &target) {
+                        Some(value) => (reference, value),
+                        None => return Err(error(reference, ErrorKind::Undefined)),
+                    }
+                }
+            };
+            match *value {
+                _ if single => {
+                    let value = value.clone();
+                    *get_mut(&mut self.value, path).unwrap() = value;
+                    self.states.insert(path.to_vec(), State::Done);
+                    return Ok(());
+                }
+                Value::String(ref s) => expanded.push_str(s),
+                Value::Array(_) | Value::Table(_) => {
+                    let found = value.type_str();
+                    return Err(error(reference, ErrorKind::InvalidType { found }));
+                }
+                ref value => expanded.push_str(&value.to_string()),
+            }
+        }
+        *get_mut(&mut self.value, path).unwrap() = Value::String(expanded);
+        self.states.insert(path.to_vec(), State::Done);
+        Ok(())
+    }
+}
+
+/// Tests whether the value at the key path `target` is the value at `path`,
+/// contains it, or is within it.
+fn overlaps(target: &[String], path: &[Key]) -> bool {
+    target
+        .iter()
+        .zip(path)
+        .all(|(a, b)| *b == Key::Table(a.clone()))
+}
+
+/// Splits `s` into text and references, returning the reference which is
+/// malformed if there is one.
+fn parse(s: &str) -> Result<Vec<Part<'_>>, &str> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(i) = rest.find("${") {
+        if rest[..i].ends_with('$') {
+            parts.push(Part::Text(&rest[..i]));
+            parts.push(Part::Text("{"));
+            rest = &rest[i + 2..];
+            continue;
+        }
+        if i > 0 {
+            parts.push(Part::Text(&rest[..i]));
+        }
+        let end = match rest[i..].find('}') {
+            Some(end) => i + end + 1,
+            None => return Err(&rest[i..]),
+        };
+        let reference = &rest[i..end];
+        parts.push(parse_reference(reference).ok_or(reference)?);
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Ok(parts)
+}
+
+fn parse_reference(reference: &str) -> Option<Part<'_>> {
+    let inner = &reference[2..reference.len() - 1];
+    if let Some(colon) = inner.find(':') {
+        let scheme = &inner[..colon];
+        let bare = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if !scheme.is_empty() && scheme.chars().all(bare) {
+            return Some(Part::Scheme(reference, scheme, &inner[colon + 1..]));
+        }
+    }
+
+    // Let the parser deal with quoting and whitespace in the key.
+    if inner.contains(&['=', '#', '\n'][..]) {
+        return None;
+    }
+    let mut value = format!("{} = 0", inner).parse::<Value>().ok()?;
+    let mut path = Vec::new();
+    loop {
+        let table = match value {
+            Value::Table(ref mut table) if table.len() == 1 => table,
+            ref value if value.as_integer() == Some(0) && !path.is_empty() => {
+                return Some(Part::Key(reference, path))
+            }
+            _ => return None,
+        };
+        let key = table.keys().next().unwrap().clone();
+        let next = table.remove(&key).unwrap();
+        path.push(key);
+        value = next;
+    }
+}
+
+/// An error expanding a reference, see [`interpolate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    key: Vec<String>,
+    reference: String,
+    kind: ErrorKind,
+}
+
+/// The kinds of error returned by [`interpolate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A reference is malformed, such as a `${` with no closing `}` or an
+    /// invalid key.
+    Invalid,
+    /// A reference is to a value which doesn't exist, or to a name which the
+    /// resolver has no value for.
+    Undefined,
+    /// A reference refers back to the string it is in, directly or through
+    /// other references.
+    Cycle,
+    /// A reference within a longer string is to an array or table.
+    InvalidType {
+        /// The type of the value referred to, as given by `Value::type_str`.
+        found: &'static str,
+    },
+}
+
+impl Error {
+    /// Returns the path of keys leading to the string containing the
+    /// reference, with the indices of arrays written as numbers.
+    pub fn key_path(&self) -> &[String] {
+        &self.key
+    }
+
+    /// Returns the reference this error is about, as it is written in the
+    /// string, e.g. `${a.b}`.
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Invalid => write!(f, "invalid reference `{}`", self.reference)?,
+            ErrorKind::Undefined => write!(f, "undefined reference `{}`", self.reference)?,
+            ErrorKind::Cycle => write!(f, "cyclic reference `{}`", self.reference)?,
+            ErrorKind::InvalidType { found } => write!(
+                f,
+                "cannot interpolate {} `{}` into a string",
+                found, self.reference
+            )?,
+        }
+        if !self.key.is_empty() {
+            write!(f, " for key `{}`", self.key.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
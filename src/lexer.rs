@@ -0,0 +1,263 @@
+//! A lexer splitting TOML source into tokens.
+//!
+//! This is the tokenizer used internally by the deserializer, exposed for
+//! tools such as syntax highlighters and formatters which need to look at the
+//! raw structure of a document. Unlike the parser the lexer never gives up:
+//! every byte of the input, including whitespace, comments and newlines, is
+//! covered by exactly one token, and text which can't be lexed is yielded as
+//! a [`Token::Error`] before lexing carries on after it.
+//!
+//! ```rust
+//! use toml::lexer::{Lexer, StringKind, Token};
+//!
+//! let tokens = Lexer::new("a = 'b' # c\n")
+//!     .map(|(_, token)| token)
+//!     .collect::<Vec<_>>();
+//! assert_eq!(tokens[0], Token::Keylike("a"));
+//! assert_eq!(tokens[2], Token::Equals);
+//! match &tokens[4] {
+//!     Token::String { val, kind, .. } => {
+//!         assert_eq!(val, "b");
+//!         assert_eq!(*kind, StringKind::Literal);
+//!     }
+//!     _ => panic!(),
+//! }
+//! assert_eq!(tokens[6], Token::Comment("# c"));
+//! assert_eq!(tokens[7], Token::Newline);
+//! ```
+
+use std::borrow::Cow;
+
+use crate::de::{self, Error, TomlVersion};
+use crate::tokens::{self, Tokenizer};
+
+pub use crate::tokens::Span;
+
+/// A token of TOML source, as yielded by [`Lexer`].
+#[derive(Eq, PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub enum Token<'a> {
+    /// A run of spaces and tabs.
+    Whitespace(&'a str),
+    /// A newline, either `\n` or `\r\n`.
+    Newline,
+    /// A comment, including the leading `#` but not the trailing newline.
+    Comment(&'a str),
+
+    /// `=`
+    Equals,
+    /// `.`
+    Period,
+    /// `,`
+    Comma,
+    /// `:`
+    Colon,
+    /// `+`
+    Plus,
+    /// `{`
+    LeftBrace,
+    /// `}`
+    RightBrace,
+    /// `[`
+    LeftBracket,
+    /// `]`
+    RightBracket,
+
+    /// A bare key or the unquoted part of a value such as a number, boolean
+    /// or datetime.
+    Keylike(&'a str),
+    /// A quoted string.
+    String {
+        /// The source text of the string, including its quotes.
+        src: &'a str,
+        /// The value of the string, with escapes resolved.
+        val: Cow<'a, str>,
+        /// Which of the four kinds of string this is.
+        kind: StringKind,
+    },
+
+    /// Text which couldn't be lexed, along with the reason why.
+    Error(Error),
+}
+
+/// The kind of a string token.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum StringKind {
+    /// A `"basic"` string.
+    Basic,
+    /// A `'literal'` string.
+    Literal,
+    /// A `"""multiline basic"""` string.
+    MultilineBasic,
+    /// A `'''multiline literal'''` string.
+    MultilineLiteral,
+}
+
+impl StringKind {
+    /// Returns whether this is a kind of basic string, which can contain
+    /// escapes.
+    pub fn is_basic(&self) -> bool {
+        matches!(self, StringKind::Basic | StringKind::MultilineBasic)
+    }
+
+    /// Returns whether this is a kind of literal string.
+    pub fn is_literal(&self) -> bool {
+        !self.is_basic()
+    }
+
+    /// Returns whether this is a kind of multiline string.
+    pub fn is_multiline(&self) -> bool {
+        matches!(
+            self,
+            StringKind::MultilineBasic | StringKind::MultilineLiteral
+        )
+    }
+}
+
+impl<'a> Token<'a> {
+    /// Returns a short description of this token, such as "an equals" or
+    /// "a multiline string", as used in error messages.
+    pub fn describe(&self) -> &'static str {
+        match *self {
+            Token::Error(_) => "an invalid token",
+            Token::Whitespace(_) => tokens::Token::Whitespace("").describe(),
+            Token::Newline => tokens::Token::Newline.describe(),
+            Token::Comment(_) => tokens::Token::Comment("").describe(),
+            Token::Equals => tokens::Token::Equals.describe(),
+            Token::Period => tokens::Token::Period.describe(),
+            Token::Comma => tokens::Token::Comma.describe(),
+            Token::Colon => tokens::Token::Colon.describe(),
+            Token::Plus => tokens::Token::Plus.describe(),
+            Token::LeftBrace => tokens::Token::LeftBrace.describe(),
+            Token::RightBrace => tokens::Token::RightBrace.describe(),
+            Token::LeftBracket => tokens::Token::LeftBracket.describe(),
+            Token::RightBracket => tokens::Token::RightBracket.describe(),
+            Token::Keylike(_) => tokens::Token::Keylike("").describe(),
+            Token::String { kind, .. } => tokens::Token::String {
+                src: "",
+                val: Cow::Borrowed(""),
+                multiline: kind.is_multiline(),
+            }
+            .describe(),
+        }
+    }
+}
+
+impl<'a> From<tokens::Token<'a>> for Token<'a> {
+    fn from(token: tokens::Token<'a>) -> Token<'a> {
+        match token {
+            tokens::Token::Whitespace(s) => Token::Whitespace(s),
+            tokens::Token::Newline => Token::Newline,
+            tokens::Token::Comment(s) => Token::Comment(s),
+            tokens::Token::Equals => Token::Equals,
+            tokens::Token::Period => Token::Period,
+            tokens::Token::Comma => Token::Comma,
+            tokens::Token::Colon => Token::Colon,
+            tokens::Token::Plus => Token::Plus,
+            tokens::Token::LeftBrace => Token::LeftBrace,
+            tokens::Token::RightBrace => Token::RightBrace,
+            tokens::Token::LeftBracket => Token::LeftBracket,
+            tokens::Token::RightBracket => Token::RightBracket,
+            tokens::Token::Keylike(s) => Token::Keylike(s),
+            tokens::Token::String {
+                src,
+                val,
+                multiline,
+            } => {
+                let kind = match (src.starts_with('\''), multiline) {
+                    (false, false) => StringKind::Basic,
+                    (true, false) => StringKind::Literal,
+                    (false, true) => StringKind::MultilineBasic,
+                    (true, true) => StringKind::MultilineLiteral,
+                };
+                Token::String { src, val, kind }
+            }
+        }
+    }
+}
+
+/// An iterator over the tokens of TOML source, along with their spans.
+///
+/// A leading byte order mark is skipped and isn't covered by any token.
+#[derive(Clone)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    tokens: Tokenizer<'a>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer over the given source.
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer::with_spec(input, TomlVersion::default())
+    }
+
+    /// Creates a lexer over the given source, accepting the escapes in
+    /// strings of the given version of the TOML specification.
+    pub fn with_spec(input: &'a str, spec: TomlVersion) -> Lexer<'a> {
+        Lexer {
+            input,
+            tokens: Tokenizer::with_spec(input, spec),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = (Span, Token<'a>);
+
+    fn next(&mut self) -> Option<(Span, Token<'a>)> {
+        let before = self.tokens.clone();
+        let start = self.tokens.current();
+        let err = match self.tokens.next() {
+            Ok(token) => return token.map(|(span, token)| (span, token.into())),
+            Err(err) => err,
+        };
+
+        // Restart from the beginning of the broken token and skip past
+        // either the offending character or the whole string containing it.
+        let end = match err {
+            tokens::Error::Unexpected(at, ch) => at + ch.len_utf8(),
+            _ => string_end(self.input, start),
+        };
+        self.tokens = before;
+        while self.tokens.current() < end {
+            self.tokens.one();
+        }
+        let span = Span { start, end };
+        Some((span, Token::Error(de::token_error(self.input, err))))
+    }
+}
+
+/// Finds where the string starting at `start` ends, stopping before the end
+/// of the line for strings which can't span lines.
+fn string_end(input: &str, start: usize) -> usize {
+    let rest = &input[start..];
+    let quote = &rest[..1];
+    let triple = quote.repeat(3);
+    if rest.starts_with(&triple) {
+        return match rest[3..].find(&triple) {
+            // Up to two more quotes may be part of the string's value.
+            Some(i) => {
+                let close = start + 3 + i + 3;
+                let extra = input[close..].len() - input[close..].trim_start_matches(quote).len();
+                close + extra.min(2)
+            }
+            None => input.len(),
+        };
+    }
+
+    let mut chars = rest.char_indices().skip(1).peekable();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '\\' if quote == "\"" => {
+                if let Some((_, '\\')) | Some((_, '"')) = chars.peek() {
+                    chars.next();
+                }
+            }
+            '\n' => return start + i,
+            '\r' if rest[i + 1..].starts_with('\n') => return start + i,
+            _ if rest[i..].starts_with(quote) => return start + i + 1,
+            _ => {}
+        }
+    }
+    input.len()
+}
@@ -24,10 +24,13 @@
 //! A value in TOML is represented with the [`Value`] enum in this crate:
 //!
 //! ```rust,ignore
+//! #[non_exhaustive]
 //! pub enum Value {
 //!     String(String),
 //!     Integer(i64),
+//!     UInteger(u64),
 //!     Float(f64),
+//!     Number(Number),
 //!     Boolean(bool),
 //!     Datetime(Datetime),
 //!     Array(Array),
@@ -140,11 +143,25 @@
 //! }
 //! ```
 //!
+//! ## Editing TOML
+//!
+//! When a document needs to be modified without losing its comments and
+//! layout, the [`document::Document`] type can be used instead. It prints
+//! back exactly what it parsed, with only the edited parts reformatted.
+//!
+//! ## Layered configuration
+//!
+//! Applications reading their configuration from several files can use a
+//! [`config::Loader`] to merge them, keeping track of which file each value
+//! came from so that errors point at the right place. Settings can then
+//! be overridden from environment variables with [`env::overlay`], and
+//! references between values expanded with [`interpolate()`].
+//!
 //! [TOML]: https://github.com/toml-lang/toml
 //! [Cargo]: https://crates.io/
 //! [`serde`]: https://serde.rs/
 
-#![doc(html_root_url = "https://docs.rs/toml/0.5")]
+#![doc(html_root_url = "https://docs.rs/toml/0.6")]
 #![deny(missing_docs)]
 #![warn(rust_2018_idioms)]
 // Makes rustc abort compilation if there are any unsafe blocks in the crate.
@@ -158,19 +175,38 @@ pub mod value;
 #[doc(no_inline)]
 pub use crate::value::Value;
 mod datetime;
+mod merge;
+mod number;
+#[doc(no_inline)]
+pub use crate::value::Number;
 
 pub mod ser;
 #[doc(no_inline)]
-pub use crate::ser::{to_string, to_string_pretty, to_vec, Serializer};
+pub use crate::ser::{
+    to_string, to_string_pretty, to_vec, to_writer, to_writer_pretty, Serializer,
+};
 pub mod de;
 #[doc(no_inline)]
-pub use crate::de::{from_slice, from_str, Deserializer};
+pub use crate::de::{from_reader, from_slice, from_str, Deserializer};
 mod tokens;
 
+pub mod lexer;
+
+pub mod document;
+
+pub mod config;
+
+pub mod env;
+
+pub mod interpolate;
+#[doc(no_inline)]
+pub use crate::interpolate::interpolate;
+
 #[doc(hidden)]
 pub mod macros;
 
-mod spanned;
+pub mod spanned;
+#[doc(no_inline)]
 pub use crate::spanned::Spanned;
 
 // Just for rustdoc
@@ -0,0 +1,278 @@
+//! Merging one `Value` into another, see `Value::merge`.
+
+use std::error;
+use std::fmt;
+use std::mem;
+
+use crate::value::Value;
+
+/// How `Value::merge` combines two arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ArrayMerge {
+    /// The array being merged in replaces the existing one, the default.
+    #[default]
+    Replace,
+    /// The values of the array being merged in are added to the end of the
+    /// existing one.
+    Append,
+    /// Like `Append`, but leaving out values which are already in the
+    /// existing array.
+    Union,
+}
+
+/// What `Value::merge` does when merging a value into one of a different
+/// type, such as a string into a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum TypeConflict {
+    /// Fails with a `MergeError`, the default.
+    #[default]
+    Error,
+    /// The value being merged in replaces the existing one.
+    Override,
+}
+
+/// How `Value::merge` combines the values at a particular path, see
+/// `MergeOptions::path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MergeStrategy {
+    /// Merges as for any other path: tables are merged key by key, arrays
+    /// are combined as set by `MergeOptions::arrays` and other values are
+    /// replaced.
+    Merge,
+    /// Replaces the existing value as a whole, even a table, whatever its
+    /// type.
+    Replace,
+    /// Appends arrays, as `ArrayMerge::Append`. Other values are merged as
+    /// with `Merge`.
+    Append,
+    /// Unites arrays, as `ArrayMerge::Union`. Other values are merged as
+    /// with `Merge`.
+    Union,
+}
+
+/// Options for `Value::merge`.
+///
+/// Options are built up from `MergeOptions::new`, which merges tables key
+/// by key, replaces arrays and other values, and fails if a value would be
+/// replaced by one of a different type.
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    arrays: ArrayMerge,
+    type_conflicts: TypeConflict,
+    paths: Vec<(Vec<String>, MergeStrategy)>,
+}
+
+impl MergeOptions {
+    /// Creates the default options.
+    pub fn new() -> MergeOptions {
+        MergeOptions::default()
+    }
+
+    /// Sets how arrays are combined.
+    pub fn arrays(mut self, arrays: ArrayMerge) -> MergeOptions {
+        self.arrays = arrays;
+        self
+    }
+
+    /// Sets what to do when merging a value into one of a different type.
+    pub fn type_conflicts(mut self, conflicts: TypeConflict) -> MergeOptions {
+        self.type_conflicts = conflicts;
+        self
+    }
+
+    /// Sets how the values at `path` are combined, overriding the other
+    /// options.
+    ///
+    /// Each element of `path` is a key into a table, starting at the value
+    /// being merged into. The strategy only applies to the value at `path`
+    /// itself; values within it are merged as usual. Setting a strategy for
+    /// the same path again replaces the earlier one.
+    pub fn path(mut self, path: &[&str], strategy: MergeStrategy) -> MergeOptions {
+        let path = path.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        self.paths.retain(|(p, _)| *p != path);
+        self.paths.push((path, strategy));
+        self
+    }
+
+    fn strategy(&self, path: &[String]) -> MergeStrategy {
+        self.paths
+            .iter()
+            .find(|(p, _)| *p == path)
+            .map_or(MergeStrategy::Merge, |&(_, strategy)| strategy)
+    }
+}
+
+/// What `Value::merge` changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    overridden: Vec<Vec<String>>,
+}
+
+impl MergeReport {
+    /// Returns the paths of the values which were replaced by a different
+    /// value, in the order they were merged.
+    ///
+    /// Keys which were only added, values replaced by an equal one and
+    /// arrays which were appended to are not included.
+    pub fn overridden(&self) -> &[Vec<String>] {
+        &self.overridden
+    }
+}
+
+/// An error merging two values, when a value would be replaced by one of a
+/// different type. See `TypeConflict`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeError {
+    path: Vec<String>,
+    existing: &'static str,
+    merged: &'static str,
+}
+
+impl MergeError {
+    /// Returns the path of keys leading to the conflicting values.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Returns the types of the existing value and of the value being merged
+    /// into it, as given by `Value::type_str`.
+    pub fn types(&self) -> (&'static str, &'static str) {
+        (self.existing, self.merged)
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot merge {} into {}", self.merged, self.existing)?;
+        if !self.path.is_empty() {
+            write!(f, " for key `{}`", self.path.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for MergeError {}
+
+impl Value {
+    /// Merges `other` into this value, such as a file of local settings into
+    /// the defaults.
+    ///
+    /// By default tables are merged key by key, and any other value in
+    /// `other` replaces the value at the same path in `self`. See
+    /// `MergeOptions` for how this can be changed. A value replaced by one of
+    /// a different type is an error unless `TypeConflict::Override` is set;
+    /// `self` is left unchanged if an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use toml::value::{ArrayMerge, MergeOptions};
+    /// use toml::Value;
+    ///
+    /// let mut config: Value = toml::from_str(
+    ///     "[server]\nhost = 'localhost'\nport = 80\nplugins = ['auth']",
+    /// )
+    /// .unwrap();
+    /// let local: Value = toml::from_str("[server]\nport = 8080\nplugins = ['gzip']").unwrap();
+    ///
+    /// let options = MergeOptions::new().arrays(ArrayMerge::Append);
+    /// let report = config.merge(local, &options).unwrap();
+    /// assert_eq!(config["server"]["host"].as_str(), Some("localhost"));
+    /// assert_eq!(config["server"]["port"].as_integer(), Some(8080));
+    /// assert_eq!(config["server"]["plugins"], Value::from(vec!["auth", "gzip"]));
+    /// assert_eq!(report.overridden(), [vec!["server", "port"]]);
+    /// ```
+    pub fn merge(
+        &mut self,
+        other: Value,
+        options: &MergeOptions,
+    ) -> Result<MergeReport, MergeError> {
+        let mut path = Vec::new();
+        if options.type_conflicts == TypeConflict::Error {
+            check(self, &other, &mut path, options)?;
+        }
+        let mut report = MergeReport::default();
+        merge(self, other, &mut path, options, &mut report);
+        Ok(report)
+    }
+}
+
+/// Checks that merging `other` into `value` involves no type conflicts.
+fn check(
+    value: &Value,
+    other: &Value,
+    path: &mut Vec<String>,
+    options: &MergeOptions,
+) -> Result<(), MergeError> {
+    if options.strategy(path) == MergeStrategy::Replace {
+        return Ok(());
+    }
+    match (value, other) {
+        (Value::Table(table), Value::Table(other)) => {
+            for (key, other) in other {
+                if let Some(value) = table.get(key) {
+                    path.push(key.clone());
+                    check(value, other, path, options)?;
+                    path.pop();
+                }
+            }
+            Ok(())
+        }
+        _ if value.same_type(other) => Ok(()),
+        _ => Err(MergeError {
+            path: path.clone(),
+            existing: value.type_str(),
+            merged: other.type_str(),
+        }),
+    }
+}
+
+fn merge(
+    value: &mut Value,
+    mut other: Value,
+    path: &mut Vec<String>,
+    options: &MergeOptions,
+    report: &mut MergeReport,
+) {
+    let strategy = options.strategy(path);
+    let arrays = match strategy {
+        MergeStrategy::Replace => return replace(value, other, path, report),
+        MergeStrategy::Append => ArrayMerge::Append,
+        MergeStrategy::Union => ArrayMerge::Union,
+        MergeStrategy::Merge => options.arrays,
+    };
+    match (value, &mut other) {
+        (Value::Table(table), Value::Table(other)) => {
+            for (key, other) in mem::take(other) {
+                match table.get_mut(&key) {
+                    Some(value) => {
+                        path.push(key);
+                        merge(value, other, path, options, report);
+                        path.pop();
+                    }
+                    None => {
+                        table.insert(key, other);
+                    }
+                }
+            }
+        }
+        (Value::Array(values), Value::Array(other)) if arrays != ArrayMerge::Replace => {
+            for other in other.drain(..) {
+                if arrays == ArrayMerge::Append || !values.contains(&other) {
+                    values.push(other);
+                }
+            }
+        }
+        (value, _) => replace(value, other, path, report),
+    }
+}
+
+fn replace(value: &mut Value, other: Value, path: &[String], report: &mut MergeReport) {
+    if *value != other {
+        report.overridden.push(path.to_vec());
+        *value = other;
+    }
+}
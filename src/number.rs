@@ -0,0 +1,301 @@
+//! A TOML number kept as the text it was written as.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de, ser};
+
+pub(crate) const FIELD: &str = "$__toml_private_number";
+pub(crate) const NAME: &str = "$__toml_private_Number";
+
+/// A TOML integer or float, kept as the text it was written as.
+///
+/// With the `arbitrary_precision` feature numbers of any size can be read,
+/// and those which an `i64` or `f64` can't represent exactly are parsed into
+/// `Value::Number` so that no precision is lost.
+/// `de::Options::preserve_number_format` parses all numbers within the usual
+/// range into `Value::Number`. Serializing a `Number` writes its text back
+/// unchanged, including any underscores, sign, radix prefix or exponent.
+///
+/// Types such as decimals which need the exact value of a number can
+/// deserialize a `Number` and parse the text returned by `as_str`, which
+/// with the `arbitrary_precision` feature is always the text written in the
+/// document. Numbers can also be deserialized into any of the primitive
+/// number types which can represent them.
+///
+/// # Examples
+///
+/// ```
+/// use toml::Number;
+///
+/// let price: Number = "19.990".parse().unwrap();
+/// assert_eq!(price.as_str(), "19.990");
+/// assert_eq!(price.as_f64(), Some(19.99));
+///
+/// let mask: Number = "0xffff_ffff".parse().unwrap();
+/// assert_eq!(mask.as_i64(), Some(0xffff_ffff));
+/// assert!("1.".parse::<Number>().is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Number {
+    raw: String,
+}
+
+impl Number {
+    /// Returns the text of this number, as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Tests whether this number is a float.
+    pub fn is_float(&self) -> bool {
+        is_float(&self.raw)
+    }
+
+    /// Tests whether this number is an integer.
+    pub fn is_integer(&self) -> bool {
+        !self.is_float()
+    }
+
+    /// Returns the value of this number if it is an integer which fits in an
+    /// `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.integer(i64::from_str_radix)
+    }
+
+    /// Returns the value of this number if it is an integer which fits in a
+    /// `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.integer(u64::from_str_radix)
+    }
+
+    /// Returns the value of this number as an `f64`, which may be rounded.
+    ///
+    /// Integers are converted as well, except for those written in hex,
+    /// octal or binary which don't fit in a `u64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        if self.is_float() || !self.has_radix_prefix() {
+            return self.raw.replace('_', "").parse().ok();
+        }
+        self.as_u64().map(|u| u as f64)
+    }
+
+    fn has_radix_prefix(&self) -> bool {
+        radix(&self.raw) != 10
+    }
+
+    fn integer<T, E>(&self, parse: fn(&str, u32) -> Result<T, E>) -> Option<T> {
+        if self.is_float() {
+            return None;
+        }
+        let digits = self.raw.replace('_', "");
+        let radix = radix(&digits);
+        let digits = if radix == 10 { &digits } else { &digits[2..] };
+        parse(digits, radix).ok()
+    }
+
+    /// Creates a number from its text, which has already been validated.
+    pub(crate) fn from_raw(raw: String) -> Number {
+        Number { raw }
+    }
+
+    fn from_f64(f: f64) -> Number {
+        let raw = match (f.is_sign_negative(), f.is_nan(), f == 0.0) {
+            (true, true, _) => "-nan".to_string(),
+            (false, true, _) => "nan".to_string(),
+            (true, false, true) => "-0.0".to_string(),
+            (false, false, true) => "0.0".to_string(),
+            (_, false, false) if f % 1.0 == 0.0 => format!("{}.0", f),
+            (_, false, false) => f.to_string(),
+        };
+        Number::from_raw(raw)
+    }
+}
+
+fn radix(s: &str) -> u32 {
+    if s.starts_with("0x") {
+        16
+    } else if s.starts_with("0o") {
+        8
+    } else if s.starts_with("0b") {
+        2
+    } else {
+        10
+    }
+}
+
+/// Tests whether the text of a valid number is that of a float.
+pub(crate) fn is_float(s: &str) -> bool {
+    radix(s) == 10 && s.contains(&['.', 'e', 'E', 'i', 'n'][..])
+}
+
+/// Tests whether the number written as `s` is represented exactly by an
+/// `i64`, or with `unsigned` a `u64`, or by an `f64` if it is a float.
+pub(crate) fn is_exact(s: &str, unsigned: bool) -> bool {
+    let number = Number::from_raw(s.to_string());
+    if !number.is_float() {
+        return number.as_i64().is_some() || unsigned && number.as_u64().is_some();
+    }
+    if s.ends_with("inf") || s.ends_with("nan") {
+        return true;
+    }
+    match number.as_f64() {
+        Some(f) if f.is_finite() => {
+            let exact = decimal(&format!("{:e}", f));
+            exact.is_some() && exact == decimal(s)
+        }
+        _ => false,
+    }
+}
+
+/// Splits a decimal float into its sign, its significant digits and the
+/// power of ten they are multiplied by, so that equal values compare equal.
+fn decimal(s: &str) -> Option<(bool, String, i64)> {
+    let s = s.replace('_', "");
+    let negative = s.starts_with('-');
+    let s = s.trim_start_matches(&['+', '-'][..]);
+    let (mantissa, exponent) = match s.find(&['e', 'E'][..]) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    let trimmed = digits.trim_end_matches('0');
+    let exponent = exponent - frac.len() as i64 + (digits.len() - trimmed.len()) as i64;
+    if trimmed.is_empty() {
+        return Some((negative, String::new(), 0));
+    }
+    Some((negative, trimmed.to_string(), exponent))
+}
+
+/// Returns the value of the number written as `s` if it is an integer which
+/// fits in a `u64` but not in an `i64`.
+pub(crate) fn as_large_u64(s: &str) -> Option<u64> {
+    let number = Number::from_raw(s.to_string());
+    match number.as_i64() {
+        Some(_) => None,
+        None => number.as_u64(),
+    }
+}
+
+/// Visits the value of the number written as `s` with the narrowest type
+/// which can represent it.
+pub(crate) fn visit<'de, V, E>(s: &str, visitor: V) -> Result<V::Value, E>
+where
+    V: de::Visitor<'de>,
+    E: de::Error,
+{
+    let number = Number::from_raw(s.to_string());
+    if number.is_float() {
+        match number.as_f64().expect("float was validated") {
+            f if f.is_finite() || s.ends_with("inf") || s.ends_with("nan") => visitor.visit_f64(f),
+            _ => Err(E::custom(format!("float `{}` is too large", s))),
+        }
+    } else if let Some(i) = number.as_i64() {
+        visitor.visit_i64(i)
+    } else if let Some(u) = number.as_u64() {
+        visitor.visit_u64(u)
+    } else if let Some(i) = number.integer(i128::from_str_radix) {
+        visitor.visit_i128(i)
+    } else if let Some(u) = number.integer(u128::from_str_radix) {
+        visitor.visit_u128(u)
+    } else {
+        Err(E::custom(format!("integer `{}` is too large", s)))
+    }
+}
+
+impl FromStr for Number {
+    type Err = crate::de::Error;
+
+    fn from_str(s: &str) -> Result<Number, crate::de::Error> {
+        crate::de::validate_number(s)?;
+        Ok(Number::from_raw(s.to_string()))
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.raw.fmt(f)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Number {
+        Number::from_raw(i.to_string())
+    }
+}
+
+impl From<u64> for Number {
+    fn from(u: u64) -> Number {
+        Number::from_raw(u.to_string())
+    }
+}
+
+impl ser::Serialize for Number {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        // Other formats see the text of the number, which the serializers of
+        // this crate write as a number instead.
+        serializer.serialize_newtype_struct(NAME, &self.raw)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Number, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct NumberVisitor;
+
+        impl<'de> de::Visitor<'de> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a TOML number")
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Number, E> {
+                Ok(Number::from(value))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Number, E> {
+                Ok(Number::from(value))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Number, E> {
+                Ok(Number::from_f64(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Number, E> {
+                value.parse().map_err(de::Error::custom)
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Number, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                deserializer.deserialize_any(self)
+            }
+
+            fn visit_map<V>(self, mut visitor: V) -> Result<Number, V::Error>
+            where
+                V: de::MapAccess<'de>,
+            {
+                match visitor.next_key::<String>()? {
+                    Some(ref key) if key == FIELD => {}
+                    _ => return Err(de::Error::custom("expected a number")),
+                }
+                let raw: String = visitor.next_value()?;
+                raw.parse().map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, NumberVisitor)
+    }
+}
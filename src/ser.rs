@@ -1,7 +1,7 @@
 //! Serializing Rust structures into TOML.
 //!
 //! This module contains all the Serde support for serializing Rust structures
-//! into TOML documents (as strings). Note that some top-level functions here
+//! into TOML documents (as strings or into any `io::Write`). Note that some top-level functions here
 //! are also provided at the top of the crate.
 //!
 //! Note that the TOML format has a restriction that if a table itself contains
@@ -26,12 +26,16 @@
 //! ```
 
 use std::cell::Cell;
+use std::convert::TryFrom;
 use std::error;
 use std::fmt::{self, Write};
+use std::io;
 use std::marker;
 use std::rc::Rc;
 
 use crate::datetime;
+use crate::de::TomlVersion;
+use crate::number;
 use serde::ser;
 
 /// Serialize the given data structure as a TOML byte vector.
@@ -106,6 +110,36 @@ where
     Ok(dst)
 }
 
+/// Serialize the given data structure as TOML into the IO stream provided.
+///
+/// Output is written as it is produced, so for anything but in-memory
+/// writers it is usually worth wrapping `writer` in an `io::BufWriter`.
+///
+/// Serialization can fail for the same reasons as `to_string`, or if writing
+/// to `writer` fails, in which case `Error::Io` is returned.
+pub fn to_writer<W, T: ?Sized>(mut writer: W, value: &T) -> Result<(), Error>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    value.serialize(&mut Serializer::from_writer(&mut writer))
+}
+
+/// Serialize the given data structure as "pretty" TOML into the IO stream
+/// provided.
+///
+/// This is identical to `to_writer` except the output has a more "pretty"
+/// output. See `Serializer::pretty` for more details.
+pub fn to_writer_pretty<W, T: ?Sized>(mut writer: W, value: &T) -> Result<(), Error>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let mut ser = Serializer::from_writer(&mut writer);
+    ser.pretty_string(true).pretty_array(true);
+    value.serialize(&mut ser)
+}
+
 /// Errors that can occur when serializing a type.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Error {
@@ -146,6 +180,12 @@ pub enum Error {
     /// type.
     Custom(String),
 
+    /// Writing the output to an `io::Write` failed.
+    ///
+    /// This contains the kind of the underlying I/O error along with its
+    /// description.
+    Io(io::ErrorKind, String),
+
     #[doc(hidden)]
     __Nonexhaustive,
 }
@@ -184,6 +224,8 @@ impl StringSettings {
 struct Settings {
     array: Option<ArraySettings>,
     string: Option<StringSettings>,
+    spec: TomlVersion,
+    unsigned_integers: bool,
 }
 
 /// Serialization implementation for TOML.
@@ -193,14 +235,50 @@ struct Settings {
 /// datatypes in Rust, such as enums, tuples, and tuple structs. These types
 /// will generate an error when serialized.
 ///
-/// Currently a serializer always writes its output to an in-memory `String`,
-/// which is passed in when creating the serializer itself.
+/// A serializer writes its output either to an in-memory `String`, see
+/// `Serializer::new`, or to any `io::Write`, see `Serializer::from_writer`.
 pub struct Serializer<'a> {
-    dst: &'a mut String,
+    dst: Dst<'a>,
     state: State<'a>,
     settings: Rc<Settings>,
 }
 
+/// Where a serializer emits its output.
+enum Dst<'a> {
+    String(&'a mut String),
+    Writer(&'a mut dyn io::Write),
+}
+
+impl<'a> Dst<'a> {
+    fn reborrow(&mut self) -> Dst<'_> {
+        match self {
+            Dst::String(s) => Dst::String(s),
+            Dst::Writer(w) => Dst::Writer(w),
+        }
+    }
+
+    fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        match *self {
+            Dst::String(ref mut dst) => {
+                dst.push_str(s);
+                Ok(())
+            }
+            Dst::Writer(ref mut dst) => dst.write_all(s.as_bytes()).map_err(Error::from),
+        }
+    }
+
+    fn push(&mut self, c: char) -> Result<(), Error> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<(), Error> {
+        match *self {
+            Dst::String(ref mut dst) => dst.write_fmt(args).map_err(ser::Error::custom),
+            Dst::Writer(ref mut dst) => dst.write_fmt(args).map_err(Error::from),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 enum ArrayState {
     Started,
@@ -250,7 +328,21 @@ impl<'a> Serializer<'a> {
     /// will be present in `dst`.
     pub fn new(dst: &'a mut String) -> Serializer<'a> {
         Serializer {
-            dst,
+            dst: Dst::String(dst),
+            state: State::End,
+            settings: Rc::new(Settings::default()),
+        }
+    }
+
+    /// Creates a new serializer which will emit TOML into the IO stream
+    /// provided.
+    ///
+    /// Output is written piece by piece as the value is serialized, so `dst`
+    /// should usually be buffered. Failures to write are reported as
+    /// `Error::Io`.
+    pub fn from_writer<W: io::Write>(dst: &'a mut W) -> Serializer<'a> {
+        Serializer {
+            dst: Dst::Writer(dst),
             state: State::End,
             settings: Rc::new(Settings::default()),
         }
@@ -266,11 +358,13 @@ impl<'a> Serializer<'a> {
     ///   have a trailing comma. See `Serializer::pretty_array`
     pub fn pretty(dst: &'a mut String) -> Serializer<'a> {
         Serializer {
-            dst,
+            dst: Dst::String(dst),
             state: State::End,
             settings: Rc::new(Settings {
                 array: Some(ArraySettings::pretty()),
                 string: Some(StringSettings::pretty()),
+                spec: TomlVersion::default(),
+                unsigned_integers: false,
             }),
         }
     }
@@ -423,11 +517,37 @@ impl<'a> Serializer<'a> {
         self
     }
 
+    /// Selects the version of the TOML specification to emit.
+    ///
+    /// By default only syntax from TOML 1.0 is emitted, so that the output
+    /// can be read by any TOML 1.0 parser. With `TomlVersion::V1_1` control
+    /// characters in strings are written with the shorter `\e` and `\xHH`
+    /// escapes instead of `\uXXXX`.
+    ///
+    /// Nothing emitted by the serializer is newer than TOML 0.5 otherwise, so
+    /// `TomlVersion::V0_5` behaves like 1.0. Note that arrays mixing values of
+    /// different types, which 0.5 doesn't allow, are not rejected.
+    pub fn spec(&mut self, version: TomlVersion) -> &mut Self {
+        Rc::get_mut(&mut self.settings).unwrap().spec = version;
+        self
+    }
+
+    /// Enable or Disable integers larger than `i64::MAX`
+    ///
+    /// The TOML specification only requires parsers to handle 64-bit signed
+    /// integers, so by default serializing a larger `u64` or `u128` fails
+    /// with `Error::NumberInvalid`. If enabled, integers up to `u64::MAX` are
+    /// emitted, which can be read back with `de::Options::unsigned_integers`.
+    pub fn unsigned_integers(&mut self, value: bool) -> &mut Self {
+        Rc::get_mut(&mut self.settings).unwrap().unsigned_integers = value;
+        self
+    }
+
     fn display<T: fmt::Display>(&mut self, t: T, type_: ArrayState) -> Result<(), Error> {
         self.emit_key(type_)?;
-        write!(self.dst, "{}", t).map_err(ser::Error::custom)?;
+        write!(self.dst, "{}", t)?;
         if let State::Table { .. } = self.state {
-            self.dst.push_str("\n");
+            self.dst.push_str("\n")?;
         }
         Ok(())
     }
@@ -468,7 +588,7 @@ impl<'a> Serializer<'a> {
                     first.set(false);
                 }
                 self.escape_key(key)?;
-                self.dst.push_str(" = ");
+                self.dst.push_str(" = ")?;
                 Ok(())
             }
         }
@@ -478,19 +598,19 @@ impl<'a> Serializer<'a> {
         match (len, &self.settings.array) {
             (Some(0..=1), _) | (_, &None) => {
                 if first.get() {
-                    self.dst.push_str("[")
+                    self.dst.push_str("[")?
                 } else {
-                    self.dst.push_str(", ")
+                    self.dst.push_str(", ")?
                 }
             }
             (_, &Some(ref a)) => {
                 if first.get() {
-                    self.dst.push_str("[\n")
+                    self.dst.push_str("[\n")?
                 } else {
-                    self.dst.push_str(",\n")
+                    self.dst.push_str(",\n")?
                 }
                 for _ in 0..a.indent {
-                    self.dst.push_str(" ");
+                    self.dst.push_str(" ")?;
                 }
             }
         }
@@ -515,7 +635,7 @@ impl<'a> Serializer<'a> {
                 _ => false,
             });
         if ok {
-            write!(self.dst, "{}", key).map_err(ser::Error::custom)?;
+            write!(self.dst, "{}", key)?;
         } else {
             self.emit_str(key, true)?;
         }
@@ -617,46 +737,53 @@ impl<'a> Serializer<'a> {
             Repr::Literal(literal, ty) => {
                 // A pretty string
                 match ty {
-                    Type::NewlineTripple => self.dst.push_str("'''\n"),
-                    Type::OnelineTripple => self.dst.push_str("'''"),
-                    Type::OnelineSingle => self.dst.push('\''),
+                    Type::NewlineTripple => self.dst.push_str("'''\n")?,
+                    Type::OnelineTripple => self.dst.push_str("'''")?,
+                    Type::OnelineSingle => self.dst.push('\'')?,
                 }
-                self.dst.push_str(&literal);
+                self.dst.push_str(&literal)?;
                 match ty {
-                    Type::OnelineSingle => self.dst.push('\''),
-                    _ => self.dst.push_str("'''"),
+                    Type::OnelineSingle => self.dst.push('\'')?,
+                    _ => self.dst.push_str("'''")?,
                 }
             }
             Repr::Std(ty) => {
                 match ty {
-                    Type::NewlineTripple => self.dst.push_str("\"\"\"\n"),
+                    Type::NewlineTripple => self.dst.push_str("\"\"\"\n")?,
                     // note: OnelineTripple can happen if do_pretty wants to do
                     // '''it's one line'''
                     // but settings.string.literal == false
-                    Type::OnelineSingle | Type::OnelineTripple => self.dst.push('"'),
+                    Type::OnelineSingle | Type::OnelineTripple => self.dst.push('"')?,
                 }
                 for ch in value.chars() {
                     match ch {
-                        '\u{8}' => self.dst.push_str("\\b"),
-                        '\u{9}' => self.dst.push_str("\\t"),
+                        '\u{8}' => self.dst.push_str("\\b")?,
+                        '\u{9}' => self.dst.push_str("\\t")?,
                         '\u{a}' => match ty {
-                            Type::NewlineTripple => self.dst.push('\n'),
-                            Type::OnelineSingle => self.dst.push_str("\\n"),
+                            Type::NewlineTripple => self.dst.push('\n')?,
+                            Type::OnelineSingle => self.dst.push_str("\\n")?,
                             _ => unreachable!(),
                         },
-                        '\u{c}' => self.dst.push_str("\\f"),
-                        '\u{d}' => self.dst.push_str("\\r"),
-                        '\u{22}' => self.dst.push_str("\\\""),
-                        '\u{5c}' => self.dst.push_str("\\\\"),
+                        '\u{c}' => self.dst.push_str("\\f")?,
+                        '\u{d}' => self.dst.push_str("\\r")?,
+                        '\u{22}' => self.dst.push_str("\\\"")?,
+                        '\u{5c}' => self.dst.push_str("\\\\")?,
+                        '\u{1b}' if self.settings.spec >= TomlVersion::V1_1 => {
+                            self.dst.push_str("\\e")?
+                        }
                         c if c <= '\u{1f}' || c == '\u{7f}' => {
-                            write!(self.dst, "\\u{:04X}", ch as u32).map_err(ser::Error::custom)?;
+                            if self.settings.spec >= TomlVersion::V1_1 {
+                                write!(self.dst, "\\x{:02X}", ch as u32)?;
+                            } else {
+                                write!(self.dst, "\\u{:04X}", ch as u32)?;
+                            }
                         }
-                        ch => self.dst.push(ch),
+                        ch => self.dst.push(ch)?,
                     }
                 }
                 match ty {
-                    Type::NewlineTripple => self.dst.push_str("\"\"\""),
-                    Type::OnelineSingle | Type::OnelineTripple => self.dst.push('"'),
+                    Type::NewlineTripple => self.dst.push_str("\"\"\"")?,
+                    Type::OnelineSingle | Type::OnelineTripple => self.dst.push('"')?,
                 }
             }
         }
@@ -698,32 +825,32 @@ impl<'a> Serializer<'a> {
                 if !first.get() {
                     // Newline if we are a table that is not the first
                     // table in the document.
-                    self.dst.push('\n');
+                    self.dst.push('\n')?;
                 }
             }
             State::Array { parent, first, .. } => {
                 if !first.get() {
                     // Always newline if we are not the first item in the
                     // table-array
-                    self.dst.push('\n');
+                    self.dst.push('\n')?;
                 } else if let State::Table { first, .. } = *parent {
                     if !first.get() {
                         // Newline if we are not the first item in the document
-                        self.dst.push('\n');
+                        self.dst.push('\n')?;
                     }
                 }
             }
             _ => {}
         }
-        self.dst.push_str("[");
+        self.dst.push_str("[")?;
         if array_of_tables {
-            self.dst.push_str("[");
+            self.dst.push_str("[")?;
         }
         self.emit_key_part(state)?;
         if array_of_tables {
-            self.dst.push_str("]");
+            self.dst.push_str("]")?;
         }
-        self.dst.push_str("]\n");
+        self.dst.push_str("]\n")?;
         Ok(())
     }
 
@@ -740,7 +867,7 @@ impl<'a> Serializer<'a> {
                 table_emitted.set(true);
                 let first = self.emit_key_part(parent)?;
                 if !first {
-                    self.dst.push_str(".");
+                    self.dst.push_str(".")?;
                 }
                 self.escape_key(key)?;
                 Ok(false)
@@ -764,11 +891,10 @@ macro_rules! serialize_float {
                     Ok(())
                 }
             }),
-        }
-        .map_err(ser::Error::custom)?;
+        }?;
 
         if let State::Table { .. } = $this.state {
-            $this.dst.push_str("\n");
+            $this.dst.push_str("\n")?;
         }
         return Ok(());
     }};
@@ -818,9 +944,26 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
     }
 
     fn serialize_u64(self, v: u64) -> Result<(), Self::Error> {
+        if v > i64::MAX as u64 && !self.settings.unsigned_integers {
+            return Err(Error::NumberInvalid);
+        }
         self.display(v, ArrayState::Started)
     }
 
+    fn serialize_i128(self, v: i128) -> Result<(), Self::Error> {
+        if v < 0 {
+            let v = i64::try_from(v).map_err(|_| Error::NumberInvalid)?;
+            self.serialize_i64(v)
+        } else {
+            self.serialize_u128(v as u128)
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Self::Error> {
+        let v = u64::try_from(v).map_err(|_| Error::NumberInvalid)?;
+        self.serialize_u64(v)
+    }
+
     fn serialize_f32(self, v: f32) -> Result<(), Self::Error> {
         serialize_float!(self, v)
     }
@@ -838,7 +981,7 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
         self.emit_key(ArrayState::Started)?;
         self.emit_str(value, false)?;
         if let State::Table { .. } = self.state {
-            self.dst.push_str("\n");
+            self.dst.push_str("\n")?;
         }
         Ok(())
     }
@@ -878,12 +1021,17 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
 
     fn serialize_newtype_struct<T: ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
     {
+        if name == number::NAME {
+            // The text of a `Number` is written as it is, without quotes.
+            self.array_type(ArrayState::Started)?;
+            return value.serialize(DateStrEmitter(self));
+        }
         value.serialize(self)
     }
 
@@ -981,7 +1129,7 @@ impl<'a, 'b> ser::SerializeSeq for SerializeSeq<'a, 'b> {
         T: ser::Serialize,
     {
         value.serialize(&mut Serializer {
-            dst: &mut *self.ser.dst,
+            dst: self.ser.dst.reborrow(),
             state: State::Array {
                 parent: &self.ser.state,
                 first: &self.first,
@@ -999,23 +1147,23 @@ impl<'a, 'b> ser::SerializeSeq for SerializeSeq<'a, 'b> {
             Some(ArrayState::StartedAsATable) => return Ok(()),
             Some(ArrayState::Started) => match (self.len, &self.ser.settings.array) {
                 (Some(0..=1), _) | (_, &None) => {
-                    self.ser.dst.push_str("]");
+                    self.ser.dst.push_str("]")?;
                 }
                 (_, &Some(ref a)) => {
                     if a.trailing_comma {
-                        self.ser.dst.push_str(",");
+                        self.ser.dst.push_str(",")?;
                     }
-                    self.ser.dst.push_str("\n]");
+                    self.ser.dst.push_str("\n]")?;
                 }
             },
             None => {
                 assert!(self.first.get());
                 self.ser.emit_key(ArrayState::Started)?;
-                self.ser.dst.push_str("[]")
+                self.ser.dst.push_str("[]")?
             }
         }
         if let State::Table { .. } = self.ser.state {
-            self.ser.dst.push_str("\n");
+            self.ser.dst.push_str("\n")?;
         }
         Ok(())
     }
@@ -1101,7 +1249,7 @@ impl<'a, 'b> ser::SerializeMap for SerializeTable<'a, 'b> {
                 ..
             } => {
                 let res = value.serialize(&mut Serializer {
-                    dst: &mut *ser.dst,
+                    dst: ser.dst.reborrow(),
                     state: State::Table {
                         key,
                         parent: &ser.state,
@@ -1157,7 +1305,7 @@ impl<'a, 'b> ser::SerializeStruct for SerializeTable<'a, 'b> {
                 ..
             } => {
                 let res = value.serialize(&mut Serializer {
-                    dst: &mut *ser.dst,
+                    dst: ser.dst.reborrow(),
                     state: State::Table {
                         key,
                         parent: &ser.state,
@@ -1541,6 +1689,7 @@ impl fmt::Display for Error {
             Error::NumberInvalid => "a serialized number was invalid".fmt(f),
             Error::UnsupportedNone => "unsupported None value".fmt(f),
             Error::Custom(ref s) => s.fmt(f),
+            Error::Io(_, ref s) => write!(f, "I/O error: {}", s),
             Error::KeyNewline => unreachable!(),
             Error::ArrayMixedType => unreachable!(),
             Error::__Nonexhaustive => panic!(),
@@ -1550,6 +1699,12 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {}
 
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e.kind(), e.to_string())
+    }
+}
+
 impl ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Error {
         Error::Custom(msg.to_string())
@@ -1,8 +1,16 @@
+//! Values annotated with the span at which they are defined in the source.
+
 use serde::{de, ser};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::datetime::Datetime;
+use crate::number::Number;
+use crate::value::{Table, Value};
 
 pub(crate) const NAME: &str = "$__toml_private_Spanned";
 pub(crate) const START: &str = "$__toml_private_start";
@@ -42,6 +50,14 @@ pub struct Spanned<T> {
 }
 
 impl<T> Spanned<T> {
+    pub(crate) fn new(span: Range<usize>, value: T) -> Spanned<T> {
+        Spanned {
+            start: span.start,
+            end: span.end,
+            value,
+        }
+    }
+
     /// Access the start of the span of the contained value.
     pub fn start(&self) -> usize {
         self.start
@@ -166,3 +182,282 @@ impl<T: ser::Serialize> ser::Serialize for Spanned<T> {
         self.value.serialize(serializer)
     }
 }
+
+/// A TOML value which records the span of every value and table key in it.
+///
+/// This mirrors `Value`, but is parsed directly from a TOML document rather
+/// than through serde, so spans are available for any value in the document
+/// without knowing its structure up front. Each variant holds its contents as
+/// a `Spanned`, and the keys of tables are `Spanned` as well.
+///
+/// The span of a table defined by a `[table]` header is the header itself,
+/// and the span of a table which is only defined implicitly, by a header or a
+/// dotted key naming one of its subtables, is the key naming it. An array of
+/// tables spans its first `[[header]]`.
+///
+/// # Examples
+///
+/// ```
+/// use toml::spanned::SpannedValue;
+///
+/// let input = "[server]\nport = 80\n";
+/// let value = input.parse::<SpannedValue>().unwrap();
+///
+/// let server = value.get("server").unwrap();
+/// assert_eq!(&input[server.start()..server.end()], "[server]");
+/// let port = server.get("port").unwrap();
+/// assert_eq!(port.span(), (16, 18));
+/// assert_eq!(port.as_integer(), Some(80));
+///
+/// let (key, _) = server.as_table().unwrap().get_key_value("port").unwrap();
+/// assert_eq!(key.span(), (9, 13));
+///
+/// let value = toml::Value::from(value);
+/// assert_eq!(value["server"]["port"].as_integer(), Some(80));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpannedValue {
+    /// Represents a TOML string
+    String(Spanned<String>),
+    /// Represents a TOML integer
+    Integer(Spanned<i64>),
+    /// Represents a TOML integer larger than `i64::MAX`, see `Value::UInteger`
+    UInteger(Spanned<u64>),
+    /// Represents a TOML float
+    Float(Spanned<f64>),
+    /// Represents a TOML integer or float kept as the text it was written as,
+    /// see `Value::Number`
+    Number(Spanned<Number>),
+    /// Represents a TOML boolean
+    Boolean(Spanned<bool>),
+    /// Represents a TOML datetime
+    Datetime(Spanned<Datetime>),
+    /// Represents a TOML array
+    Array(Spanned<Vec<SpannedValue>>),
+    /// Represents a TOML table
+    Table(Spanned<SpannedTable>),
+}
+
+impl SpannedValue {
+    /// Access the start of the span of this value.
+    pub fn start(&self) -> usize {
+        self.span().0
+    }
+
+    /// Access the end of the span of this value.
+    pub fn end(&self) -> usize {
+        self.span().1
+    }
+
+    /// Get the span of this value.
+    pub fn span(&self) -> (usize, usize) {
+        match *self {
+            SpannedValue::String(ref s) => s.span(),
+            SpannedValue::Integer(ref i) => i.span(),
+            SpannedValue::UInteger(ref i) => i.span(),
+            SpannedValue::Float(ref f) => f.span(),
+            SpannedValue::Number(ref n) => n.span(),
+            SpannedValue::Boolean(ref b) => b.span(),
+            SpannedValue::Datetime(ref d) => d.span(),
+            SpannedValue::Array(ref a) => a.span(),
+            SpannedValue::Table(ref t) => t.span(),
+        }
+    }
+
+    /// Looks up the value of `key` if this is a table.
+    pub fn get(&self, key: &str) -> Option<&SpannedValue> {
+        self.as_table().and_then(|t| t.get(key))
+    }
+
+    /// Extracts the integer value if it is an integer.
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            SpannedValue::Integer(ref i) => Some(*i.get_ref()),
+            SpannedValue::Number(ref n) => n.get_ref().as_i64(),
+            _ => None,
+        }
+    }
+
+    /// Extracts the integer value as a `u64` if it is a non-negative integer,
+    /// including one larger than `i64::MAX`.
+    pub fn as_uinteger(&self) -> Option<u64> {
+        match *self {
+            SpannedValue::Integer(ref i) if *i.get_ref() >= 0 => Some(*i.get_ref() as u64),
+            SpannedValue::UInteger(ref i) => Some(*i.get_ref()),
+            SpannedValue::Number(ref n) => n.get_ref().as_u64(),
+            _ => None,
+        }
+    }
+
+    /// Extracts the float value if it is a float.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            SpannedValue::Float(ref f) => Some(*f.get_ref()),
+            SpannedValue::Number(ref n) if n.get_ref().is_float() => n.get_ref().as_f64(),
+            _ => None,
+        }
+    }
+
+    /// Extracts the number if it is a number kept as text.
+    pub fn as_number(&self) -> Option<&Number> {
+        match *self {
+            SpannedValue::Number(ref n) => Some(n.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Extracts the boolean value if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            SpannedValue::Boolean(ref b) => Some(*b.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Extracts the string of this value if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            SpannedValue::String(ref s) => Some(s.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Extracts the datetime value if it is a datetime.
+    pub fn as_datetime(&self) -> Option<&Datetime> {
+        match *self {
+            SpannedValue::Datetime(ref d) => Some(d.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Extracts the array value if it is an array.
+    pub fn as_array(&self) -> Option<&Vec<SpannedValue>> {
+        match *self {
+            SpannedValue::Array(ref a) => Some(a.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Extracts the table value if it is a table.
+    pub fn as_table(&self) -> Option<&SpannedTable> {
+        match *self {
+            SpannedValue::Table(ref t) => Some(t.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Returns a human-readable representation of the type of this value.
+    pub fn type_str(&self) -> &'static str {
+        match *self {
+            SpannedValue::String(..) => "string",
+            SpannedValue::Integer(..) | SpannedValue::UInteger(..) => "integer",
+            SpannedValue::Float(..) => "float",
+            SpannedValue::Number(ref n) if n.get_ref().is_float() => "float",
+            SpannedValue::Number(..) => "integer",
+            SpannedValue::Boolean(..) => "boolean",
+            SpannedValue::Datetime(..) => "datetime",
+            SpannedValue::Array(..) => "array",
+            SpannedValue::Table(..) => "table",
+        }
+    }
+}
+
+impl FromStr for SpannedValue {
+    type Err = crate::de::Error;
+    fn from_str(s: &str) -> Result<SpannedValue, Self::Err> {
+        crate::de::parse_spanned(s)
+    }
+}
+
+impl From<SpannedValue> for Value {
+    fn from(value: SpannedValue) -> Value {
+        match value {
+            SpannedValue::String(s) => Value::String(s.into_inner()),
+            SpannedValue::Integer(i) => Value::Integer(i.into_inner()),
+            SpannedValue::UInteger(i) => Value::UInteger(i.into_inner()),
+            SpannedValue::Float(f) => Value::Float(f.into_inner()),
+            SpannedValue::Number(n) => Value::Number(n.into_inner()),
+            SpannedValue::Boolean(b) => Value::Boolean(b.into_inner()),
+            SpannedValue::Datetime(d) => Value::Datetime(d.into_inner()),
+            SpannedValue::Array(a) => {
+                Value::Array(a.into_inner().into_iter().map(Value::from).collect())
+            }
+            SpannedValue::Table(t) => Value::Table(t.into_inner().into()),
+        }
+    }
+}
+
+/// The table of a `SpannedValue`, mapping spanned keys to spanned values.
+///
+/// Unlike `Table`, keys are kept in the order in which they first appear in
+/// the document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpannedTable {
+    entries: Vec<(Spanned<String>, SpannedValue)>,
+}
+
+impl SpannedTable {
+    /// Returns the number of keys in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the table contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if the table contains a value for `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the value of `key`, if present.
+    pub fn get(&self, key: &str) -> Option<&SpannedValue> {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    /// Returns the spanned key along with its value, if present.
+    pub fn get_key_value(&self, key: &str) -> Option<(&Spanned<String>, &SpannedValue)> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.get_ref() == key)
+            .map(|(k, v)| (k, v))
+    }
+
+    /// Iterates over the keys and values of the table, in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Spanned<String>, &SpannedValue)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    /// Returns the entry for `key`, inserting the value made by `f` if there
+    /// is none yet.
+    pub(crate) fn entry<F>(
+        &mut self,
+        key: Spanned<String>,
+        f: F,
+    ) -> &mut (Spanned<String>, SpannedValue)
+    where
+        F: FnOnce() -> SpannedValue,
+    {
+        let i = match self.entries.iter().position(|(k, _)| *k == key) {
+            Some(i) => i,
+            None => {
+                let value = f();
+                self.entries.push((key, value));
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[i]
+    }
+}
+
+impl From<SpannedTable> for Table {
+    fn from(table: SpannedTable) -> Table {
+        table
+            .entries
+            .into_iter()
+            .map(|(k, v)| (k.into_inner(), Value::from(v)))
+            .collect()
+    }
+}
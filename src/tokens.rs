@@ -5,6 +5,7 @@ use std::string;
 use std::string::String as StdString;
 
 use self::Token::*;
+use crate::de::TomlVersion;
 
 /// A span, designating a range of bytes where a token is located.
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
@@ -55,9 +56,10 @@ pub enum Error {
     Unexpected(usize, char),
     UnterminatedString(usize),
     NewlineInTableKey(usize),
-    MultilineStringKey(usize),
+    MultilineStringKey(Span),
+    StringTooLong(Span),
     Wanted {
-        at: usize,
+        span: Span,
         expected: &'static str,
         found: &'static str,
     },
@@ -67,6 +69,8 @@ pub enum Error {
 pub struct Tokenizer<'a> {
     input: &'a str,
     chars: CrlfFold<'a>,
+    spec: TomlVersion,
+    max_string_len: usize,
 }
 
 #[derive(Clone)]
@@ -81,18 +85,32 @@ enum MaybeString {
 }
 
 impl<'a> Tokenizer<'a> {
+    #[cfg(test)]
     pub fn new(input: &'a str) -> Tokenizer<'a> {
+        Tokenizer::with_spec(input, TomlVersion::default())
+    }
+
+    pub fn with_spec(input: &'a str, spec: TomlVersion) -> Tokenizer<'a> {
         let mut t = Tokenizer {
             input,
             chars: CrlfFold {
                 chars: input.char_indices(),
             },
+            spec,
+            max_string_len: usize::MAX,
         };
         // Eat utf-8 BOM
         t.eatc('\u{feff}');
         t
     }
 
+    /// Sets the longest string which is accepted, in bytes once unescaped.
+    /// Longer strings are rejected as soon as the limit is reached, before
+    /// the rest of them is read.
+    pub fn set_max_string_len(&mut self, max: usize) {
+        self.max_string_len = max;
+    }
+
     pub fn next(&mut self) -> Result<Option<(Span, Token<'a>)>, Error> {
         let (start, token) = match self.one() {
             Some((start, '\n')) => (start, Newline),
@@ -156,21 +174,20 @@ impl<'a> Tokenizer<'a> {
 
     /// Expect the given token returning its span.
     pub fn expect_spanned(&mut self, expected: Token<'a>) -> Result<Span, Error> {
-        let current = self.current();
         match self.next()? {
             Some((span, found)) => {
                 if expected == found {
                     Ok(span)
                 } else {
                     Err(Error::Wanted {
-                        at: current,
+                        span,
                         expected: expected.describe(),
                         found: found.describe(),
                     })
                 }
             }
             None => Err(Error::Wanted {
-                at: self.input.len(),
+                span: self.eof_span(),
                 expected: expected.describe(),
                 found: "eof",
             }),
@@ -178,7 +195,6 @@ impl<'a> Tokenizer<'a> {
     }
 
     pub fn table_key(&mut self) -> Result<(Span, Cow<'a, str>), Error> {
-        let current = self.current();
         match self.next()? {
             Some((span, Token::Keylike(k))) => Ok((span, k.into())),
             Some((
@@ -191,20 +207,20 @@ impl<'a> Tokenizer<'a> {
             )) => {
                 let offset = self.substr_offset(src);
                 if multiline {
-                    return Err(Error::MultilineStringKey(offset));
+                    return Err(Error::MultilineStringKey(span));
                 }
                 match src.find('\n') {
                     None => Ok((span, val)),
                     Some(i) => Err(Error::NewlineInTableKey(offset + i)),
                 }
             }
-            Some((_, other)) => Err(Error::Wanted {
-                at: current,
+            Some((span, other)) => Err(Error::Wanted {
+                span,
                 expected: "a table key",
                 found: other.describe(),
             }),
             None => Err(Error::Wanted {
-                at: self.input.len(),
+                span: self.eof_span(),
                 expected: "a table key",
                 found: "eof",
             }),
@@ -227,11 +243,10 @@ impl<'a> Tokenizer<'a> {
     }
 
     pub fn eat_newline_or_eof(&mut self) -> Result<(), Error> {
-        let current = self.current();
         match self.next()? {
             None | Some((_, Token::Newline)) => Ok(()),
-            Some((_, other)) => Err(Error::Wanted {
-                at: current,
+            Some((span, other)) => Err(Error::Wanted {
+                span,
                 expected: "newline",
                 found: other.describe(),
             }),
@@ -257,7 +272,7 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    pub fn current(&mut self) -> usize {
+    pub fn current(&self) -> usize {
         self.chars
             .clone()
             .next()
@@ -265,6 +280,12 @@ impl<'a> Tokenizer<'a> {
             .unwrap_or_else(|| self.input.len())
     }
 
+    /// The empty span at the end of the input.
+    fn eof_span(&self) -> Span {
+        let end = self.input.len();
+        Span { start: end, end }
+    }
+
     pub fn input(&self) -> &'a str {
         self.input
     }
@@ -314,6 +335,10 @@ impl<'a> Tokenizer<'a> {
         let mut n = 0;
         'outer: loop {
             n += 1;
+            if val.len(self.current()) > self.max_string_len {
+                let end = self.current();
+                return Err(Error::StringTooLong(Span { start, end }));
+            }
             match self.one() {
                 Some((i, '\n')) => {
                     if multiline {
@@ -385,6 +410,10 @@ impl<'a> Tokenizer<'a> {
                     Some((_, 'n')) => val.push('\n'),
                     Some((_, 'r')) => val.push('\r'),
                     Some((_, 't')) => val.push('\t'),
+                    Some((_, 'e')) if me.spec >= TomlVersion::V1_1 => val.push('\u{1b}'),
+                    Some((i, 'x')) if me.spec >= TomlVersion::V1_1 => {
+                        val.push(me.hex(start, i, 2)?);
+                    }
                     Some((i, c @ 'u')) | Some((i, c @ 'U')) => {
                         let len = if c == 'u' { 4 } else { 8 };
                         val.push(me.hex(start, i, len)?);
@@ -499,6 +528,15 @@ impl<'a> Iterator for CrlfFold<'a> {
 }
 
 impl MaybeString {
+    /// The length of the string so far, when the input has been read up to
+    /// `current`.
+    fn len(&self, current: usize) -> usize {
+        match *self {
+            MaybeString::NotEscaped(start) => current - start,
+            MaybeString::Owned(ref s) => s.len(),
+        }
+    }
+
     fn push(&mut self, ch: char) {
         match *self {
             MaybeString::NotEscaped(..) => {}
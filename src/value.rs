@@ -1,10 +1,15 @@
 //! Definition of a TOML value
 
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::fmt;
 use std::hash::Hash;
-use std::mem::discriminant;
+use std::iter;
+use std::marker::PhantomData;
+use std::mem;
 use std::ops;
+use std::slice;
 use std::str::FromStr;
 use std::vec;
 
@@ -14,18 +19,53 @@ use serde::ser;
 
 use crate::datetime::{self, DatetimeFromString};
 pub use crate::datetime::{Date, Datetime, DatetimeParseError, Offset, Time};
+use crate::number;
+pub use crate::number::Number;
 
+use crate::map;
 pub use crate::map::{Entry, Map};
+pub use crate::merge::{
+    ArrayMerge, MergeError, MergeOptions, MergeReport, MergeStrategy, TypeConflict,
+};
+
+/// The newtype struct `Value` and `ValueRef` are deserialized as, so that
+/// this crate's deserializers can pass them integers larger than `i64::MAX`.
+pub(crate) const NAME: &str = "$__toml_private_Value";
+/// The private field an integer larger than `i64::MAX` is deserialized from.
+pub(crate) const UNSIGNED_FIELD: &str = "$__toml_private_unsigned";
+/// The newtype struct a `Value::UInteger` is serialized as, so that it is
+/// only converted back into one by this crate's serializers.
+pub(crate) const UNSIGNED_NAME: &str = "$__toml_private_UInteger";
 
 /// Representation of a TOML value.
-#[derive(PartialEq, Clone, Debug)]
+///
+/// Dropping, comparing, formatting and serializing a value recurse once per
+/// level of nesting, which is bounded by `Limits::max_depth` for values
+/// parsed from TOML. Values built by other means can be cloned to any depth,
+/// and dropped with `Value::drop_iteratively` once nested too deeply for the
+/// stack.
+#[derive(PartialEq, Debug)]
+#[non_exhaustive]
 pub enum Value {
     /// Represents a TOML string
     String(String),
     /// Represents a TOML integer
     Integer(i64),
+    /// Represents a TOML integer larger than `i64::MAX`
+    ///
+    /// Integers which fit in an `i64` are always represented as `Integer`.
+    /// These are only parsed when `de::Options::unsigned_integers` is enabled,
+    /// and only written out by a `Serializer` with `unsigned_integers` set.
+    /// Displaying a value writes them out regardless.
+    UInteger(u64),
     /// Represents a TOML float
     Float(f64),
+    /// Represents a TOML integer or float kept as the text it was written as
+    ///
+    /// Numbers are only parsed into this, rather than `Integer`, `UInteger`
+    /// or `Float`, with `de::Options::preserve_number_format`, or with the
+    /// `arbitrary_precision` feature if they can't be represented exactly.
+    Number(Number),
     /// Represents a TOML boolean
     Boolean(bool),
     /// Represents a TOML datetime
@@ -54,7 +94,9 @@ impl Value {
     where
         T: ser::Serialize,
     {
-        value.serialize(Serializer)
+        value.serialize(Serializer {
+            unsigned_integers: false,
+        })
     }
 
     /// Interpret a `toml::Value` as an instance of type `T`.
@@ -73,6 +115,47 @@ impl Value {
         de::Deserialize::deserialize(self)
     }
 
+    /// Interpret the value at `path` within a `toml::Value` as an instance of
+    /// type `T`.
+    ///
+    /// Each element of `path` is a key into a table, starting at this value.
+    /// Returns `Ok(None)` if there is no value at `path`, and fails if any of
+    /// the values along the way is not a table. Errors include the path of
+    /// keys leading to where the conversion failed, as with
+    /// `toml::de::from_str_at`.
+    ///
+    /// ```
+    /// let value: toml::Value = toml::from_str("[a.b]\nc = 1").unwrap();
+    /// let c = value.clone().try_into_at::<i64>(&["a", "b", "c"]).unwrap();
+    /// assert_eq!(c, Some(1));
+    /// assert_eq!(value.try_into_at::<i64>(&["a", "d"]).unwrap(), None);
+    /// ```
+    pub fn try_into_at<'de, T>(self, path: &[&str]) -> Result<Option<T>, crate::de::Error>
+    where
+        T: de::Deserialize<'de>,
+    {
+        let with_context = |keys: &[&str], mut err: crate::de::Error| {
+            for key in keys.iter().rev() {
+                err.add_key_context(key);
+            }
+            err
+        };
+        let mut value = self;
+        for (i, key) in path.iter().enumerate() {
+            let mut table = value
+                .try_into::<Table>()
+                .map_err(|e| with_context(&path[..i], e))?;
+            value = match table.remove(*key) {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+        }
+        value
+            .try_into()
+            .map(Some)
+            .map_err(|e| with_context(path, e))
+    }
+
     /// Index into a TOML array or map. A string index can be used to access a
     /// value in a map, and a usize index can be used to access an element of an
     /// array.
@@ -101,6 +184,7 @@ impl Value {
     pub fn as_integer(&self) -> Option<i64> {
         match *self {
             Value::Integer(i) => Some(i),
+            Value::Number(ref n) => n.as_i64(),
             _ => None,
         }
     }
@@ -110,10 +194,22 @@ impl Value {
         self.as_integer().is_some()
     }
 
+    /// Extracts the integer value as a `u64` if it is a non-negative integer,
+    /// including one larger than `i64::MAX`.
+    pub fn as_uinteger(&self) -> Option<u64> {
+        match *self {
+            Value::Integer(i) if i >= 0 => Some(i as u64),
+            Value::UInteger(i) => Some(i),
+            Value::Number(ref n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
     /// Extracts the float value if it is a float.
     pub fn as_float(&self) -> Option<f64> {
         match *self {
             Value::Float(f) => Some(f),
+            Value::Number(ref n) if n.is_float() => n.as_f64(),
             _ => None,
         }
     }
@@ -123,6 +219,14 @@ impl Value {
         self.as_float().is_some()
     }
 
+    /// Extracts the number if it is a number kept as text.
+    pub fn as_number(&self) -> Option<&Number> {
+        match *self {
+            Value::Number(ref n) => Some(n),
+            _ => None,
+        }
+    }
+
     /// Extracts the boolean value if it is a boolean.
     pub fn as_bool(&self) -> Option<bool> {
         match *self {
@@ -213,15 +317,17 @@ impl Value {
 
     /// Tests whether this and another value have the same type.
     pub fn same_type(&self, other: &Value) -> bool {
-        discriminant(self) == discriminant(other)
+        self.type_str() == other.type_str()
     }
 
     /// Returns a human-readable representation of the type of this value.
     pub fn type_str(&self) -> &'static str {
         match *self {
             Value::String(..) => "string",
-            Value::Integer(..) => "integer",
+            Value::Integer(..) | Value::UInteger(..) => "integer",
             Value::Float(..) => "float",
+            Value::Number(ref n) if n.is_float() => "float",
+            Value::Number(..) => "integer",
             Value::Boolean(..) => "boolean",
             Value::Datetime(..) => "datetime",
             Value::Array(..) => "array",
@@ -296,13 +402,15 @@ impl_into_value!(Integer: i32);
 impl_into_value!(Integer: i8);
 impl_into_value!(Integer: u8);
 impl_into_value!(Integer: u32);
+
 impl_into_value!(Float: f64);
 impl_into_value!(Float: f32);
+impl_into_value!(Number: Number);
 impl_into_value!(Boolean: bool);
 impl_into_value!(Datetime: Datetime);
 impl_into_value!(Table: Table);
 
-/// Types that can be used to index a `toml::Value`
+/// Types that can be used to index a `toml::Value` or a `toml::value::ValueRef`
 ///
 /// Currently this is implemented for `usize` to index arrays and `str` to index
 /// tables.
@@ -314,6 +422,10 @@ pub trait Index: Sealed {
     fn index<'a>(&self, val: &'a Value) -> Option<&'a Value>;
     #[doc(hidden)]
     fn index_mut<'a>(&self, val: &'a mut Value) -> Option<&'a mut Value>;
+    #[doc(hidden)]
+    fn index_ref<'a, 'v>(&self, val: &'a ValueRef<'v>) -> Option<&'a ValueRef<'v>>;
+    #[doc(hidden)]
+    fn index_ref_mut<'a, 'v>(&self, val: &'a mut ValueRef<'v>) -> Option<&'a mut ValueRef<'v>>;
 }
 
 /// An implementation detail that should not be implemented, this will change in
@@ -339,6 +451,20 @@ impl Index for usize {
             _ => None,
         }
     }
+
+    fn index_ref<'a, 'v>(&self, val: &'a ValueRef<'v>) -> Option<&'a ValueRef<'v>> {
+        match *val {
+            ValueRef::Array(ref a) => a.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_ref_mut<'a, 'v>(&self, val: &'a mut ValueRef<'v>) -> Option<&'a mut ValueRef<'v>> {
+        match *val {
+            ValueRef::Array(ref mut a) => a.get_mut(*self),
+            _ => None,
+        }
+    }
 }
 
 impl Index for str {
@@ -355,6 +481,20 @@ impl Index for str {
             _ => None,
         }
     }
+
+    fn index_ref<'a, 'v>(&self, val: &'a ValueRef<'v>) -> Option<&'a ValueRef<'v>> {
+        match *val {
+            ValueRef::Table(ref a) => a.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_ref_mut<'a, 'v>(&self, val: &'a mut ValueRef<'v>) -> Option<&'a mut ValueRef<'v>> {
+        match *val {
+            ValueRef::Table(ref mut a) => a.get_mut(self),
+            _ => None,
+        }
+    }
 }
 
 impl Index for String {
@@ -365,6 +505,14 @@ impl Index for String {
     fn index_mut<'a>(&self, val: &'a mut Value) -> Option<&'a mut Value> {
         self[..].index_mut(val)
     }
+
+    fn index_ref<'a, 'v>(&self, val: &'a ValueRef<'v>) -> Option<&'a ValueRef<'v>> {
+        self[..].index_ref(val)
+    }
+
+    fn index_ref_mut<'a, 'v>(&self, val: &'a mut ValueRef<'v>) -> Option<&'a mut ValueRef<'v>> {
+        self[..].index_ref_mut(val)
+    }
 }
 
 impl<'s, T: ?Sized> Index for &'s T
@@ -378,13 +526,148 @@ where
     fn index_mut<'a>(&self, val: &'a mut Value) -> Option<&'a mut Value> {
         (**self).index_mut(val)
     }
+
+    fn index_ref<'a, 'v>(&self, val: &'a ValueRef<'v>) -> Option<&'a ValueRef<'v>> {
+        (**self).index_ref(val)
+    }
+
+    fn index_ref_mut<'a, 'v>(&self, val: &'a mut ValueRef<'v>) -> Option<&'a mut ValueRef<'v>> {
+        (**self).index_ref_mut(val)
+    }
+}
+
+impl Value {
+    /// Drops this value without recursing into its arrays and tables.
+    ///
+    /// Dropping a `Value` as usual recurses once per level of nesting, which
+    /// can overflow the stack for values nested many thousands of levels
+    /// deep. This drops them one level at a time instead.
+    pub fn drop_iteratively(mut self) {
+        let mut stack = Vec::new();
+        take_nested(&mut self, &mut stack);
+        while let Some(mut value) = stack.pop() {
+            take_nested(&mut value, &mut stack);
+        }
+    }
+}
+
+/// Moves the non-empty arrays and tables in `value` onto `stack`.
+fn take_nested(value: &mut Value, stack: &mut Vec<Value>) {
+    let mut take = |value: &mut Value| {
+        let nested = match value {
+            Value::Array(a) => !a.is_empty(),
+            Value::Table(t) => !t.is_empty(),
+            _ => false,
+        };
+        if nested {
+            stack.push(mem::replace(value, Value::Boolean(false)));
+        }
+    };
+    match value {
+        Value::Array(a) => a.iter_mut().for_each(take),
+        Value::Table(t) => t.iter_mut().for_each(|(_, v)| take(v)),
+        _ => {}
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Value {
+        // Nested arrays and tables are cloned using a stack of our own rather
+        // than recursively, so that values nested to any depth can be cloned.
+        let mut stack: Vec<Cloning<'_>> = Vec::new();
+        let mut next = self;
+        loop {
+            let mut value = match next {
+                Value::String(s) => Value::String(s.clone()),
+                Value::Integer(i) => Value::Integer(*i),
+                Value::UInteger(i) => Value::UInteger(*i),
+                Value::Float(f) => Value::Float(*f),
+                Value::Number(n) => Value::Number(n.clone()),
+                Value::Boolean(b) => Value::Boolean(*b),
+                Value::Datetime(d) => Value::Datetime(d.clone()),
+                Value::Array(a) => {
+                    stack.push(Cloning::Array(a.iter(), Vec::with_capacity(a.len())));
+                    Value::Array(Vec::new())
+                }
+                Value::Table(t) => {
+                    stack.push(Cloning::Table(t.iter(), Map::new(), None));
+                    Value::Table(Map::new())
+                }
+            };
+            if let Value::Array(_) | Value::Table(_) = next {
+                // The array or table is only complete once its values are.
+                match stack.last_mut().expect("just pushed").next() {
+                    Some(v) => {
+                        next = v;
+                        continue;
+                    }
+                    None => value = stack.pop().expect("just pushed").into_value(),
+                }
+            }
+            // Add the clone to the arrays and tables it completes.
+            loop {
+                let cloning = match stack.last_mut() {
+                    Some(cloning) => cloning,
+                    None => return value,
+                };
+                cloning.push(value);
+                match cloning.next() {
+                    Some(v) => {
+                        next = v;
+                        break;
+                    }
+                    None => value = stack.pop().expect("not empty").into_value(),
+                }
+            }
+        }
+    }
+}
+
+/// An array or table being cloned by `Value::clone`, holding the values
+/// still to be cloned and the clones so far.
+enum Cloning<'a> {
+    Array(slice::Iter<'a, Value>, Array),
+    Table(map::Iter<'a>, Table, Option<String>),
+}
+
+impl<'a> Cloning<'a> {
+    fn next(&mut self) -> Option<&'a Value> {
+        match self {
+            Cloning::Array(iter, _) => iter.next(),
+            Cloning::Table(iter, _, key) => iter.next().map(|(k, v)| {
+                *key = Some(k.clone());
+                v
+            }),
+        }
+    }
+
+    /// Adds the clone of the value last returned by `next`.
+    fn push(&mut self, value: Value) {
+        match self {
+            Cloning::Array(_, array) => array.push(value),
+            Cloning::Table(_, table, key) => {
+                table.insert(key.take().expect("key of the value"), value);
+            }
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Cloning::Array(_, array) => Value::Array(array),
+            Cloning::Table(_, table, _) => Value::Table(table),
+        }
+    }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        crate::ser::to_string(self)
-            .expect("Unable to represent value as string")
-            .fmt(f)
+        let mut s = String::new();
+        ser::Serialize::serialize(
+            self,
+            crate::ser::Serializer::new(&mut s).unsigned_integers(true),
+        )
+        .expect("Unable to represent value as string");
+        s.fmt(f)
     }
 }
 
@@ -405,7 +688,9 @@ impl ser::Serialize for Value {
         match *self {
             Value::String(ref s) => serializer.serialize_str(s),
             Value::Integer(i) => serializer.serialize_i64(i),
+            Value::UInteger(i) => serializer.serialize_newtype_struct(UNSIGNED_NAME, &i),
             Value::Float(f) => serializer.serialize_f64(f),
+            Value::Number(ref n) => n.serialize(serializer),
             Value::Boolean(b) => serializer.serialize_bool(b),
             Value::Datetime(ref s) => s.serialize(serializer),
             Value::Array(ref a) => a.serialize(serializer),
@@ -464,8 +749,11 @@ impl<'de> de::Deserialize<'de> for Value {
                 Ok(Value::Integer(value))
             }
 
-            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Value, E> {
-                if value <= i64::max_value() as u64 {
+            fn visit_u64<E>(self, value: u64) -> Result<Value, E>
+            where
+                E: de::Error,
+            {
+                if value <= i64::MAX as u64 {
                     Ok(Value::Integer(value as i64))
                 } else {
                     Err(de::Error::custom("u64 value was too large"))
@@ -499,6 +787,13 @@ impl<'de> de::Deserialize<'de> for Value {
                 de::Deserialize::deserialize(deserializer)
             }
 
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                deserializer.deserialize_any(self)
+            }
+
             fn visit_seq<V>(self, mut visitor: V) -> Result<Value, V::Error>
             where
                 V: de::SeqAccess<'de>,
@@ -515,14 +810,24 @@ impl<'de> de::Deserialize<'de> for Value {
                 V: de::MapAccess<'de>,
             {
                 let mut key = String::new();
-                let datetime = visitor.next_key_seed(DatetimeOrTable { key: &mut key })?;
-                match datetime {
-                    Some(true) => {
+                match visitor.next_key_seed(FirstKey { key: &mut key })? {
+                    Some(KeyKind::Datetime) => {
                         let date: DatetimeFromString = visitor.next_value()?;
                         return Ok(Value::Datetime(date.value));
                     }
+                    Some(KeyKind::Number) => {
+                        let raw: String = visitor.next_value()?;
+                        return raw.parse().map(Value::Number).map_err(de::Error::custom);
+                    }
+                    Some(KeyKind::Unsigned) => {
+                        let value: u64 = visitor.next_value()?;
+                        return Ok(match i64::try_from(value) {
+                            Ok(value) => Value::Integer(value),
+                            Err(_) => Value::UInteger(value),
+                        });
+                    }
                     None => return Ok(Value::Table(Map::new())),
-                    Some(false) => {}
+                    Some(KeyKind::Key) => {}
                 }
                 let mut map = Map::new();
                 map.insert(key, visitor.next_value()?);
@@ -538,7 +843,7 @@ impl<'de> de::Deserialize<'de> for Value {
             }
         }
 
-        deserializer.deserialize_any(ValueVisitor)
+        deserializer.deserialize_newtype_struct(NAME, ValueVisitor)
     }
 }
 
@@ -552,7 +857,9 @@ impl<'de> de::Deserializer<'de> for Value {
         match self {
             Value::Boolean(v) => visitor.visit_bool(v),
             Value::Integer(n) => visitor.visit_i64(n),
+            Value::UInteger(n) => visitor.visit_u64(n),
             Value::Float(n) => visitor.visit_f64(n),
+            Value::Number(ref n) => number::visit(n.as_str(), visitor),
             Value::String(v) => visitor.visit_string(v),
             Value::Datetime(v) => visitor.visit_string(v.to_string()),
             Value::Array(v) => {
@@ -610,17 +917,27 @@ impl<'de> de::Deserializer<'de> for Value {
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, crate::de::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        match self {
+            Value::UInteger(i) if name == NAME => visit_unsigned(i, visitor),
+            Value::Number(n) if name == NAME || name == number::NAME => {
+                let field = iter::once((number::FIELD, n.to_string()));
+                let mut map = de::value::MapDeserializer::new(field);
+                let number = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(number)
+            }
+            _ => visitor.visit_newtype_struct(self),
+        }
     }
 
     serde::forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit seq
+        bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64 char str string unit seq
         bytes byte_buf map unit_struct tuple_struct struct
         tuple ignored_any identifier
     }
@@ -719,7 +1036,24 @@ impl<'de> de::IntoDeserializer<'de, crate::de::Error> for Value {
     }
 }
 
-struct Serializer;
+/// Visits an integer larger than `i64::MAX` as the private map that `Value`
+/// and `ValueRef` turn into an `UInteger`, see `NAME`.
+pub(crate) fn visit_unsigned<'de, V>(value: u64, visitor: V) -> Result<V::Value, crate::de::Error>
+where
+    V: de::Visitor<'de>,
+{
+    let field = iter::once((UNSIGNED_FIELD, value));
+    let mut map = de::value::MapDeserializer::new(field);
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+struct Serializer {
+    /// Whether a `u64` larger than `i64::MAX` is a `Value::UInteger`, only
+    /// set when serializing one.
+    unsigned_integers: bool,
+}
 
 impl ser::Serializer for Serializer {
     type Ok = Value;
@@ -766,13 +1100,29 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_u64(self, value: u64) -> Result<Value, crate::ser::Error> {
-        if value <= i64::max_value() as u64 {
+        if value <= i64::MAX as u64 {
             self.serialize_i64(value as i64)
+        } else if self.unsigned_integers {
+            Ok(Value::UInteger(value))
         } else {
             Err(ser::Error::custom("u64 value was too large"))
         }
     }
 
+    fn serialize_i128(self, value: i128) -> Result<Value, crate::ser::Error> {
+        if value < 0 {
+            let value = i64::try_from(value).map_err(|_| crate::ser::Error::NumberInvalid)?;
+            self.serialize_i64(value)
+        } else {
+            self.serialize_u128(value as u128)
+        }
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<Value, crate::ser::Error> {
+        let value = u64::try_from(value).map_err(|_| crate::ser::Error::NumberInvalid)?;
+        self.serialize_u64(value)
+    }
+
     fn serialize_f32(self, value: f32) -> Result<Value, crate::ser::Error> {
         self.serialize_f64(value.into())
     }
@@ -815,12 +1165,26 @@ impl ser::Serializer for Serializer {
 
     fn serialize_newtype_struct<T: ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Value, crate::ser::Error>
     where
         T: ser::Serialize,
     {
+        if name == UNSIGNED_NAME {
+            return value.serialize(Serializer {
+                unsigned_integers: true,
+            });
+        }
+        if name == number::NAME {
+            return match value.serialize(self)? {
+                Value::String(raw) => raw
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| crate::ser::Error::NumberInvalid),
+                _ => Err(crate::ser::Error::NumberInvalid),
+            };
+        }
         value.serialize(self)
     }
 
@@ -1033,12 +1397,21 @@ impl ser::SerializeStruct for SerializeMap {
     }
 }
 
-struct DatetimeOrTable<'a> {
+/// The first key of a map, which may be the private field that datetimes,
+/// numbers or unsigned integers are deserialized from.
+struct FirstKey<'a> {
     key: &'a mut String,
 }
 
-impl<'a, 'de> de::DeserializeSeed<'de> for DatetimeOrTable<'a> {
-    type Value = bool;
+enum KeyKind {
+    Datetime,
+    Number,
+    Unsigned,
+    Key,
+}
+
+impl<'a, 'de> de::DeserializeSeed<'de> for FirstKey<'a> {
+    type Value = KeyKind;
 
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
@@ -1048,34 +1421,465 @@ impl<'a, 'de> de::DeserializeSeed<'de> for DatetimeOrTable<'a> {
     }
 }
 
-impl<'a, 'de> de::Visitor<'de> for DatetimeOrTable<'a> {
-    type Value = bool;
+impl<'a, 'de> de::Visitor<'de> for FirstKey<'a> {
+    type Value = KeyKind;
 
     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter.write_str("a string key")
     }
 
-    fn visit_str<E>(self, s: &str) -> Result<bool, E>
+    fn visit_str<E>(self, s: &str) -> Result<KeyKind, E>
     where
         E: de::Error,
     {
         if s == datetime::FIELD {
-            Ok(true)
+            Ok(KeyKind::Datetime)
+        } else if s == number::FIELD {
+            Ok(KeyKind::Number)
+        } else if s == UNSIGNED_FIELD {
+            Ok(KeyKind::Unsigned)
         } else {
             self.key.push_str(s);
-            Ok(false)
+            Ok(KeyKind::Key)
         }
     }
 
-    fn visit_string<E>(self, s: String) -> Result<bool, E>
+    fn visit_string<E>(self, s: String) -> Result<KeyKind, E>
     where
         E: de::Error,
     {
         if s == datetime::FIELD {
-            Ok(true)
+            Ok(KeyKind::Datetime)
+        } else if s == number::FIELD {
+            Ok(KeyKind::Number)
+        } else if s == UNSIGNED_FIELD {
+            Ok(KeyKind::Unsigned)
         } else {
             *self.key = s;
-            Ok(false)
+            Ok(KeyKind::Key)
+        }
+    }
+}
+
+/// A TOML value borrowing its strings and keys from the document it was
+/// deserialized from.
+///
+/// This mirrors `Value`, but strings and table keys are `Cow<'a, str>`, which
+/// borrow from the input unless they contain escapes. Deserializing a large
+/// document into a `ValueRef` to inspect it is therefore much cheaper than
+/// deserializing it into a `Value`. It can be converted into a `Value` with
+/// `into_owned`.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use toml::value::ValueRef;
+///
+/// let input = "name = 'toml'\n[dependencies]\nserde = \"1.0\"\n";
+/// let value: ValueRef<'_> = toml::from_str(input).unwrap();
+/// assert_eq!(value["dependencies"]["serde"].as_str(), Some("1.0"));
+/// assert!(matches!(value["name"], ValueRef::String(Cow::Borrowed(_))));
+///
+/// let value = value.into_owned();
+/// assert_eq!(value["name"].as_str(), Some("toml"));
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub enum ValueRef<'a> {
+    /// Represents a TOML string
+    String(Cow<'a, str>),
+    /// Represents a TOML integer
+    Integer(i64),
+    /// Represents a TOML integer larger than `i64::MAX`, see `Value::UInteger`
+    UInteger(u64),
+    /// Represents a TOML float
+    Float(f64),
+    /// Represents a TOML integer or float kept as text, see `Value::Number`
+    Number(Number),
+    /// Represents a TOML boolean
+    Boolean(bool),
+    /// Represents a TOML datetime
+    Datetime(Datetime),
+    /// Represents a TOML array
+    Array(ArrayRef<'a>),
+    /// Represents a TOML table
+    Table(TableRef<'a>),
+}
+
+/// Type representing a borrowed TOML array, payload of the `ValueRef::Array`
+/// variant
+pub type ArrayRef<'a> = Vec<ValueRef<'a>>;
+
+/// Type representing a borrowed TOML table, payload of the `ValueRef::Table`
+/// variant. Its keys are always sorted, regardless of the `preserve_order`
+/// feature.
+pub type TableRef<'a> = BTreeMap<Cow<'a, str>, ValueRef<'a>>;
+
+impl<'a> ValueRef<'a> {
+    /// Index into a TOML array or map. A string index can be used to access a
+    /// value in a map, and a usize index can be used to access an element of an
+    /// array.
+    ///
+    /// Returns `None` if the type of `self` does not match the type of the
+    /// index, for example if the index is a string and `self` is an array or a
+    /// number. Also returns `None` if the given key does not exist in the map
+    /// or the given index is not within the bounds of the array.
+    pub fn get<I: Index>(&self, index: I) -> Option<&ValueRef<'a>> {
+        index.index_ref(self)
+    }
+
+    /// Mutably index into a TOML array or map. A string index can be used to
+    /// access a value in a map, and a usize index can be used to access an
+    /// element of an array.
+    ///
+    /// Returns `None` if the type of `self` does not match the type of the
+    /// index, for example if the index is a string and `self` is an array or a
+    /// number. Also returns `None` if the given key does not exist in the map
+    /// or the given index is not within the bounds of the array.
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut ValueRef<'a>> {
+        index.index_ref_mut(self)
+    }
+
+    /// Extracts the integer value if it is an integer.
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            ValueRef::Integer(i) => Some(i),
+            ValueRef::Number(ref n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this value is an integer.
+    pub fn is_integer(&self) -> bool {
+        self.as_integer().is_some()
+    }
+
+    /// Extracts the integer value as a `u64` if it is a non-negative integer,
+    /// including one larger than `i64::MAX`.
+    pub fn as_uinteger(&self) -> Option<u64> {
+        match *self {
+            ValueRef::Integer(i) if i >= 0 => Some(i as u64),
+            ValueRef::UInteger(i) => Some(i),
+            ValueRef::Number(ref n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    /// Extracts the float value if it is a float.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            ValueRef::Float(f) => Some(f),
+            ValueRef::Number(ref n) if n.is_float() => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this value is a float.
+    pub fn is_float(&self) -> bool {
+        self.as_float().is_some()
+    }
+
+    /// Extracts the number if it is a number kept as text.
+    pub fn as_number(&self) -> Option<&Number> {
+        match *self {
+            ValueRef::Number(ref n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Extracts the boolean value if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            ValueRef::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this value is a boolean.
+    pub fn is_bool(&self) -> bool {
+        self.as_bool().is_some()
+    }
+
+    /// Extracts the string of this value if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            ValueRef::String(ref s) => Some(&**s),
+            _ => None,
+        }
+    }
+
+    /// Tests if this value is a string.
+    pub fn is_str(&self) -> bool {
+        self.as_str().is_some()
+    }
+
+    /// Extracts the datetime value if it is a datetime.
+    pub fn as_datetime(&self) -> Option<&Datetime> {
+        match *self {
+            ValueRef::Datetime(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this value is a datetime.
+    pub fn is_datetime(&self) -> bool {
+        self.as_datetime().is_some()
+    }
+
+    /// Extracts the array value if it is an array.
+    pub fn as_array(&self) -> Option<&ArrayRef<'a>> {
+        match *self {
+            ValueRef::Array(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Extracts the array value if it is an array.
+    pub fn as_array_mut(&mut self) -> Option<&mut ArrayRef<'a>> {
+        match *self {
+            ValueRef::Array(ref mut s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this value is an array.
+    pub fn is_array(&self) -> bool {
+        self.as_array().is_some()
+    }
+
+    /// Extracts the table value if it is a table.
+    pub fn as_table(&self) -> Option<&TableRef<'a>> {
+        match *self {
+            ValueRef::Table(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Extracts the table value if it is a table.
+    pub fn as_table_mut(&mut self) -> Option<&mut TableRef<'a>> {
+        match *self {
+            ValueRef::Table(ref mut s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Tests whether this value is a table.
+    pub fn is_table(&self) -> bool {
+        self.as_table().is_some()
+    }
+
+    /// Tests whether this and another value have the same type.
+    pub fn same_type(&self, other: &ValueRef<'_>) -> bool {
+        self.type_str() == other.type_str()
+    }
+
+    /// Returns a human-readable representation of the type of this value.
+    pub fn type_str(&self) -> &'static str {
+        match *self {
+            ValueRef::String(..) => "string",
+            ValueRef::Integer(..) | ValueRef::UInteger(..) => "integer",
+            ValueRef::Float(..) => "float",
+            ValueRef::Number(ref n) if n.is_float() => "float",
+            ValueRef::Number(..) => "integer",
+            ValueRef::Boolean(..) => "boolean",
+            ValueRef::Datetime(..) => "datetime",
+            ValueRef::Array(..) => "array",
+            ValueRef::Table(..) => "table",
+        }
+    }
+
+    /// Converts this value into an owned `Value`, copying any borrowed strings.
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::String(s) => Value::String(s.into_owned()),
+            ValueRef::Integer(i) => Value::Integer(i),
+            ValueRef::UInteger(i) => Value::UInteger(i),
+            ValueRef::Float(f) => Value::Float(f),
+            ValueRef::Number(n) => Value::Number(n),
+            ValueRef::Boolean(b) => Value::Boolean(b),
+            ValueRef::Datetime(d) => Value::Datetime(d),
+            ValueRef::Array(a) => Value::Array(a.into_iter().map(ValueRef::into_owned).collect()),
+            ValueRef::Table(t) => Value::Table(
+                t.into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<'a, I> ops::Index<I> for ValueRef<'a>
+where
+    I: Index,
+{
+    type Output = ValueRef<'a>;
+
+    fn index(&self, index: I) -> &ValueRef<'a> {
+        self.get(index).expect("index not found")
+    }
+}
+
+impl<'a, I> ops::IndexMut<I> for ValueRef<'a>
+where
+    I: Index,
+{
+    fn index_mut(&mut self, index: I) -> &mut ValueRef<'a> {
+        self.get_mut(index).expect("index not found")
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for Value {
+    fn from(value: ValueRef<'a>) -> Value {
+        value.into_owned()
+    }
+}
+
+impl<'de: 'a, 'a> de::Deserialize<'de> for ValueRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<ValueRef<'a>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct ValueRefVisitor<'a>(PhantomData<ValueRef<'a>>);
+
+        impl<'de: 'a, 'a> de::Visitor<'de> for ValueRefVisitor<'a> {
+            type Value = ValueRef<'a>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("any valid TOML value")
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<ValueRef<'a>, E> {
+                Ok(ValueRef::Boolean(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<ValueRef<'a>, E> {
+                Ok(ValueRef::Integer(value))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<ValueRef<'a>, E>
+            where
+                E: de::Error,
+            {
+                if value <= i64::MAX as u64 {
+                    Ok(ValueRef::Integer(value as i64))
+                } else {
+                    Err(de::Error::custom("u64 value was too large"))
+                }
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<ValueRef<'a>, E> {
+                Ok(ValueRef::Float(value))
+            }
+
+            fn visit_borrowed_str<E>(self, value: &'de str) -> Result<ValueRef<'a>, E> {
+                Ok(ValueRef::String(Cow::Borrowed(value)))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<ValueRef<'a>, E> {
+                Ok(ValueRef::String(Cow::Owned(value.into())))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<ValueRef<'a>, E> {
+                Ok(ValueRef::String(Cow::Owned(value)))
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<ValueRef<'a>, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                de::Deserialize::deserialize(deserializer)
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<ValueRef<'a>, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                deserializer.deserialize_any(self)
+            }
+
+            fn visit_seq<V>(self, mut visitor: V) -> Result<ValueRef<'a>, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                let mut vec = Vec::new();
+                while let Some(elem) = visitor.next_element()? {
+                    vec.push(elem);
+                }
+                Ok(ValueRef::Array(vec))
+            }
+
+            fn visit_map<V>(self, mut visitor: V) -> Result<ValueRef<'a>, V::Error>
+            where
+                V: de::MapAccess<'de>,
+            {
+                let key = match visitor.next_key_seed(KeyRef(PhantomData))? {
+                    Some(key) if key == datetime::FIELD => {
+                        let date: DatetimeFromString = visitor.next_value()?;
+                        return Ok(ValueRef::Datetime(date.value));
+                    }
+                    Some(key) if key == number::FIELD => {
+                        let raw: String = visitor.next_value()?;
+                        return raw.parse().map(ValueRef::Number).map_err(de::Error::custom);
+                    }
+                    Some(key) if key == UNSIGNED_FIELD => {
+                        let value: u64 = visitor.next_value()?;
+                        return Ok(match i64::try_from(value) {
+                            Ok(value) => ValueRef::Integer(value),
+                            Err(_) => ValueRef::UInteger(value),
+                        });
+                    }
+                    Some(key) => key,
+                    None => return Ok(ValueRef::Table(TableRef::new())),
+                };
+                let mut map = TableRef::new();
+                map.insert(key, visitor.next_value()?);
+                while let Some(key) = visitor.next_key_seed(KeyRef(PhantomData))? {
+                    if map.contains_key(&key) {
+                        let msg = format!("duplicate key: `{}`", key);
+                        return Err(de::Error::custom(msg));
+                    }
+                    let value = visitor.next_value()?;
+                    map.insert(key, value);
+                }
+                Ok(ValueRef::Table(map))
+            }
         }
+
+        deserializer.deserialize_newtype_struct(NAME, ValueRefVisitor(PhantomData))
+    }
+}
+
+/// Deserializes a table key, borrowing it if possible.
+struct KeyRef<'a>(PhantomData<Cow<'a, str>>);
+
+impl<'de: 'a, 'a> de::DeserializeSeed<'de> for KeyRef<'a> {
+    type Value = Cow<'a, str>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de: 'a, 'a> de::Visitor<'de> for KeyRef<'a> {
+    type Value = Cow<'a, str>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_borrowed_str<E>(self, s: &'de str) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(s))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(s))
     }
 }
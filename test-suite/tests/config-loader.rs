@@ -0,0 +1,227 @@
+extern crate serde;
+extern crate toml;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use serde::Deserialize;
+use toml::config::{ErrorKind, Loader};
+use toml::value::{ArrayMerge, MergeOptions, TypeConflict};
+use toml::Value;
+
+const BASE: &str = "\
+[server]
+host = 'localhost'
+port = 80
+tags = ['a']
+
+[log]
+level = 'info'
+";
+
+const LOCAL: &str = "\
+[server]
+port = 8080
+tags = ['b']
+";
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("toml-config-loader-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn later_sources_override_earlier_ones() {
+    let defaults: Value = "name = 'app'\n[log]\nlevel = 'warn'".parse().unwrap();
+    let config = Loader::new()
+        .value("defaults", defaults)
+        .str("base.toml", BASE)
+        .str("local.toml", LOCAL)
+        .load()
+        .unwrap();
+    let value = config.value();
+    assert_eq!(value["name"].as_str(), Some("app"));
+    assert_eq!(value["server"]["host"].as_str(), Some("localhost"));
+    assert_eq!(value["server"]["port"].as_integer(), Some(8080));
+    assert_eq!(value["server"]["tags"], Value::from(vec!["b"]));
+    assert_eq!(value["log"]["level"].as_str(), Some("info"));
+}
+
+#[test]
+fn origins() {
+    let defaults: Value = "name = 'app'".parse().unwrap();
+    let config = Loader::new()
+        .value("defaults", defaults)
+        .str("base.toml", BASE)
+        .str("local.toml", LOCAL)
+        .load()
+        .unwrap();
+
+    let name = config.origin(&["name"]).unwrap();
+    assert_eq!(name.source(), "defaults");
+    assert_eq!(name.span(), None);
+    assert_eq!(name.to_string(), "defaults");
+
+    let host = config.origin(&["server", "host"]).unwrap();
+    assert_eq!(host.source(), "base.toml");
+    assert_eq!(&BASE[host.span().unwrap()], "'localhost'");
+    assert_eq!(host.line_col(), Some((1, 7)));
+
+    let port = config.origin(&["server", "port"]).unwrap();
+    assert_eq!(port.source(), "local.toml");
+    assert_eq!(&LOCAL[port.span().unwrap()], "8080");
+    assert_eq!(port.to_string(), "local.toml at line 2 column 8");
+
+    // Tables come from the last source defining them.
+    let server = config.origin(&["server"]).unwrap();
+    assert_eq!(server.source(), "local.toml");
+    let log = config.origin(&["log"]).unwrap();
+    assert_eq!(log.source(), "base.toml");
+
+    assert_eq!(config.origin(&["missing"]), None);
+    assert_eq!(config.origin(&["server", "tags", "0"]), None);
+}
+
+#[test]
+fn arrays_combined_from_several_sources() {
+    let config = Loader::new()
+        .str("base.toml", BASE)
+        .str("local.toml", LOCAL)
+        .merge_options(MergeOptions::new().arrays(ArrayMerge::Append))
+        .load()
+        .unwrap();
+    assert_eq!(
+        config.value()["server"]["tags"],
+        Value::from(vec!["a", "b"])
+    );
+    let tags = config.origin(&["server", "tags"]).unwrap();
+    assert_eq!(tags.source(), "local.toml");
+}
+
+#[test]
+fn replaced_values_lose_their_origins() {
+    let config = Loader::new()
+        .str("base.toml", BASE)
+        .str("local.toml", "server = 'example.com:80'")
+        .merge_options(MergeOptions::new().type_conflicts(TypeConflict::Override))
+        .load()
+        .unwrap();
+    assert_eq!(config.origin(&["server", "host"]), None);
+    assert_eq!(config.origin(&["server"]).unwrap().source(), "local.toml");
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Config {
+    server: Server,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Server {
+    host: String,
+    port: u16,
+}
+
+#[test]
+fn deserialize_errors_point_at_the_source() {
+    let config = Loader::new()
+        .str("base.toml", BASE)
+        .str("local.toml", "[server]\nport = 65536\n")
+        .load()
+        .unwrap();
+    let err = config.try_into::<Config>().unwrap_err();
+    match *err.kind() {
+        ErrorKind::Deserialize(ref e) => assert_eq!(e.key_path(), ["server", "port"]),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(err.origin().unwrap().line_col(), Some((1, 7)));
+    assert_eq!(
+        err.to_string(),
+        "invalid value: integer `65536`, expected u16 for key `server.port` \
+         in local.toml at line 2 column 8"
+    );
+
+    // Missing fields point at the table they are missing from.
+    let config = Loader::new()
+        .str("base.toml", "[log]\n")
+        .str("local.toml", "\n[server]\nport = 1\n")
+        .load()
+        .unwrap();
+    let err = config.try_into::<Config>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "missing field `host` for key `server` in local.toml at line 2 column 1"
+    );
+
+    let config = Loader::new().str("local.toml", LOCAL).load().unwrap();
+    let server = config.try_into_at::<Server>(&["server"]).unwrap_err();
+    assert_eq!(server.origin().unwrap().source(), "local.toml");
+    assert!(config.try_into_at::<Server>(&["nope"]).unwrap().is_none());
+}
+
+#[test]
+fn parse_and_merge_errors() {
+    let err = Loader::new()
+        .str("base.toml", BASE)
+        .str("local.toml", "[server]\nport = 80x\n")
+        .load()
+        .unwrap_err();
+    match *err.kind() {
+        ErrorKind::Parse(_) => {}
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(err.origin().unwrap().source(), "local.toml");
+    assert_eq!(err.origin().unwrap().line_col(), Some((1, 7)));
+    assert!(err
+        .to_string()
+        .ends_with("at line 2 column 8 in local.toml"));
+
+    let err = Loader::new()
+        .str("base.toml", BASE)
+        .str("local.toml", "[server]\nport = '80'\n")
+        .load()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot merge string into integer for key `server.port` \
+         in local.toml at line 2 column 8"
+    );
+}
+
+#[test]
+fn files() {
+    let base = temp_file("base.toml", BASE);
+    let local = temp_file("local.toml", LOCAL);
+    let missing = base.with_file_name("missing.toml");
+
+    let config = Loader::new()
+        .file(&base)
+        .optional_file(&missing)
+        .file(&local)
+        .load()
+        .unwrap();
+    assert_eq!(config.value()["server"]["port"].as_integer(), Some(8080));
+    let host = config.origin(&["server", "host"]).unwrap();
+    assert_eq!(host.source(), base.display().to_string());
+
+    let err = Loader::new().file(&missing).load().unwrap_err();
+    match *err.kind() {
+        ErrorKind::Io(ref e) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(
+        err.origin().unwrap().source(),
+        missing.display().to_string()
+    );
+    assert!(err
+        .to_string()
+        .starts_with(&format!("failed to read {}: ", missing.display())));
+
+    fs::remove_dir_all(base.parent().unwrap()).unwrap();
+}
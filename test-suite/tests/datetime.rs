@@ -113,22 +113,108 @@ fn bad_times() {
 
     bad!(
         "foo = 1997-00-09T09:09:09.09Z",
-        "failed to parse datetime for key `foo` at line 1 column 7"
+        "invalid date: month must be between 01 and 12 at line 1 column 7"
     );
     bad!(
         "foo = 1997-09-00T09:09:09.09Z",
-        "failed to parse datetime for key `foo` at line 1 column 7"
+        "invalid date: day is out of range for the month at line 1 column 7"
     );
     bad!(
         "foo = 1997-09-09T30:09:09.09Z",
-        "failed to parse datetime for key `foo` at line 1 column 7"
+        "invalid date: hour must be between 00 and 23 at line 1 column 7"
     );
     bad!(
         "foo = 1997-09-09T12:69:09.09Z",
-        "failed to parse datetime for key `foo` at line 1 column 7"
+        "invalid date: minute must be between 00 and 59 at line 1 column 7"
     );
     bad!(
         "foo = 1997-09-09T12:09:69.09Z",
-        "failed to parse datetime for key `foo` at line 1 column 7"
+        "invalid date: second must be between 00 and 60 at line 1 column 7"
+    );
+}
+
+#[test]
+fn impossible_times() {
+    bad!(
+        "foo = 2021-02-29",
+        "invalid date: day is out of range for the month at line 1 column 7"
+    );
+    bad!(
+        "foo = 1900-02-29",
+        "invalid date: day is out of range for the month at line 1 column 7"
+    );
+    bad!(
+        "foo = 2021-04-31T00:00:00",
+        "invalid date: day is out of range for the month at line 1 column 7"
+    );
+    bad!(
+        "foo = 24:00:00",
+        "invalid date: hour must be between 00 and 23 at line 1 column 7"
+    );
+    bad!(
+        "foo = 1997-09-09T09:09:09+24:00",
+        "invalid date: offset hour must be between 00 and 23 at line 1 column 7"
+    );
+    bad!(
+        "foo = 1997-09-09T09:09:09-09:60",
+        "invalid date: offset minute must be between 00 and 59 at line 1 column 7"
+    );
+    bad!(
+        "foo = 1997-09-09T12:00:60Z",
+        "invalid date: second can only be 60 for a leap second at line 1 column 7"
+    );
+    bad!(
+        "foo = 2016-12-31T23:59:60+01:00",
+        "invalid date: second can only be 60 for a leap second at line 1 column 7"
+    );
+
+    for good in &[
+        "2020-02-29",
+        "2000-02-29",
+        "2016-12-31T23:59:60Z",
+        "2017-01-01T00:59:60+01:00",
+        "2016-12-31T15:59:60-08:00",
+        "2017-01-01T05:29:60+05:30",
+        "23:59:60",
+    ] {
+        let input = format!("foo = {}", good);
+        let value = input.parse::<toml::Value>().unwrap();
+        assert_eq!(value["foo"].as_datetime().unwrap().to_string(), *good);
+        assert!(toml::value::Datetime::from_str(good).is_ok());
+    }
+
+    let err = toml::value::Datetime::from_str("2021-02-30").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to parse datetime: day is out of range for the month"
+    );
+    let err = toml::value::Datetime::from_str("2021-02").unwrap_err();
+    assert_eq!(err.to_string(), "failed to parse datetime");
+}
+
+#[test]
+fn lenient_times() {
+    let lenient = toml::de::Options::new().strict_datetimes(false);
+    for input in &[
+        "2021-02-30",
+        "1997-09-09T24:00:00",
+        "1997-09-09T09:09:09+99:99",
+        "12:00:60",
+    ] {
+        assert!(toml::value::Datetime::from_str(input).is_err(), "{}", input);
+        let datetime = toml::value::Datetime::from_str_lenient(input).unwrap();
+        assert_eq!(datetime.to_string(), *input);
+
+        let toml = format!("foo = {}", input);
+        assert!(toml.parse::<toml::Value>().is_err(), "{}", input);
+        let value = toml::de::from_str_with_options::<toml::Value>(&toml, &lenient).unwrap();
+        assert_eq!(value["foo"].as_datetime().unwrap().to_string(), *input);
+    }
+
+    let err =
+        toml::de::from_str_with_options::<toml::Value>("foo = 2021-13-01", &lenient).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid date: month must be between 01 and 12 at line 1 column 7"
     );
 }
@@ -181,20 +181,19 @@ fn custom_errors() {
     );
 
     // Sub-table in the middle of a Vec has an extra field.
-    // FIXME: This location could be better.
     bad!(
         "
             p_a = 'a'
             p_b = [
                 {c_a = 'a', c_b = 'b'},
                 {c_a = 'aa', c_b = 'bb', c_d = 'd'},
-              # ^
+                                       # ^
                 {c_a = 'aaa', c_b = 'bbb'},
                 {c_a = 'aaaa', c_b = 'bbbb'},
             ]
         ",
         Parent<CasedString>,
-        "unknown field `c_d`, expected `c_a` or `c_b` for key `p_b` at line 5 column 17"
+        "unknown field `c_d`, expected `c_a` or `c_b` for key `p_b` at line 5 column 42"
     );
 
     // Sub-table in the middle of a Vec is missing a field.
@@ -240,7 +239,6 @@ fn custom_errors() {
     );
 
     // Sub-table in the middle of a Vec has an extra field.
-    // FIXME: This location is pretty off.
     bad!(
         "
             p_a = 'a'
@@ -250,16 +248,16 @@ fn custom_errors() {
             [[p_b]]
             c_a = 'aa'
             c_d = 'dd' # unknown field
+          # ^
             [[p_b]]
             c_a = 'aaa'
             c_b = 'bbb'
             [[p_b]]
-          # ^
             c_a = 'aaaa'
             c_b = 'bbbb'
         ",
         Parent<CasedString>,
-        "unknown field `c_d`, expected `c_a` or `c_b` for key `p_b` at line 12 column 13"
+        "unknown field `c_d`, expected `c_a` or `c_b` for key `p_b` at line 8 column 13"
     );
 }
 
@@ -298,17 +296,16 @@ fn serde_derive_deserialize_errors() {
         "invalid type: integer `1`, expected a string for key `p_b` at line 4 column 34"
     );
 
-    // FIXME: This location could be better.
     bad!(
         "
             p_a = ''
             p_b = [
                 {c_a = '', c_b = '', c_d = ''},
-              # ^
+                                   # ^
             ]
         ",
         Parent<String>,
-        "unknown field `c_d`, expected `c_a` or `c_b` for key `p_b` at line 4 column 17"
+        "unknown field `c_d`, expected `c_a` or `c_b` for key `p_b` at line 4 column 38"
     );
 
     bad!(
@@ -334,7 +331,7 @@ fn error_handles_crlf() {
          a = 2\r\n\
          ",
         toml::Value,
-        "duplicate key: `a` for key `t2` at line 3 column 1"
+        "duplicate key: `a` for key `t2` at line 5 column 1"
     );
 
     // Should be the same as above.
@@ -346,6 +343,161 @@ fn error_handles_crlf() {
          a = 2\n\
          ",
         toml::Value,
-        "duplicate key: `a` for key `t2` at line 3 column 1"
+        "duplicate key: `a` for key `t2` at line 5 column 1"
+    );
+}
+
+#[test]
+fn error_kind_and_accessors() {
+    use toml::de::ErrorKind;
+
+    let err = toml::from_str::<toml::Value>("[a]\n[a]").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::DuplicateTable("a".to_string()));
+    assert_eq!(err.offset(), Some(4));
+    assert_eq!(err.key_path(), ["a"]);
+
+    let err = toml::from_str::<toml::Value>("a = ").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::UnexpectedEof);
+
+    let err = toml::from_str::<toml::Value>("a = 1 2").unwrap_err();
+    assert_eq!(err.wanted(), Some(("newline", "an identifier")));
+    assert_eq!(err.offset(), Some(6));
+    assert!(err.unexpected_keys().is_none());
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Shape {
+        Rect { width: u32, height: u32 },
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Drawing {
+        shape: Shape,
+    }
+
+    let err = toml::from_str::<Drawing>("shape = { Rect = { width = 1, height = 2, depth = 3 } }")
+        .unwrap_err();
+    let (keys, available) = err.unexpected_keys().unwrap();
+    assert_eq!(keys, ["depth"]);
+    assert_eq!(available, ["width", "height"]);
+    assert_eq!(err.key_path(), ["shape"]);
+    match err.kind() {
+        ErrorKind::UnexpectedKeys { .. } => {}
+        kind => panic!("unexpected kind {:?}", kind),
+    }
+
+    let err = toml::from_str::<Parent<String>>("p_a = 1\np_b = []").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Custom);
+    assert_eq!(err.key_path(), ["p_a"]);
+}
+
+#[test]
+fn error_spans() {
+    fn span_of<T: de::DeserializeOwned + fmt::Debug>(input: &str) -> &str {
+        let err = toml::from_str::<T>(input).unwrap_err();
+        &input[err.span().unwrap()]
+    }
+
+    assert_eq!(span_of::<toml::Value>("[a]\nb = 1\n[a]\n"), "[a]");
+    assert_eq!(span_of::<toml::Value>("[[a.b]]\n[[a]]\n"), "[[a]]");
+    assert_eq!(span_of::<toml::Value>("a = 1_000_\n"), "1_000_");
+    assert_eq!(span_of::<toml::Value>("a = 1.5e+\n"), "1.5e+");
+    assert_eq!(span_of::<toml::Value>("a = [1.5e+]\n"), "1.5e+");
+    assert_eq!(span_of::<toml::Value>("a = \"\\uD800\"\n"), "uD800");
+    assert_eq!(span_of::<toml::Value>("[a]\nb = 1\n[a] # again\n"), "[a]");
+    assert_eq!(
+        span_of::<toml::Value>("a = 1979-13-27T07:32:00Z\n"),
+        "1979-13-27T07:32:00Z"
+    );
+    assert_eq!(span_of::<toml::Value>("a = ?\n"), "?");
+    assert_eq!(span_of::<toml::Value>("a = \"\\q\"\n"), "q");
+    assert_eq!(span_of::<toml::Value>("a = \"abc\n"), "\n");
+    assert_eq!(span_of::<toml::Value>("a = 'abc"), "'abc");
+    assert_eq!(span_of::<toml::Value>("a = 1 b\n"), "b");
+
+    let err = toml::from_str::<toml::Value>("a = ").unwrap_err();
+    assert_eq!(err.span(), Some(4..4));
+
+    // Custom errors raised from within a value cover the whole value.
+    assert_eq!(span_of::<Parent<String>>("p_a = 1.25\np_b = []"), "1.25");
+    assert_eq!(
+        span_of::<Parent<String>>("p_a = ''\np_b = [{c_a = '', c_b = [1, 2]}]"),
+        "[1, 2]"
     );
+    // Unknown fields point at the key.
+    assert_eq!(
+        span_of::<Parent<String>>("p_a = ''\np_b = [{c_a = '', c_b = '', c_d = ''}]"),
+        "c_d"
+    );
+    assert_eq!(
+        span_of::<Parent<String>>("p_a = ''\n[[p_b]]\nc_a = ''\nc_b = ''\n\"c.d\" = 1\n"),
+        "\"c.d\""
+    );
+}
+
+#[test]
+fn suggestions() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Config {
+        database_url: String,
+        #[serde(default)]
+        pool: Option<Pool>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Pool {
+        Fixed { size: u32 },
+        Dynamic { min: u32, max: u32 },
+    }
+
+    let err = toml::from_str::<Config>("databse_url = 'x'").unwrap_err();
+    assert_eq!(err.suggestion(), Some("database_url"));
+    assert_eq!(
+        err.to_string(),
+        "unknown field `databse_url`, expected `database_url` or `pool` \
+         at line 1 column 1, did you mean `database_url`?"
+    );
+    assert_eq!(err.span(), Some(0..11));
+
+    // Case differences are always suggested.
+    let err = toml::from_str::<Config>("DATABASE_URL = 'x'").unwrap_err();
+    assert_eq!(err.suggestion(), Some("database_url"));
+
+    // Unknown variants, however the enum is written.
+    let inputs = [
+        "database_url = ''\npool = 'Fixd'",
+        "database_url = ''\npool = { Fixd = { size = 1 } }",
+    ];
+    for input in inputs.iter() {
+        let err = toml::from_str::<Config>(input).unwrap_err();
+        assert_eq!(err.suggestion(), Some("Fixed"), "{}", input);
+        assert!(
+            err.to_string().contains(", did you mean `Fixed`?"),
+            "{}",
+            err
+        );
+    }
+    let input = "database_url = ''\npool = { Fixd = { size = 1 } }";
+    let err = toml::from_str::<Config>(input).unwrap_err();
+    assert_eq!(&input[err.span().unwrap()], "Fixd");
+    let err = toml::from_str::<Pool>("[Fixd]\nsize = 1").unwrap_err();
+    assert_eq!(err.suggestion(), Some("Fixed"));
+
+    // Keys rejected by a struct variant.
+    let err =
+        toml::from_str::<Config>("database_url = ''\npool = { Dynamic = { min = 1, mx = 2 } }")
+            .unwrap_err();
+    assert_eq!(err.suggestion(), Some("max"));
+    assert!(err.to_string().contains(", did you mean `max`?"), "{}", err);
+
+    // Nothing close enough, or too many equally close names.
+    let err = toml::from_str::<Config>("hostname = 'x'").unwrap_err();
+    assert_eq!(err.suggestion(), None);
+    let err = toml::from_str::<Config>("database_url = ''\npool = { Dynamic = { mix = 1 } }")
+        .unwrap_err();
+    assert_eq!(err.suggestion(), None);
 }
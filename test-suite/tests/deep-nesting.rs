@@ -0,0 +1,140 @@
+extern crate serde;
+extern crate toml;
+
+use serde::de::{Deserialize, IgnoredAny};
+use toml::de::{Deserializer, ErrorKind, Limits};
+use toml::document::Document;
+use toml::map::Map;
+use toml::spanned::SpannedValue;
+use toml::Value;
+
+const DEPTH: usize = 100_000;
+
+fn nested_arrays(depth: usize) -> Value {
+    let mut value = Value::Integer(1);
+    for _ in 0..depth {
+        value = Value::Array(vec![value]);
+    }
+    value
+}
+
+fn nested_tables(depth: usize) -> Value {
+    let mut value = Value::Table(Map::new());
+    for _ in 0..depth {
+        let mut table = Map::new();
+        table.insert("a".to_string(), value);
+        value = Value::Table(table);
+    }
+    value
+}
+
+// Compares two values without recursing, as `PartialEq` would.
+fn assert_same(a: &Value, b: &Value) {
+    let mut stack = vec![(a, b)];
+    while let Some((a, b)) = stack.pop() {
+        match (a, b) {
+            (Value::Array(a), Value::Array(b)) => {
+                assert_eq!(a.len(), b.len());
+                stack.extend(a.iter().zip(b));
+            }
+            (Value::Table(a), Value::Table(b)) => {
+                assert_eq!(a.len(), b.len());
+                for ((ka, va), (kb, vb)) in a.iter().zip(b) {
+                    assert_eq!(ka, kb);
+                    stack.push((va, vb));
+                }
+            }
+            (a, b) => assert_eq!(a, b),
+        }
+    }
+}
+
+#[test]
+fn arrays() {
+    let value = nested_arrays(DEPTH);
+    let clone = value.clone();
+    assert_same(&clone, &value);
+    clone.drop_iteratively();
+    value.drop_iteratively();
+}
+
+#[test]
+fn tables() {
+    let value = nested_tables(DEPTH);
+    let clone = value.clone();
+    assert_same(&clone, &value);
+    clone.drop_iteratively();
+    value.drop_iteratively();
+}
+
+#[test]
+fn mixed() {
+    let mut value = Value::Integer(1);
+    for i in 0..DEPTH {
+        value = if i % 2 == 0 {
+            Value::Array(vec![value, Value::Boolean(true)])
+        } else {
+            let mut table = Map::new();
+            table.insert("a".to_string(), value);
+            Value::Table(table)
+        };
+    }
+    let clone = value.clone();
+    assert_same(&clone, &value);
+    clone.drop_iteratively();
+    value.drop_iteratively();
+}
+
+#[test]
+fn parsing_fails_cleanly() {
+    for input in &[
+        format!("a = {}", "[".repeat(DEPTH)),
+        format!("a = {}1{}", "[".repeat(DEPTH), "]".repeat(DEPTH)),
+        format!("a = {}", "{ a = ".repeat(DEPTH)),
+        format!("[a{}]", ".a".repeat(DEPTH)),
+    ] {
+        // With the default options, whatever is built from the input is
+        // shallow enough to be dropped and formatted.
+        let errors = vec![
+            input.parse::<Value>().unwrap_err(),
+            input.parse::<SpannedValue>().unwrap_err(),
+            input.parse::<Document>().unwrap_err(),
+        ];
+        for err in errors {
+            match *err.kind() {
+                ErrorKind::LimitExceeded { limit, max } => {
+                    assert_eq!(limit, "max_depth");
+                    assert_eq!(max, Limits::DEFAULT_MAX_DEPTH);
+                }
+                ref kind => panic!("unexpected error {:?}", kind),
+            }
+            assert!(err
+                .to_string()
+                .starts_with("exceeded the `max_depth` limit"));
+        }
+    }
+}
+
+#[test]
+fn parsing_up_to_the_default_limit() {
+    let depth = Limits::DEFAULT_MAX_DEPTH - 1;
+    let input = format!("a = {}1{}", "[".repeat(depth), "]".repeat(depth));
+    let value = input.parse::<Value>().unwrap();
+    assert_eq!(value.to_string(), input + "\n");
+    assert_eq!(value.clone(), value);
+    drop(value);
+}
+
+#[test]
+fn parsing_without_a_limit() {
+    // The parser itself doesn't recurse, so it reports unclosed arrays
+    // however deeply they are nested.
+    let input = format!("a = {}", "[".repeat(DEPTH));
+    let mut de = Deserializer::new(&input);
+    de.set_limits(Limits {
+        max_depth: usize::MAX,
+        ..Limits::default()
+    });
+    let err = IgnoredAny::deserialize(&mut de).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::UnexpectedEof);
+}
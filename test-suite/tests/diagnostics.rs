@@ -0,0 +1,239 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+use toml::de::parse_with_diagnostics;
+
+fn messages(input: &str) -> Vec<String> {
+    parse_with_diagnostics(input)
+        .1
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[test]
+fn valid_document() {
+    let (value, errors) = parse_with_diagnostics("a = 1\n[b]\nc = 'd'\n");
+    assert!(errors.is_empty());
+    assert_eq!(value.unwrap(), "a = 1\n[b]\nc = 'd'\n".parse().unwrap());
+}
+
+#[test]
+fn reports_every_syntax_error() {
+    let input = "\
+a = 1
+b =
+c = \"unterminated
+d = [1, 2 3]
+e = { f = 1, }
+[table
+g = 2
+[other]
+h = tru
+i = 3
+";
+    assert_eq!(
+        messages(input),
+        [
+            "expected a value, found a newline at line 2 column 4",
+            "newline in string found at line 3 column 18",
+            "expected a right bracket, found an identifier at line 4 column 11",
+            "expected a table key, found a right brace at line 5 column 14",
+            "expected a right bracket, found a newline at line 6 column 7",
+            "invalid TOML value, did you mean to use a quoted string? at line 9 column 5",
+        ]
+    );
+
+    let value = parse_with_diagnostics(input).0.unwrap();
+    assert_eq!(
+        value,
+        "a = 1\n[other]\ni = 3\n".parse::<toml::Value>().unwrap()
+    );
+}
+
+#[test]
+fn skips_rest_of_broken_multiline_values() {
+    let input = "\
+a = [
+  1,
+  2 3,
+  4,
+]
+b = { c = 1, d = }
+d = 1
+";
+    assert_eq!(
+        messages(input),
+        [
+            "expected a right bracket, found an identifier at line 3 column 5",
+            "expected a value, found a right brace at line 6 column 18",
+        ]
+    );
+    let value = parse_with_diagnostics(input).0.unwrap();
+    assert_eq!(value["d"].as_integer(), Some(1));
+}
+
+#[test]
+fn resumes_at_statement_after_unclosed_array() {
+    let input = "a = [1, 2\nb = 3\n";
+    assert_eq!(
+        messages(input),
+        ["expected a right bracket, found an identifier at line 2 column 1"]
+    );
+    let value = parse_with_diagnostics(input).0.unwrap();
+    assert_eq!(value["b"].as_integer(), Some(3));
+}
+
+#[test]
+fn reports_semantic_errors_after_syntax_errors() {
+    let (value, errors) = parse_with_diagnostics("a = 1\nb = ?\na = 2\n");
+    assert!(value.is_none());
+    let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "unexpected character found: `?` at line 2 column 5",
+            "duplicate key: `a` at line 3 column 1",
+        ]
+    );
+}
+
+#[test]
+fn error_at_end_of_input() {
+    assert_eq!(
+        messages("a = 1\nb = "),
+        ["unexpected eof encountered at line 2 column 5"]
+    );
+    assert_eq!(
+        messages("a = '''\nfoo"),
+        ["unterminated string at line 1 column 5"]
+    );
+}
+
+#[test]
+fn invalid_suite_reports_errors() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/invalid");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let input = std::fs::read_to_string(&path).unwrap();
+        let (_, errors) = parse_with_diagnostics(&input);
+        assert!(!errors.is_empty(), "{}", path.display());
+    }
+}
+
+#[test]
+fn display_with_source() {
+    let input = "[a]\nb = 1\n\n[a]\n";
+    let err = toml::from_str::<toml::Value>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, Some("Cargo.toml")),
+        "\
+error: redefinition of table `a` for key `a`
+ --> Cargo.toml:4:1
+  |
+4 | [a]
+  | ^^^
+  |
+1 | [a]
+  | --- first defined here
+  |
+  = help: tables must be defined before values
+"
+    );
+
+    // Without a file name, and pointing at the end of the input.
+    let input = "a = 1\nb =";
+    let err = toml::from_str::<toml::Value>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, None),
+        "\
+error: unexpected eof encountered
+ --> 2:4
+  |
+2 | b =
+  |    ^
+"
+    );
+
+    // Tabs are kept so the underline lines up.
+    let input = "a = [\n\t1,\n\t2 3,\n]\n";
+    let err = toml::from_str::<toml::Value>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, None),
+        "\
+error: expected a right bracket, found an identifier
+ --> 3:4
+  |
+3 | \t2 3,
+  | \t  ^
+"
+    );
+}
+
+#[test]
+fn display_with_source_multiline_span() {
+    let input = "a = 1\nb = [\n  1,\n  2,\n]\n";
+    let err = toml::from_str::<std::collections::BTreeMap<String, String>>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, Some("x.toml")),
+        "\
+error: invalid type: integer `1`, expected a string for key `a`
+ --> x.toml:1:5
+  |
+1 | a = 1
+  |     ^
+"
+    );
+
+    let input = "b = [\n  1,\n  2,\n]\n";
+    let err = toml::from_str::<std::collections::BTreeMap<String, String>>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, Some("x.toml")),
+        "\
+error: invalid type: sequence, expected a string for key `b`
+ --> x.toml:1:5
+  |
+1 | b = [
+  |     ^
+2 |   1,
+  | ^^^^
+3 |   2,
+  | ^^^^
+4 | ]
+  | ^
+"
+    );
+}
+
+#[test]
+fn display_with_source_lists_available_keys() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Shape {
+        Circle { radius: f64 },
+        Rect { width: f64, height: f64 },
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        shape: Shape,
+    }
+
+    let input = "[shape.Rect]\nwidth = 1.0\nheigth = 2.0\n";
+    let err = toml::from_str::<Config>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, Some("shape.toml")),
+        "\
+error: unexpected keys in table: `[\"heigth\"]`, available keys: `[\"width\", \"height\"]` for key `shape`, did you mean `height`?
+ --> shape.toml:3:1
+  |
+3 | heigth = 2.0
+  | ^^^^^^
+  |
+  = help: available keys: `width`, `height`
+"
+    );
+}
@@ -0,0 +1,251 @@
+extern crate toml;
+
+use std::fs;
+use std::path::Path;
+
+use toml::document::{Array, Document, Table, Value};
+
+fn round_trip(input: &str) {
+    let doc = input.parse::<Document>().unwrap();
+    assert_eq!(doc.to_string(), input);
+}
+
+#[test]
+fn round_trips_valid_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/valid");
+    let mut count = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+            let input = fs::read_to_string(&path).unwrap();
+            let doc = input
+                .parse::<Document>()
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!(doc.to_string(), input, "{}", path.display());
+            count += 1;
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn round_trips_layout() {
+    round_trip("");
+    round_trip("\u{feff}a = 1");
+    round_trip("a = 1\r\n# comment\r\n[b]\r\nc = 2\r\n");
+    round_trip("  # leading\n\n\ta   =   'x'   # trailing\n\n\n# end");
+    round_trip("[ a . \"b c\" ]  # header\n  d.e = 1\n[[ f ]]\n[[f]]\ng = 2\n");
+    round_trip("a = [ 1 ,2,\n  # inside\n  3 , ]\nb = [ ]\nc = []\n");
+    round_trip("a = {  b = 1 ,c={ } , d . e = [ {f = 2} ] }\n");
+    round_trip("a = 0xDEAD_beef\nb = 1_000\nc = +1e1_0\nd = -inf\ne = 1979-05-27 07:32:00Z\n");
+    round_trip("a = \"\"\"\nmulti\\\n  line\"\"\"\nb = '''\nraw'''\n");
+}
+
+#[test]
+fn rejects_invalid_documents() {
+    let err = "a = 1\na = 2".parse::<Document>().unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key: `a` at line 2 column 1");
+    assert!("a = ".parse::<Document>().is_err());
+    assert!("a = {b = 1, b = 2}".parse::<Document>().is_err());
+    assert!("[a]\n[a]".parse::<Document>().is_err());
+    assert!("a.b = 1\n[a.b]".parse::<Document>().is_err());
+    assert!("a = [1, {b = 1, b.c = 2}]".parse::<Document>().is_err());
+
+    for input in &[
+        "a = 1 b = 2",
+        "[a] b = 1",
+        "[[a]] b = 1",
+        "x = { a = 1, }",
+        "x = { a = 1,\n b = 2 }",
+        "x = {\n}",
+        "x = { a = 1 # c\n}",
+    ] {
+        let expected = input.parse::<toml::Value>().unwrap_err();
+        let err = input.parse::<Document>().unwrap_err();
+        assert_eq!(err.to_string(), expected.to_string(), "{:?}", input);
+    }
+}
+
+#[test]
+fn rejects_invalid_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/invalid");
+    let mut count = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+            let input = fs::read_to_string(&path).unwrap();
+            if input.parse::<toml::Value>().is_err() {
+                assert!(input.parse::<Document>().is_err(), "{}", path.display());
+                count += 1;
+            }
+        }
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn reads_values() {
+    let doc = r#"
+name = "toml" # comment
+version = 0x10
+ratio = 1.25_5
+dotted.key = true
+date = 1979-05-27
+
+[table]
+array = [1, 2, 3]
+inline = { a = "b" }
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+"#
+    .parse::<Document>()
+    .unwrap();
+
+    let root = doc.root();
+    assert_eq!(root.len(), 5);
+    assert_eq!(root.get("name").unwrap().as_str(), Some("toml"));
+    assert_eq!(root.get("version").unwrap().as_integer(), Some(16));
+    assert_eq!(root.get("ratio").unwrap().as_float(), Some(1.255));
+    assert_eq!(
+        root.get_dotted(&["dotted", "key"]).unwrap().as_bool(),
+        Some(true)
+    );
+    assert!(root.get("dotted").is_none());
+    assert_eq!(
+        root.get("date").unwrap().as_datetime().unwrap().to_string(),
+        "1979-05-27"
+    );
+    match root.get("version").unwrap() {
+        Value::Integer(i) => assert_eq!(i.repr(), Some("0x10")),
+        v => panic!("unexpected {:?}", v),
+    }
+
+    let table = doc.table(&["table"]).unwrap();
+    let array = table.get("array").unwrap().as_array().unwrap();
+    let ints = array
+        .iter()
+        .map(|v| v.as_integer().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(ints, [1, 2, 3]);
+    let inline = table.get("inline").unwrap().as_inline_table().unwrap();
+    assert_eq!(inline.get("a").unwrap().as_str(), Some("b"));
+
+    let names = doc
+        .array_of_tables(&["bin"])
+        .map(|t| t.get("name").unwrap().as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "b"]);
+
+    let headers = doc.headers().collect::<Vec<_>>();
+    assert_eq!(headers.len(), 3);
+    assert_eq!(headers[0], (&["table".to_string()][..], false));
+    assert_eq!(headers[1], (&["bin".to_string()][..], true));
+}
+
+#[test]
+fn edits_keep_formatting() {
+    let mut doc = r#"# header comment
+title = "old"   # the title
+count = 1
+
+[server]
+# the port
+port = 80
+hosts = [
+    "a",
+    "b",
+]
+opts = { a = 1 }
+"#
+    .parse::<Document>()
+    .unwrap();
+
+    assert!(doc.root_mut().insert("title", "new").is_some());
+    doc.root_mut().remove("count");
+    let server = doc.table_mut(&["server"]).unwrap();
+    server.insert("port", 8080);
+    server
+        .get_mut("hosts")
+        .unwrap()
+        .as_array_mut()
+        .unwrap()
+        .push("c");
+    server
+        .get_mut("opts")
+        .unwrap()
+        .as_inline_table_mut()
+        .unwrap()
+        .insert("b c", 2.0);
+    server.insert("enabled", true);
+
+    assert_eq!(
+        doc.to_string(),
+        r#"# header comment
+title = "new"   # the title
+
+[server]
+# the port
+port = 8080
+hosts = [
+    "a",
+    "b",
+    "c",
+]
+opts = { a = 1, "b c" = 2.0 }
+enabled = true
+"#
+    );
+}
+
+#[test]
+fn edits_tables() {
+    let mut doc = "a = 1\n\n[x]\nb = 2\n\n# trailing\n"
+        .parse::<Document>()
+        .unwrap();
+    doc.insert_table(&["y", "z.w"]).insert("c", "three");
+    doc.push_array_of_tables(&["p"]).insert("d", 4);
+    doc.push_array_of_tables(&["p"]).insert("d", 5);
+    assert_eq!(
+        doc.to_string(),
+        "a = 1\n\n[x]\nb = 2\n\n# trailing\n\n[y.\"z.w\"]\nc = \"three\"\n\n[[p]]\nd = 4\n\n[[p]]\nd = 5\n"
+    );
+
+    let removed = doc.remove_table(&["x"]).unwrap();
+    assert_eq!(removed.get("b").unwrap().as_integer(), Some(2));
+    assert!(doc.table(&["x"]).is_none());
+    assert_eq!(doc.array_of_tables(&["p"]).count(), 2);
+    doc.to_string().parse::<toml::Value>().unwrap();
+
+    let mut doc = "a = 1".parse::<Document>().unwrap();
+    doc.root_mut().insert("b", 2);
+    doc.insert_table(&["c"]);
+    assert_eq!(doc.to_string(), "a = 1\nb = 2\n\n[c]\n");
+
+    let mut doc = Document::new();
+    doc.insert_table(&["a"]).insert("b", "c");
+    assert_eq!(doc.to_string(), "[a]\nb = \"c\"\n");
+}
+
+#[test]
+fn builds_new_values() {
+    let mut array = Array::new();
+    array.push(1);
+    array.push("two");
+    let mut table = Table::new();
+    table.insert("k", 0.5);
+    table.insert("nested", array);
+
+    let mut doc = Document::new();
+    doc.root_mut().insert("t", table);
+    doc.root_mut().insert("s", "quote\" and\nnewline");
+    doc.root_mut().insert("f", -0.0);
+    assert_eq!(
+        doc.to_string(),
+        "t = { k = 0.5, nested = [1, \"two\"] }\ns = \"quote\\\" and\\nnewline\"\nf = -0.0\n"
+    );
+    doc.to_string().parse::<Document>().unwrap();
+}
@@ -0,0 +1,174 @@
+extern crate serde;
+extern crate toml;
+
+use serde::Deserialize;
+use toml::de::{from_str_with_options, Deserializer, DuplicateKeyPolicy, ErrorKind, Options};
+use toml::Value;
+
+fn parse(input: &str, policy: DuplicateKeyPolicy) -> Result<Value, toml::de::Error> {
+    let mut de = Deserializer::new(input);
+    de.set_duplicate_key_policy(policy);
+    Value::deserialize(&mut de)
+}
+
+fn merged(input: &str, policy: DuplicateKeyPolicy) -> String {
+    toml::to_string(&parse(input, policy).unwrap()).unwrap()
+}
+
+#[test]
+fn errors_point_at_both_definitions() {
+    let input = "a = 1\nb = 2\na = 3\n";
+    let err = parse(input, DuplicateKeyPolicy::Error).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::DuplicateKey("a".to_string()));
+    assert_eq!(err.span(), Some(12..13));
+    assert_eq!(err.original_span(), Some(0..1));
+
+    let input = "[a]\nb = 1\n[a]\n";
+    let err = parse(input, DuplicateKeyPolicy::Error).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::DuplicateTable("a".to_string()));
+    assert_eq!(err.span(), Some(10..13));
+    assert_eq!(err.original_span(), Some(0..3));
+
+    // A key defined by a value and by a table header.
+    let input = "a = 1\n[a.b]\n";
+    let err = parse(input, DuplicateKeyPolicy::Error).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::DuplicateKey("a".to_string()));
+    assert_eq!(err.span(), Some(6..11));
+    assert_eq!(err.original_span(), Some(0..1));
+
+    let input = "t = { a = 1, a = 2 }";
+    let err = parse(input, DuplicateKeyPolicy::Error).unwrap_err();
+    assert_eq!(err.span(), Some(13..14));
+    assert_eq!(err.original_span(), Some(6..7));
+    assert_eq!(err.key_path(), ["t"]);
+
+    // A key defined by a value and extended by a dotted key.
+    for (input, span, original) in [
+        ("a = 1\na.b = 2", 6..7, 0..1),
+        ("a.b = 1\na.b.c = 2", 10..11, 2..3),
+        ("a = {x=1}\na.y = 2", 10..11, 0..1),
+    ] {
+        let err = parse(input, DuplicateKeyPolicy::Error).unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::DottedKeyInvalidType);
+        assert_eq!(err.span(), Some(span), "{}", input);
+        assert_eq!(err.original_span(), Some(original), "{}", input);
+    }
+
+    let err = toml::from_str::<Value>("a = 1_").unwrap_err();
+    assert_eq!(err.original_span(), None);
+}
+
+#[test]
+fn display_with_source() {
+    let input = "name = 'a'\nversion = 1\nname = 'b'\n";
+    let err = toml::from_str::<Value>(input).unwrap_err();
+    assert_eq!(
+        err.display_with_source(input, None),
+        "\
+error: duplicate key: `name`
+ --> 3:1
+  |
+3 | name = 'b'
+  | ^^^^
+  |
+1 | name = 'a'
+  | ---- first defined here
+"
+    );
+}
+
+#[test]
+fn first_wins() {
+    let policy = DuplicateKeyPolicy::FirstWins;
+    assert_eq!(merged("a = 1\na = 2\n", policy), "a = 1\n");
+    assert_eq!(
+        merged("[t]\nx = 1\n\n[t]\nx = 2\ny = 3\n", policy),
+        "[t]\nx = 1\n"
+    );
+    assert_eq!(merged("a = 1\n[a.b]\nc = 1\n", policy), "a = 1\n");
+    assert_eq!(merged("a = [1]\n[[a]]\nx = 1\n", policy), "a = [1]\n");
+}
+
+#[test]
+fn last_wins() {
+    let policy = DuplicateKeyPolicy::LastWins;
+    assert_eq!(merged("a = 1\na = 2\n", policy), "a = 2\n");
+    assert_eq!(
+        merged("[t]\nx = 1\ny = 2\n\n[t]\nx = 3\n", policy),
+        "[t]\nx = 3\n"
+    );
+    assert_eq!(merged("a = 1\n[a.b]\nc = 1\n", policy), "[a.b]\nc = 1\n");
+    assert_eq!(merged("t = { a = 1, a = 2 }\n", policy), "[t]\na = 2\n");
+}
+
+#[test]
+fn merge_tables() {
+    let policy = DuplicateKeyPolicy::MergeTables;
+    assert_eq!(
+        merged(
+            "[t]\nx = 1\ny = { p = 1 }\n\n[t]\nx = 2\ny = { q = 2 }\n",
+            policy
+        ),
+        "[t]\nx = 2\n\n[t.y]\np = 1\nq = 2\n"
+    );
+    assert_eq!(merged("a = 1\na = 2\n", policy), "a = 2\n");
+    assert_eq!(merged("a = { x = 1 }\na = 2\n", policy), "a = 2\n");
+}
+
+#[test]
+fn implied_tables_are_always_extended() {
+    for &policy in &[
+        DuplicateKeyPolicy::FirstWins,
+        DuplicateKeyPolicy::LastWins,
+        DuplicateKeyPolicy::MergeTables,
+    ] {
+        assert_eq!(
+            merged("[a.b]\nx = 1\n\n[a]\nb.y = 2\nc = 3\n", policy),
+            "[a]\nc = 3\n\n[a.b]\nx = 1\ny = 2\n"
+        );
+        let input = "[[p]]\n[p.q]\nx = 1\n[[p]]\n[p.q]\nx = 2\n";
+        assert_eq!(
+            merged(input, policy),
+            merged(input, DuplicateKeyPolicy::Error)
+        );
+    }
+}
+
+#[test]
+fn inline_tables_are_never_extended() {
+    for &policy in &[
+        DuplicateKeyPolicy::FirstWins,
+        DuplicateKeyPolicy::LastWins,
+        DuplicateKeyPolicy::MergeTables,
+    ] {
+        let err = parse("a = { x = 1 }\na.y = 2\n", policy).unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::DottedKeyInvalidType);
+        assert_eq!(err.span(), Some(14..15));
+        assert_eq!(err.original_span(), Some(0..1));
+        assert!(parse("a = { x = { y = 1 } }\na.x.z = 2\n", policy).is_err());
+        assert!(parse("t = { a = { x = 1 }, a.y = 2 }\n", policy).is_err());
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+}
+
+#[test]
+fn typed() {
+    let input = "name = 'a'\nport = 80\nport = 8080\n";
+    let err = toml::from_str::<Config>(input).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key: `port` at line 3 column 1");
+
+    let options = Options::new().duplicate_key_policy(DuplicateKeyPolicy::LastWins);
+    let config: Config = from_str_with_options(input, &options).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "a".to_string(),
+            port: 8080
+        }
+    );
+}
@@ -0,0 +1,141 @@
+extern crate toml;
+
+use std::env;
+
+use toml::env::{overlay, overlay_from, ErrorKind};
+use toml::Value;
+
+fn config() -> Value {
+    "name = 'app'\nratio = 0.5\n[database]\nurl = 'postgres://localhost'\npool_size = 10\n"
+        .parse()
+        .unwrap()
+}
+
+fn overlaid(vars: &[(&str, &str)]) -> Result<Value, toml::env::Error> {
+    let mut value = config();
+    overlay_from(&mut value, vars.iter().cloned(), "APP", "__")?;
+    Ok(value)
+}
+
+#[test]
+fn values_are_parsed_as_toml() {
+    let value = overlaid(&[
+        ("APP__DATABASE__POOL_SIZE", "20"),
+        ("APP__DATABASE__URL", "mysql://remote"),
+        ("APP__NAME", "42"),
+        ("APP__RATIO", "2"),
+        ("APP__NEW__ENABLED", "true"),
+        ("APP__NEW__HOSTS", "['a', 'b']"),
+        ("APP__NEW__TEXT", "hello world"),
+        ("APP__NEW__QUOTED", "'1'"),
+        ("APP__NEW__WHEN", "1979-05-27"),
+        ("APP__NEW__INLINE", "{ a = 1 }"),
+        ("APP__NEW__TWO", "1\nb = 2"),
+    ])
+    .unwrap();
+    assert_eq!(value["database"]["pool_size"].as_integer(), Some(20));
+    assert_eq!(value["database"]["url"].as_str(), Some("mysql://remote"));
+    // Strings stay strings, whatever they look like.
+    assert_eq!(value["name"].as_str(), Some("42"));
+    assert_eq!(value["ratio"].as_float(), Some(2.0));
+
+    let new = &value["new"];
+    assert_eq!(new["enabled"].as_bool(), Some(true));
+    assert_eq!(new["hosts"], Value::from(vec!["a", "b"]));
+    assert_eq!(new["text"].as_str(), Some("hello world"));
+    assert_eq!(new["quoted"].as_str(), Some("1"));
+    assert!(new["when"].is_datetime());
+    assert_eq!(new["inline"]["a"].as_integer(), Some(1));
+    assert_eq!(new["two"].as_str(), Some("1\nb = 2"));
+}
+
+#[test]
+fn only_prefixed_variables_are_used() {
+    let value = overlaid(&[
+        ("PATH", "/usr/bin"),
+        ("APP", "1"),
+        ("APPNAME", "1"),
+        ("app__name", "1"),
+        ("OTHER__NAME", "1"),
+    ])
+    .unwrap();
+    assert_eq!(value, config());
+
+    let mut value = config();
+    let vars = vec![("MY_APP.DATABASE.POOL_SIZE", "5")];
+    overlay_from(&mut value, vars, "MY_APP", ".").unwrap();
+    assert_eq!(value["database"]["pool_size"].as_integer(), Some(5));
+}
+
+#[test]
+fn conversion_failures_name_the_variable() {
+    let err = overlaid(&[("APP__DATABASE__POOL_SIZE", "many")]).unwrap_err();
+    assert_eq!(err.var(), "APP__DATABASE__POOL_SIZE");
+    assert_eq!(err.key_path(), ["database", "pool_size"]);
+    assert_eq!(
+        *err.kind(),
+        ErrorKind::InvalidType {
+            expected: "integer"
+        }
+    );
+
+    let err = overlaid(&[("APP__NAME__FIRST", "x")]).unwrap_err();
+    assert_eq!(err.key_path(), ["name"]);
+    assert_eq!(
+        err.to_string(),
+        "invalid value for environment variable `APP__NAME__FIRST`: \
+         expected table for key `name`"
+    );
+
+    let err = overlaid(&[("APP__DATABASE", "x")]).unwrap_err();
+    assert_eq!(err.key_path(), ["database"]);
+
+    let err = overlaid(&[("APP__RATIO", "1.0.0")]).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::InvalidType { expected: "float" });
+
+    let err = overlaid(&[("APP____NAME", "x")]).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::InvalidName);
+    assert_eq!(
+        err.to_string(),
+        "environment variable `APP____NAME` has an empty key in its name"
+    );
+}
+
+#[test]
+fn failures_leave_the_value_unchanged() {
+    let mut value = config();
+    let vars = vec![("APP__A", "1"), ("APP__DATABASE__POOL_SIZE", "x")];
+    assert!(overlay_from(&mut value, vars, "APP", "__").is_err());
+    assert_eq!(value, config());
+
+    // Variables are applied in order of their names.
+    let mut value = config();
+    let vars = vec![("APP__B__C", "1"), ("APP__B", "1")];
+    let err = overlay_from(&mut value, vars, "APP", "__").unwrap_err();
+    assert_eq!(err.var(), "APP__B__C");
+}
+
+#[cfg(unix)]
+#[test]
+fn not_unicode() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut value = config();
+    let vars = vec![(OsStr::new("APP__NAME"), OsStr::from_bytes(b"\xff"))];
+    let err = overlay_from(&mut value, vars, "APP", "__").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::NotUnicode);
+    assert_eq!(
+        err.to_string(),
+        "environment variable `APP__NAME` is not valid unicode"
+    );
+}
+
+#[test]
+fn environment() {
+    env::set_var("TOML_ENV_OVERLAY_TEST__DATABASE__POOL_SIZE", "30");
+    let mut value = config();
+    overlay(&mut value, "TOML_ENV_OVERLAY_TEST", "__").unwrap();
+    assert_eq!(value["database"]["pool_size"].as_integer(), Some(30));
+    env::remove_var("TOML_ENV_OVERLAY_TEST__DATABASE__POOL_SIZE");
+}
@@ -0,0 +1,106 @@
+extern crate serde;
+extern crate toml;
+
+use serde::Deserialize;
+use toml::de::from_str_at;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Tool {
+    level: u32,
+    #[serde(default)]
+    names: Vec<String>,
+}
+
+const MANIFEST: &str = r#"
+[package]
+name = "foo"
+
+[package.metadata.other]
+level = "high"
+
+[package.metadata.tool]
+level = 3
+names = ["a", "b"]
+
+[[bin]]
+name = "x"
+
+[dependencies]
+serde = "1.0"
+"#;
+
+#[test]
+fn finds_subtree() {
+    let tool = from_str_at::<Tool>(MANIFEST, &["package", "metadata", "tool"]).unwrap();
+    assert_eq!(
+        tool,
+        Some(Tool {
+            level: 3,
+            names: vec!["a".to_string(), "b".to_string()],
+        })
+    );
+    let serde = from_str_at::<String>(MANIFEST, &["dependencies", "serde"]).unwrap();
+    assert_eq!(serde.as_deref(), Some("1.0"));
+    let bins = from_str_at::<Vec<toml::Value>>(MANIFEST, &["bin"]).unwrap();
+    assert_eq!(bins.unwrap().len(), 1);
+    let all = from_str_at::<toml::Value>(MANIFEST, &[]).unwrap();
+    assert_eq!(all, Some(MANIFEST.parse().unwrap()));
+}
+
+#[test]
+fn missing_path() {
+    assert_eq!(
+        from_str_at::<Tool>(MANIFEST, &["package", "metadata", "missing"]).unwrap(),
+        None
+    );
+    assert_eq!(
+        from_str_at::<Tool>(MANIFEST, &["missing", "tool"]).unwrap(),
+        None
+    );
+    assert_eq!(from_str_at::<Tool>("", &["tool"]).unwrap(), None);
+}
+
+#[test]
+fn errors_have_full_path_and_position() {
+    let err = from_str_at::<Tool>(MANIFEST, &["package", "metadata", "other"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: string \"high\", expected u32 for key `package.metadata.other.level` at line 6 column 9"
+    );
+
+    let err = from_str_at::<Tool>(MANIFEST, &["package", "name", "tool"]).unwrap_err();
+    assert_eq!(err.key_path(), ["package", "name"]);
+    assert_eq!(err.line_col(), Some((2, 7)));
+
+    // The rest of the document is still checked.
+    let err = from_str_at::<Tool>("[a]\n[b]\n[a]\n", &["b"]).unwrap_err();
+    assert_eq!(err.line_col(), Some((2, 0)));
+    assert!(from_str_at::<Tool>("[a]\nb = ", &["a"]).is_err());
+}
+
+#[test]
+fn value_try_into_at() {
+    let value = MANIFEST.parse::<toml::Value>().unwrap();
+    let tool = value
+        .clone()
+        .try_into_at::<Tool>(&["package", "metadata", "tool"])
+        .unwrap();
+    assert_eq!(tool.unwrap().level, 3);
+    assert_eq!(
+        value
+            .clone()
+            .try_into_at::<Tool>(&["package", "missing"])
+            .unwrap(),
+        None
+    );
+
+    let err = value
+        .clone()
+        .try_into_at::<Tool>(&["package", "metadata", "other"])
+        .unwrap_err();
+    assert_eq!(err.key_path(), ["package", "metadata", "other", "level"]);
+    let err = value
+        .try_into_at::<Tool>(&["package", "name", "tool"])
+        .unwrap_err();
+    assert_eq!(err.key_path(), ["package", "name"]);
+}
@@ -0,0 +1,182 @@
+extern crate serde;
+extern crate toml;
+
+use serde::Deserialize;
+use toml::interpolate::{ErrorKind, Resolver};
+use toml::Value;
+
+fn expand(input: &str) -> Result<Value, toml::interpolate::Error> {
+    let mut value = input.parse().unwrap();
+    let resolver = Resolver::new()
+        .scheme("env", |name| match name {
+            "HOME" => Some("/home/me".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        })
+        .scheme("upper", |name| Some(name.to_uppercase()));
+    toml::interpolate(&mut value, &resolver)?;
+    Ok(value)
+}
+
+#[test]
+fn references() {
+    let value = expand(
+        r#"
+        base = "/srv"
+        logs = "${base}/logs"
+        nested = '${paths.cache} and ${ "quoted.key" }'
+        "quoted.key" = "q"
+        list = ["${base}", "x${logs}"]
+        mixed = "${number}:${float}:${flag}:${date}"
+        number = 8080
+        float = 0.5
+        flag = true
+        date = 1979-05-27
+
+        [paths]
+        cache = "${logs}/cache"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(value["logs"].as_str(), Some("/srv/logs"));
+    assert_eq!(value["paths"]["cache"].as_str(), Some("/srv/logs/cache"));
+    assert_eq!(value["nested"].as_str(), Some("/srv/logs/cache and q"));
+    assert_eq!(value["list"], Value::from(vec!["/srv", "x/srv/logs"]));
+    assert_eq!(value["mixed"].as_str(), Some("8080:0.5:true:1979-05-27"));
+}
+
+#[test]
+fn whole_references_keep_their_type() {
+    let value = expand(
+        r#"
+        port = "${defaults.port}"
+        hosts = "${defaults.hosts}"
+        copy = "${defaults}"
+        indirect = "${port}"
+
+        [defaults]
+        port = 8080
+        hosts = ["${env:HOME}", "b"]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(value["port"].as_integer(), Some(8080));
+    assert_eq!(value["hosts"], Value::from(vec!["/home/me", "b"]));
+    assert_eq!(value["copy"], value["defaults"]);
+    assert_eq!(value["indirect"].as_integer(), Some(8080));
+}
+
+#[test]
+fn schemes_and_escapes() {
+    let value = expand(
+        r#"
+        home = "${env:HOME}/.config"
+        empty = "[${env:EMPTY}]"
+        upper = "${upper:abc}"
+        escaped = "$${env:HOME} costs $5 or $$"
+        both = "$${a}${env:HOME}"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(value["home"].as_str(), Some("/home/me/.config"));
+    assert_eq!(value["empty"].as_str(), Some("[]"));
+    assert_eq!(value["upper"].as_str(), Some("ABC"));
+    assert_eq!(
+        value["escaped"].as_str(),
+        Some("${env:HOME} costs $5 or $$")
+    );
+    assert_eq!(value["both"].as_str(), Some("${a}/home/me"));
+}
+
+#[test]
+fn undefined() {
+    let err = expand("[a]\nb = ['x', '${missing.key}']").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Undefined);
+    assert_eq!(err.key_path(), ["a", "b", "1"]);
+    assert_eq!(err.reference(), "${missing.key}");
+    assert_eq!(
+        err.to_string(),
+        "undefined reference `${missing.key}` for key `a.b.1`"
+    );
+
+    let err = expand("a = '${env:NOPE}'").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Undefined);
+    let err = expand("a = '${other:x}'").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Undefined);
+    let err = expand("a = 1\nb = '${a.c}'").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Undefined);
+}
+
+#[test]
+fn cycles() {
+    let err = expand("a = '${a}'").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Cycle);
+    assert_eq!(err.to_string(), "cyclic reference `${a}` for key `a`");
+
+    let err = expand("a = 'x${b}'\nb = '${c}'\nc = '${a}'").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Cycle);
+    assert_eq!(err.key_path(), ["c"]);
+
+    let err = expand("[t]\nx = '${t}'").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Cycle);
+}
+
+#[test]
+fn invalid() {
+    for input in &[
+        "a = '${'",
+        "a = 'x ${b'",
+        "a = '${}'",
+        "a = '${a..b}'",
+        "a = '${a = 1}'",
+        "a = '${a.b:c}'",
+    ] {
+        let err = expand(input).unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::Invalid, "{}", input);
+    }
+    let err = expand("a = 'x ${b'").unwrap_err();
+    assert_eq!(err.to_string(), "invalid reference `${b` for key `a`");
+
+    let err = expand("a = 'x ${t}'\n[t]").unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::InvalidType { found: "table" });
+    assert_eq!(
+        err.to_string(),
+        "cannot interpolate table `${t}` into a string for key `a`"
+    );
+}
+
+#[test]
+fn failures_leave_the_value_unchanged() {
+    let input = "a = '${b}'\nb = 'b'\nc = '${nope}'";
+    let mut value: Value = input.parse().unwrap();
+    assert!(toml::interpolate(&mut value, &Resolver::new()).is_err());
+    assert_eq!(value, input.parse::<Value>().unwrap());
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config {
+    url: String,
+    port: u16,
+}
+
+#[test]
+fn typed() {
+    let value = expand("host = 'db'\nport = 5432\nurl = 'pg://${host}:${port}'\n").unwrap();
+    let config: Config = value.try_into().unwrap();
+    assert_eq!(
+        config,
+        Config {
+            url: "pg://db:5432".to_string(),
+            port: 5432
+        }
+    );
+}
+
+#[test]
+fn unsigned_integers() {
+    let options = toml::de::Options::new().unsigned_integers(true);
+    let input = "big = 18446744073709551615\nid = \"x${big}\"";
+    let mut value = toml::de::from_str_with_options::<Value>(input, &options).unwrap();
+    toml::interpolate(&mut value, &Resolver::new()).unwrap();
+    assert_eq!(value["id"].as_str(), Some("x18446744073709551615"));
+}
@@ -15,7 +15,9 @@ fn bad() {
     bad!("a = 1__1", "invalid number at line 1 column 5");
     bad!("a = 1_", "invalid number at line 1 column 5");
     bad!("''", "expected an equals, found eof at line 1 column 3");
-    bad!("a = 9e99999", "invalid number at line 1 column 5");
+    if !cfg!(feature = "arbitrary_precision") {
+        bad!("a = 9e99999", "invalid number at line 1 column 5");
+    }
 
     bad!(
         "a = \"\u{7f}\"",
@@ -37,15 +39,15 @@ fn bad() {
         "a.b.c = 1
          a.b = 2
         ",
-        "duplicate key: `b` for key `a` at line 1 column 9"
+        "duplicate key: `b` for key `a` at line 2 column 12"
     );
     bad!(
         "a = 1
          a.b = 2",
-        "dotted key attempted to extend non-table type at line 1 column 5"
+        "dotted key attempted to extend non-table type at line 2 column 10"
     );
     bad!(
         "a = {k1 = 1, k1.name = \"joe\"}",
-        "dotted key attempted to extend non-table type at line 1 column 11"
+        "dotted key attempted to extend non-table type at line 1 column 14"
     );
 }
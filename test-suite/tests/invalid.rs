@@ -42,7 +42,7 @@ test!(
 test!(
     duplicate_keys,
     include_str!("invalid/duplicate-keys.toml"),
-    "duplicate key: `dupe` at line 1 column 1"
+    "duplicate key: `dupe` at line 2 column 1"
 );
 test!(
     duplicate_table,
@@ -0,0 +1,112 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+use std::io::{self, Read, Write};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    ports: Vec<u16>,
+    server: Server,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Server {
+    host: String,
+    text: String,
+}
+
+fn config() -> Config {
+    Config {
+        name: "test".to_string(),
+        ports: vec![80, 443],
+        server: Server {
+            host: "localhost".to_string(),
+            text: "multi\nline".to_string(),
+        },
+    }
+}
+
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct FailingReader;
+
+impl Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "no access"))
+    }
+}
+
+#[test]
+fn to_writer_matches_to_string() {
+    let mut out = Vec::new();
+    toml::to_writer(&mut out, &config()).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        toml::to_string(&config()).unwrap()
+    );
+
+    let mut out = Vec::new();
+    toml::to_writer_pretty(&mut out, &config()).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        toml::to_string_pretty(&config()).unwrap()
+    );
+
+    let mut out = Vec::new();
+    let mut ser = toml::Serializer::from_writer(&mut out);
+    ser.pretty_array(true);
+    serde::Serialize::serialize(&config(), &mut ser).unwrap();
+    let mut expected = String::new();
+    let mut ser = toml::Serializer::new(&mut expected);
+    ser.pretty_array(true);
+    serde::Serialize::serialize(&config(), &mut ser).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn to_writer_io_error() {
+    let err = toml::to_writer(FailingWriter, &config()).unwrap_err();
+    assert_eq!(
+        err,
+        toml::ser::Error::Io(io::ErrorKind::BrokenPipe, "pipe closed".to_string())
+    );
+    assert_eq!(err.to_string(), "I/O error: pipe closed");
+}
+
+#[test]
+fn from_reader() {
+    let input = toml::to_string(&config()).unwrap();
+    let parsed: Config = toml::from_reader(input.as_bytes()).unwrap();
+    assert_eq!(parsed, config());
+
+    let value: toml::Value = toml::from_reader(&b"a = 1"[..]).unwrap();
+    assert_eq!(value["a"].as_integer(), Some(1));
+
+    let err = toml::from_reader::<_, toml::Value>(&b"a = "[..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unexpected eof encountered at line 1 column 5"
+    );
+
+    assert!(toml::from_reader::<_, toml::Value>(&b"a = '\xff'"[..]).is_err());
+}
+
+#[test]
+fn from_reader_io_error() {
+    let err = toml::from_reader::<_, toml::Value>(FailingReader).unwrap_err();
+    assert_eq!(err.to_string(), "I/O error: no access");
+    let err = io::Error::from(err);
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+}
@@ -0,0 +1,166 @@
+extern crate toml;
+
+use std::fs;
+use std::path::Path;
+
+use toml::lexer::{Lexer, Span, StringKind, Token};
+
+fn lex(input: &str) -> Vec<(&str, Token<'_>)> {
+    Lexer::new(input)
+        .map(|(Span { start, end }, token)| (&input[start..end], token))
+        .collect()
+}
+
+fn assert_covers(input: &str) {
+    let mut at = input.len() - input.trim_start_matches('\u{feff}').len();
+    for (span, _) in Lexer::new(input) {
+        assert_eq!(span.start, at, "gap in {:?}", input);
+        assert!(span.end > span.start, "empty token in {:?}", input);
+        at = span.end;
+    }
+    assert_eq!(at, input.len(), "{:?} not fully lexed", input);
+}
+
+#[test]
+fn trivia() {
+    let tokens = lex("a = 1 # one\r\n\t[b]\n");
+    let expected = vec![
+        ("a", Token::Keylike("a")),
+        (" ", Token::Whitespace(" ")),
+        ("=", Token::Equals),
+        (" ", Token::Whitespace(" ")),
+        ("1", Token::Keylike("1")),
+        (" ", Token::Whitespace(" ")),
+        ("# one", Token::Comment("# one")),
+        ("\r\n", Token::Newline),
+        ("\t", Token::Whitespace("\t")),
+        ("[", Token::LeftBracket),
+        ("b", Token::Keylike("b")),
+        ("]", Token::RightBracket),
+        ("\n", Token::Newline),
+    ];
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn string_kinds() {
+    let input = r##"'a' "b\tc" '''d''' """e"""""##;
+    let strings = lex(input)
+        .into_iter()
+        .filter_map(|(src, token)| match token {
+            Token::String { val, kind, .. } => Some((src, val.into_owned(), kind)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        strings,
+        vec![
+            ("'a'", "a".to_string(), StringKind::Literal),
+            (r#""b\tc""#, "b\tc".to_string(), StringKind::Basic),
+            ("'''d'''", "d".to_string(), StringKind::MultilineLiteral),
+            (
+                r##""""e"""""##,
+                "e\"".to_string(),
+                StringKind::MultilineBasic
+            ),
+        ]
+    );
+    assert!(StringKind::MultilineLiteral.is_multiline());
+    assert!(StringKind::MultilineLiteral.is_literal());
+    assert!(StringKind::Basic.is_basic());
+    assert!(!StringKind::Basic.is_multiline());
+}
+
+#[test]
+fn describe() {
+    assert_eq!(Token::Equals.describe(), "an equals");
+    assert_eq!(Token::Keylike("a").describe(), "an identifier");
+    let (_, token) = Lexer::new("'''a'''").next().unwrap();
+    assert_eq!(token.describe(), "a multiline string");
+    let (_, token) = Lexer::new("\u{0}").next().unwrap();
+    assert_eq!(token.describe(), "an invalid token");
+}
+
+#[test]
+fn recovers_from_errors() {
+    let tokens = lex("a = \"b\\q\" \u{0}\nc = 'd\ne = \"\"\"f\\q\n\"\"\" # g\nh = \"i");
+    let errors = tokens
+        .iter()
+        .filter_map(|(src, token)| match token {
+            Token::Error(e) => Some((*src, e.to_string())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                "\"b\\q\"",
+                "invalid escape character in string: `q` at line 1 column 8".to_string()
+            ),
+            (
+                "\u{0}",
+                "unexpected character found: `\\u{0}` at line 1 column 11".to_string()
+            ),
+            (
+                "'d",
+                "newline in string found at line 2 column 7".to_string()
+            ),
+            (
+                "\"\"\"f\\q\n\"\"\"",
+                "invalid escape character in string: `q` at line 3 column 10".to_string()
+            ),
+            ("\"i", "unterminated string at line 5 column 5".to_string()),
+        ]
+    );
+
+    // Lexing carries on with the tokens following each error.
+    let rest = tokens
+        .iter()
+        .skip_while(|(src, _)| *src != "'d")
+        .map(|(_, token)| token.clone())
+        .take(6)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rest[1..],
+        [
+            Token::Newline,
+            Token::Keylike("e"),
+            Token::Whitespace(" "),
+            Token::Equals,
+            Token::Whitespace(" "),
+        ]
+    );
+    assert!(tokens.contains(&("# g", Token::Comment("# g"))));
+}
+
+#[test]
+fn covers_input() {
+    let inputs = [
+        "",
+        "\u{feff}a = 1",
+        "a\r",
+        "\"\\",
+        "'''abc",
+        "\"\"\"a\\u12\"\"\"\"\"\n",
+        "x = \"\u{7f}\"\r\n",
+        "é = ☃\n",
+    ];
+    for input in inputs.iter() {
+        assert_covers(input);
+    }
+
+    for dir in ["valid", "invalid"].iter() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(dir);
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+                if let Ok(input) = fs::read_to_string(&path) {
+                    assert_covers(&input);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+extern crate serde;
+extern crate toml;
+
+use serde::Deserialize;
+use toml::de::{from_str_with_options, Deserializer, ErrorKind, Limits, Options};
+use toml::Value;
+
+fn parse(input: &str, limits: Limits) -> Result<Value, toml::de::Error> {
+    let mut de = Deserializer::new(input);
+    de.set_limits(limits);
+    Value::deserialize(&mut de)
+}
+
+fn exceeded(input: &str, limits: Limits) -> &'static str {
+    match *parse(input, limits).unwrap_err().kind() {
+        ErrorKind::LimitExceeded { limit, .. } => limit,
+        ref kind => panic!("unexpected error {:?} for {:?}", kind, input),
+    }
+}
+
+#[test]
+fn depth() {
+    let limits = Limits {
+        max_depth: 3,
+        ..Limits::default()
+    };
+    for input in &[
+        "a = [[1]]",
+        "a.b = [1]",
+        "a = { b = [1] }",
+        "[a.b]\nc = 1",
+        "[[a]]\nc = 1",
+        "a = [{ b = 1 }]",
+    ] {
+        assert!(parse(input, limits).is_ok(), "{}", input);
+    }
+    for input in &[
+        "a = [[[1]]]",
+        "a.b.c.d = 1",
+        "a = { b = { c = { d = 1 } } }",
+        "[a.b.c.d]",
+        "[[a.b.c]]",
+        "[a.b.c]\nd = 1",
+        "a = [{ b = [1] }]",
+    ] {
+        assert_eq!(exceeded(input, limits), "max_depth", "{}", input);
+    }
+
+    let err = parse("a = [[[1]]]", limits).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "exceeded the `max_depth` limit of 3 at line 1 column 7"
+    );
+}
+
+#[test]
+fn deep_nesting_is_rejected_without_overflowing() {
+    let limits = Limits {
+        max_depth: 128,
+        ..Limits::default()
+    };
+    let input = format!("a = {}", "[".repeat(100_000));
+    assert_eq!(exceeded(&input, limits), "max_depth");
+    let input = format!("a = {}", "{ b = ".repeat(100_000));
+    assert_eq!(exceeded(&input, limits), "max_depth");
+    let input = format!("a{} = 1", ".a".repeat(100_000));
+    assert_eq!(exceeded(&input, limits), "max_depth");
+}
+
+#[test]
+fn keys() {
+    let limits = Limits {
+        max_keys: 4,
+        ..Limits::default()
+    };
+    assert!(parse("a = 1\n[b]\nc = { d.e = 2 }", limits).is_ok());
+    let err = parse("a = 1\n[b]\nc = { d = 2, e = 3 }", limits).unwrap_err();
+    assert_eq!(
+        *err.kind(),
+        ErrorKind::LimitExceeded {
+            limit: "max_keys",
+            max: 4
+        }
+    );
+    assert_eq!(err.span(), Some(23..24));
+    assert_eq!(
+        exceeded("[[a]]\n[[a]]\n[[a]]\n[[a]]\n[[a]]", limits),
+        "max_keys"
+    );
+}
+
+#[test]
+fn strings() {
+    let limits = Limits {
+        max_string_len: 3,
+        ..Limits::default()
+    };
+    assert!(parse("abc = 'abc'\n'def' = \"\\u00e9\"", limits).is_ok());
+    for input in &[
+        "a = 'abcd'",
+        "a = \"\"\"\nabcd\"\"\"",
+        "abcd = 1",
+        "a.'abcd' = 1",
+        "[abcd]",
+        "a = ['abcd']",
+        "a = \"\\u00e9\\u00e9\"",
+    ] {
+        assert_eq!(exceeded(input, limits), "max_string_len", "{}", input);
+    }
+    let err = parse("a = 1\nb = 'abcd'", limits).unwrap_err();
+    assert_eq!(err.line_col(), Some((1, 4)));
+    // Strings are rejected once they are too long, without reading the rest.
+    assert_eq!(err.span(), Some(10..15));
+    assert_eq!(exceeded("a = 'abcd", limits), "max_string_len");
+    assert_eq!(exceeded("a = \"\\t\\t\\t\\t", limits), "max_string_len");
+}
+
+#[test]
+fn input() {
+    let limits = Limits {
+        max_input_len: 5,
+        ..Limits::default()
+    };
+    assert!(parse("a = 1", limits).is_ok());
+    let err = parse("a = 10", limits).unwrap_err();
+    assert_eq!(
+        *err.kind(),
+        ErrorKind::LimitExceeded {
+            limit: "max_input_len",
+            max: 5
+        }
+    );
+    assert_eq!(err.offset(), Some(5));
+
+    // The error points at a character boundary.
+    let err = parse("a = 'é'", limits).unwrap_err();
+    assert_eq!(err.offset(), Some(5));
+    let err = parse("ab='é'", limits).unwrap_err();
+    assert_eq!(err.offset(), Some(4));
+}
+
+#[test]
+fn arrays() {
+    let limits = Limits {
+        max_array_len: 2,
+        ..Limits::default()
+    };
+    assert!(parse("a = [1, 2]\nb = [[1, 2], [3, 4]]", limits).is_ok());
+    let err = parse("a = [1, 2, 3]", limits).unwrap_err();
+    assert_eq!(
+        *err.kind(),
+        ErrorKind::LimitExceeded {
+            limit: "max_array_len",
+            max: 2
+        }
+    );
+    assert_eq!(err.span(), Some(11..12));
+    assert_eq!(exceeded("a = [[1, 2, 3]]", limits), "max_array_len");
+
+    // The error covers the whole of the value which didn't fit.
+    let err = parse("a = [1, 2, {x = 1}]", limits).unwrap_err();
+    assert_eq!(err.span(), Some(11..18));
+}
+
+#[test]
+fn options() {
+    let options = Options::new().limits(Limits {
+        max_keys: 1,
+        ..Limits::default()
+    });
+    assert!(from_str_with_options::<Value>("a = 1", &options).is_ok());
+    let err = from_str_with_options::<Value>("a = 1\nb = 2", &options).unwrap_err();
+    assert_eq!(err.line_col(), Some((1, 0)));
+}
+
+#[test]
+fn defaults() {
+    let depth = Limits::DEFAULT_MAX_DEPTH;
+    let input = format!("a = {}1{}", "[".repeat(depth - 1), "]".repeat(depth - 1));
+    assert!(input.parse::<Value>().is_ok());
+    let input = format!("a = {}1{}", "[".repeat(depth), "]".repeat(depth));
+    let err = input.parse::<Value>().unwrap_err();
+    assert_eq!(
+        *err.kind(),
+        ErrorKind::LimitExceeded {
+            limit: "max_depth",
+            max: depth
+        }
+    );
+    assert_eq!(Limits::default().max_keys, usize::MAX);
+}
@@ -0,0 +1,121 @@
+extern crate serde;
+extern crate toml;
+
+use serde::{Deserialize, Serialize};
+use toml::de::{from_str_with_options, ErrorKind, Options};
+use toml::Value;
+
+fn parse(input: &str) -> Result<Value, toml::de::Error> {
+    from_str_with_options(input, &Options::new().preserve_number_format(true))
+}
+
+#[test]
+fn round_trip() {
+    let input = "\
+mode = 0o755
+mask = 0xDEAD_BEEF
+flags = 0b1010
+count = 1_000_000
+offset = +7
+avogadro = 6.022e23
+planck = 6.626_070_15E-34
+ratio = 0.50
+none = -nan
+";
+    let value = parse(input).unwrap();
+    assert_eq!(value["mode"].as_integer(), Some(0o755));
+    assert_eq!(value["mask"].as_integer(), Some(0xdead_beef));
+    assert_eq!(value["count"].as_integer(), Some(1_000_000));
+    assert_eq!(value["avogadro"].as_float(), Some(6.022e23));
+    assert_eq!(value["mode"].as_number().unwrap().as_str(), "0o755");
+    assert_eq!(value["ratio"].type_str(), "float");
+
+    let mut expected = input.lines().collect::<Vec<_>>();
+    expected.sort_unstable();
+    let out = value.to_string();
+    let mut out = out.lines().collect::<Vec<_>>();
+    out.sort_unstable();
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn nested() {
+    let input = "a = [0x1, 2e0]\n\n[b]\nc = { d = 0b11 }\n";
+    let value = parse(input).unwrap();
+    assert_eq!(value.to_string(), "a = [0x1, 2e0]\n[b.c]\nd = 0b11\n");
+}
+
+#[test]
+fn still_range_checked() {
+    if !cfg!(feature = "arbitrary_precision") {
+        let err = parse("a = 0x8000_0000_0000_0000").unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::NumberInvalid);
+    }
+    let options = Options::new()
+        .preserve_number_format(true)
+        .unsigned_integers(true);
+    let value = from_str_with_options::<Value>("a = 0xffff_ffff_ffff_ffff", &options).unwrap();
+    assert_eq!(value["a"].as_uinteger(), Some(u64::MAX));
+    assert_eq!(value.to_string(), "a = 0xffff_ffff_ffff_ffff\n");
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    mode: u32,
+    scale: f64,
+}
+
+#[test]
+fn typed() {
+    let input = "mode = 0o755\nscale = 1e3\n";
+    let options = Options::new().preserve_number_format(true);
+    let config: Config = from_str_with_options(input, &options).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            mode: 0o755,
+            scale: 1000.0
+        }
+    );
+
+    let value = parse(input).unwrap();
+    assert_eq!(value.clone().try_into::<Config>().unwrap(), config);
+
+    // Numbers only remember their format in a `Value`.
+    assert_eq!(
+        toml::to_string(&config).unwrap(),
+        "mode = 493\nscale = 1000.0\n"
+    );
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Flattened {
+    name: String,
+    #[serde(flatten)]
+    rest: std::collections::BTreeMap<String, Scale>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Scale {
+    Whole(i64),
+    Fraction(f64),
+}
+
+#[test]
+fn flattened() {
+    // Fields which buffer their contents are given the numbers themselves.
+    let input = "name = 'x'\nmode = 0o755\nscale = 1e3\n";
+    let options = Options::new().preserve_number_format(true);
+    let flattened: Flattened = from_str_with_options(input, &options).unwrap();
+    assert_eq!(flattened.rest["mode"], Scale::Whole(0o755));
+    assert_eq!(flattened.rest["scale"], Scale::Fraction(1000.0));
+    assert_eq!(flattened, toml::from_str(input).unwrap());
+}
+
+#[test]
+fn default() {
+    let value: Value = "mode = 0o755".parse().unwrap();
+    assert_eq!(value["mode"], Value::Integer(0o755));
+    assert_eq!(value.to_string(), "mode = 493\n");
+}
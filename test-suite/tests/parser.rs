@@ -251,6 +251,11 @@ fn bad_leading_zeros() {
     bad!("a = 00.0", "invalid number at line 1 column 6");
     bad!("a = -00.0", "invalid number at line 1 column 7");
     bad!("a = +00.0", "invalid number at line 1 column 7");
+}
+
+#[test]
+#[cfg(not(feature = "arbitrary_precision"))]
+fn bad_integer_range() {
     bad!(
         "a = 9223372036854775808",
         "invalid number at line 1 column 5"
@@ -454,7 +459,7 @@ fn inline_tables() {
     );
     bad!(
         "a = {a=1,a=1}",
-        "duplicate key: `a` for key `a` at line 1 column 5"
+        "duplicate key: `a` for key `a` at line 1 column 10"
     );
     bad!(
         "a = {\n}",
@@ -0,0 +1,228 @@
+extern crate toml;
+
+use std::fs;
+use std::path::Path;
+
+use toml::de::{Event, Parser, Scalar};
+use toml::value::{Table, Value};
+
+fn events(input: &str) -> Vec<Event<'_>> {
+    Parser::new(input).collect::<Result<_, _>>().unwrap()
+}
+
+/// Builds a `Value` from the events of a valid document.
+fn build(input: &str) -> Value {
+    fn table<'t>(mut table: &'t mut Table, path: &[String]) -> &'t mut Table {
+        for key in path {
+            let value = table
+                .entry(key.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            table = match value {
+                Value::Table(t) => t,
+                Value::Array(a) => match a.last_mut() {
+                    Some(Value::Table(t)) => t,
+                    _ => panic!("not an array of tables"),
+                },
+                _ => panic!("not a table"),
+            };
+        }
+        table
+    }
+
+    fn value<'a>(parser: &mut Parser<'a>, first: Event<'a>) -> Value {
+        match first {
+            Event::Scalar(Scalar::String(s)) => Value::String(s.into_owned()),
+            Event::Scalar(Scalar::Integer(i)) => Value::Integer(i),
+            Event::Scalar(Scalar::Float(f)) => Value::Float(f),
+            Event::Scalar(Scalar::Boolean(b)) => Value::Boolean(b),
+            Event::Scalar(Scalar::Datetime(d)) => Value::Datetime(d),
+            Event::BeginArray => {
+                let mut array = Vec::new();
+                loop {
+                    match parser.next().unwrap().unwrap() {
+                        Event::End => return Value::Array(array),
+                        Event::Comment(_) => {}
+                        event => array.push(value(parser, event)),
+                    }
+                }
+            }
+            Event::BeginInlineTable => {
+                let mut t = Table::new();
+                loop {
+                    match parser.next().unwrap().unwrap() {
+                        Event::End => return Value::Table(t),
+                        Event::KeyValue { dotted_key, .. } => key_value(parser, &mut t, dotted_key),
+                        event => panic!("unexpected {:?}", event),
+                    }
+                }
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+
+    fn key_value<'a>(parser: &mut Parser<'a>, t: &mut Table, key: Vec<std::borrow::Cow<'a, str>>) {
+        let mut key = key.into_iter().map(|k| k.into_owned()).collect::<Vec<_>>();
+        let last = key.pop().unwrap();
+        let first = parser.next().unwrap().unwrap();
+        let v = value(parser, first);
+        table(t, &key).insert(last, v);
+    }
+
+    let mut root = Table::new();
+    let mut current = Vec::new();
+    let mut parser = Parser::new(input);
+    while let Some(event) = parser.next() {
+        match event.unwrap() {
+            Event::TableHeader { path, array, .. } => {
+                current = path.into_iter().map(|k| k.into_owned()).collect();
+                if array {
+                    let (last, parent) = current.split_last().unwrap();
+                    let array = table(&mut root, parent)
+                        .entry(last.clone())
+                        .or_insert_with(|| Value::Array(Vec::new()));
+                    array
+                        .as_array_mut()
+                        .unwrap()
+                        .push(Value::Table(Table::new()));
+                } else {
+                    table(&mut root, &current);
+                }
+            }
+            Event::KeyValue { dotted_key, .. } => {
+                let t = table(&mut root, &current);
+                key_value(&mut parser, t, dotted_key);
+            }
+            Event::Comment(_) => {}
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+    Value::Table(root)
+}
+
+fn suite(dir: &str) -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(dir);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        if let Ok(input) = fs::read_to_string(&path) {
+            let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+            files.push((name, input));
+        }
+    }
+    files
+}
+
+#[test]
+fn structure() {
+    let input = r#"
+# top
+a.b = "c" # trailing
+[[t . u]]
+v = { w = [1, 2.5], x = {} }
+y = [
+    true, # inside
+    1979-05-27,
+]
+"#;
+    let date = "1979-05-27".parse().unwrap();
+    assert_eq!(
+        events(input),
+        [
+            Event::Comment("# top"),
+            Event::KeyValue {
+                dotted_key: vec!["a".into(), "b".into()],
+                span: 7..10,
+            },
+            Event::Scalar(Scalar::String("c".into())),
+            Event::Comment("# trailing"),
+            Event::TableHeader {
+                path: vec!["t".into(), "u".into()],
+                array: true,
+                span: 28..37,
+            },
+            Event::KeyValue {
+                dotted_key: vec!["v".into()],
+                span: 38..39,
+            },
+            Event::BeginInlineTable,
+            Event::KeyValue {
+                dotted_key: vec!["w".into()],
+                span: 44..45,
+            },
+            Event::BeginArray,
+            Event::Scalar(Scalar::Integer(1)),
+            Event::Scalar(Scalar::Float(2.5)),
+            Event::End,
+            Event::KeyValue {
+                dotted_key: vec!["x".into()],
+                span: 58..59,
+            },
+            Event::BeginInlineTable,
+            Event::End,
+            Event::End,
+            Event::KeyValue {
+                dotted_key: vec!["y".into()],
+                span: 67..68,
+            },
+            Event::BeginArray,
+            Event::Scalar(Scalar::Boolean(true)),
+            Event::Comment("# inside"),
+            Event::Scalar(Scalar::Datetime(date)),
+            Event::End,
+        ]
+    );
+}
+
+#[test]
+fn stops_at_first_error() {
+    let mut parser = Parser::new("a = 1\nb = [1 2]\nc = 3\n");
+    assert_eq!(
+        parser.next().unwrap().unwrap(),
+        Event::KeyValue {
+            dotted_key: vec!["a".into()],
+            span: 0..1,
+        }
+    );
+    let err = parser
+        .by_ref()
+        .find_map(|event| event.err())
+        .expect("an error");
+    assert_eq!(err.line_col(), Some((1, 7)));
+    assert_eq!(
+        err.to_string(),
+        "expected a right bracket, found an identifier at line 2 column 8"
+    );
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn matches_value() {
+    for (name, input) in suite("valid") {
+        let expected = input
+            .parse::<Value>()
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert_eq!(build(&input), expected, "{}", name);
+    }
+}
+
+#[test]
+fn syntax_errors() {
+    // These are only invalid because of what they define, which a streaming
+    // parser doesn't keep track of.
+    let semantic = [
+        "duplicate-key-table",
+        "duplicate-keys",
+        "duplicate-table",
+        "duplicate-tables",
+        "table-array-implicit",
+    ];
+    for (name, input) in suite("invalid") {
+        let failed = Parser::new(&input).any(|event| event.is_err());
+        assert_eq!(failed, !semantic.contains(&&*name), "{}", name);
+    }
+}
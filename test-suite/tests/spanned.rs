@@ -5,6 +5,9 @@ extern crate serde_derive;
 
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+use toml::spanned::SpannedValue;
 use toml::value::Datetime;
 use toml::Spanned;
 
@@ -247,3 +250,119 @@ fn test_spanned_array() {
     ",
     );
 }
+
+#[test]
+fn test_spanned_value() {
+    let input = r#"title = "x"
+dates = [1979-05-27, 1980-01-01]
+
+[a.b]
+c = { d = 1, e.f = true }
+g.h = 0.5
+
+[a]
+i = [[1], []]
+
+[[p]]
+q = 1
+
+[[p]]
+r = 'two'
+"#;
+    let value = input.parse::<SpannedValue>().unwrap();
+    let span = |v: &SpannedValue| &input[v.start()..v.end()];
+
+    assert_eq!(value.span(), (0, input.len()));
+    assert_eq!(span(value.get("title").unwrap()), "\"x\"");
+    let dates = value.get("dates").unwrap().as_array().unwrap();
+    assert_eq!(span(&dates[1]), "1980-01-01");
+    assert_eq!(dates[1].as_datetime().unwrap().to_string(), "1980-01-01");
+
+    let a = value.get("a").unwrap();
+    assert_eq!(span(a), "[a]");
+    let b = a.get("b").unwrap();
+    assert_eq!(span(b), "[a.b]");
+    let c = b.get("c").unwrap();
+    assert_eq!(span(c), "{ d = 1, e.f = true }");
+    assert_eq!(span(c.get("e").unwrap()), "e");
+    assert_eq!(span(c.get("e").unwrap().get("f").unwrap()), "true");
+    assert_eq!(span(b.get("g").unwrap()), "g");
+    assert_eq!(span(b.get("g").unwrap().get("h").unwrap()), "0.5");
+    let i = a.get("i").unwrap();
+    assert_eq!(span(i), "[[1], []]");
+    assert_eq!(span(&i.as_array().unwrap()[0]), "[1]");
+
+    let p = value.get("p").unwrap().as_array().unwrap();
+    assert_eq!(p.len(), 2);
+    assert_eq!(span(&p[0]), "[[p]]");
+    assert_eq!(span(p[1].get("r").unwrap()), "'two'");
+    assert_eq!(p[1].start(), input.rfind("[[p]]").unwrap());
+
+    // Keys are spanned, and kept in document order.
+    let keys = value
+        .as_table()
+        .unwrap()
+        .iter()
+        .map(|(k, _)| (k.get_ref().as_str(), &input[k.start()..k.end()]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            ("title", "title"),
+            ("dates", "dates"),
+            ("a", "a"),
+            ("p", "p")
+        ]
+    );
+    let (key, _) = b.as_table().unwrap().get_key_value("g").unwrap();
+    assert_eq!(key.start(), input.find("g.h").unwrap());
+
+    assert_eq!(
+        toml::Value::from(value),
+        input.parse::<toml::Value>().unwrap()
+    );
+}
+
+#[test]
+fn test_spanned_value_errors() {
+    assert!("a = 1\na = 2".parse::<SpannedValue>().is_err());
+    assert!("[a]\n[a]".parse::<SpannedValue>().is_err());
+    assert!("a = ".parse::<SpannedValue>().is_err());
+}
+
+#[test]
+fn test_spanned_value_invalid_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/invalid");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let input = fs::read_to_string(&path).unwrap();
+        if let Err(expected) = input.parse::<toml::Value>() {
+            let err = input.parse::<SpannedValue>().unwrap_err();
+            assert_eq!(err.to_string(), expected.to_string(), "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn test_spanned_value_valid_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/valid");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let input = fs::read_to_string(&path).unwrap();
+        let value = input
+            .parse::<SpannedValue>()
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert_eq!(
+            toml::Value::from(value),
+            input.parse::<toml::Value>().unwrap(),
+            "{}",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+extern crate serde;
+extern crate toml;
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use toml::de::{from_str_with_options, ErrorKind, Event, Options, Parser, TomlVersion};
+use toml::Value;
+
+fn parse(version: TomlVersion, input: &str) -> Result<Value, toml::de::Error> {
+    from_str_with_options(input, &Options::new().spec(version))
+}
+
+#[test]
+fn inline_tables() {
+    let input = "a = { b = 1, c = [\n  2,\n], }\nd = {\n  e = 1, # comment\n\n  f = 2\n}\n";
+    let value = parse(TomlVersion::V1_1, input).unwrap();
+    assert_eq!(value["a"]["c"][0].as_integer(), Some(2));
+    assert_eq!(value["d"]["e"].as_integer(), Some(1));
+    assert_eq!(value["d"]["f"].as_integer(), Some(2));
+
+    let err = parse(TomlVersion::V1_0, "a = { b = 1, }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a table key, found a right brace at line 1 column 14"
+    );
+    let err = parse(TomlVersion::V1_0, "a = {\nb = 1 }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a table key, found a newline at line 1 column 6"
+    );
+
+    // A comma is still required between entries, and only one may trail.
+    assert!(parse(TomlVersion::V1_1, "a = { b = 1\n c = 2 }").is_err());
+    assert!(parse(TomlVersion::V1_1, "a = { , }").is_err());
+    assert!(parse(TomlVersion::V1_1, "a = { b = 1,, }").is_err());
+}
+
+#[test]
+fn escapes() {
+    let value = parse(TomlVersion::V1_1, r#"a = "\e[0m \x41\xe9""#).unwrap();
+    assert_eq!(value["a"].as_str(), Some("\u{1b}[0m A\u{e9}"));
+
+    let err = parse(TomlVersion::V1_0, r#"a = "\e""#).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::InvalidEscape('e'));
+    let err = parse(TomlVersion::V1_0, r#"a = "\x41""#).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::InvalidEscape('x'));
+    let err = parse(TomlVersion::V1_1, r#"a = "\x4""#).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::InvalidHexEscape('"'));
+
+    // Escapes are never interpreted in literal strings.
+    let value = parse(TomlVersion::V1_1, r#"a = '\e'"#).unwrap();
+    assert_eq!(value["a"].as_str(), Some("\\e"));
+}
+
+#[test]
+fn times_without_seconds() {
+    let input = "a = 07:32\nb = 1979-05-27T07:32Z\nc = 1979-05-27 07:32-07:00\nd = [07:32]\n";
+    let value = parse(TomlVersion::V1_1, input).unwrap();
+    assert_eq!(value["a"].as_datetime().unwrap().to_string(), "07:32:00");
+    assert_eq!(
+        value["b"].as_datetime().unwrap().to_string(),
+        "1979-05-27T07:32:00Z"
+    );
+    assert_eq!(
+        value["c"].as_datetime().unwrap().to_string(),
+        "1979-05-27T07:32:00-07:00"
+    );
+    assert_eq!(value["d"][0].as_datetime().unwrap().to_string(), "07:32:00");
+
+    for input in input.lines() {
+        assert!(parse(TomlVersion::V1_0, input).is_err(), "{}", input);
+    }
+    assert!(parse(TomlVersion::V1_1, "a = 07:32.5").is_err());
+    assert!("07:32".parse::<toml::value::Datetime>().is_err());
+    assert!(toml::value::Datetime::from_str_lenient("1979-05-27T07:32Z").is_err());
+}
+
+#[test]
+fn mixed_arrays() {
+    let input = "a = [1, 'b']";
+    let err = parse(TomlVersion::V0_5, input).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::MixedArrayType);
+    assert_eq!(err.span(), Some(8..11));
+    assert!(parse(TomlVersion::V1_0, input).is_ok());
+
+    // Arrays of arrays are all of the same type, whatever they contain.
+    assert!(parse(TomlVersion::V0_5, "a = [[1], ['b'], []]").is_ok());
+    assert!(parse(TomlVersion::V0_5, "a = ['b', \"c\", '''d''']").is_ok());
+}
+
+#[test]
+fn valid_suite_is_compatible() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/valid");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let input = fs::read_to_string(&path).unwrap();
+        let expected = toml::from_str::<Value>(&input).unwrap();
+        assert_eq!(
+            parse(TomlVersion::V1_1, &input).unwrap(),
+            expected,
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn pull_parser() {
+    let input = "a = {\n  b = 1, # c\n}\n";
+    let options = Options::new().spec(TomlVersion::V1_1);
+    let events = Parser::with_options(input, &options)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(events[1], Event::BeginInlineTable);
+    assert_eq!(events[4], Event::Comment("# c"));
+    assert_eq!(events[5], Event::End);
+    assert!(Parser::new(input).any(|event| event.is_err()));
+}
+
+#[derive(Serialize)]
+struct Text {
+    text: String,
+}
+
+#[test]
+fn serializer() {
+    let value = Text {
+        text: "\u{1b}[1m\u{1}".to_string(),
+    };
+    assert_eq!(
+        toml::to_string(&value).unwrap(),
+        "text = \"\\u001B[1m\\u0001\"\n"
+    );
+
+    let mut out = String::new();
+    let mut ser = toml::Serializer::new(&mut out);
+    ser.spec(TomlVersion::V1_1);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(out, "text = \"\\e[1m\\x01\"\n");
+    assert_eq!(
+        parse(TomlVersion::V1_1, &out).unwrap()["text"].as_str(),
+        Some(&*value.text)
+    );
+    assert!(parse(TomlVersion::V1_0, &out).is_err());
+}
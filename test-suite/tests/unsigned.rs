@@ -0,0 +1,134 @@
+extern crate serde;
+extern crate toml;
+
+use serde::{Deserialize, Serialize};
+use toml::de::{from_str_with_options, ErrorKind, Options};
+use toml::Value;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Ids {
+    id: u64,
+    hash: u128,
+}
+
+fn options() -> Options {
+    Options::new().unsigned_integers(true)
+}
+
+fn to_string<T: Serialize>(value: &T) -> Result<String, toml::ser::Error> {
+    let mut out = String::new();
+    let mut ser = toml::Serializer::new(&mut out);
+    ser.unsigned_integers(true);
+    value.serialize(&mut ser)?;
+    Ok(out)
+}
+
+#[test]
+fn round_trip() {
+    let ids = Ids {
+        id: u64::MAX,
+        hash: u64::MAX as u128 - 1,
+    };
+    let s = to_string(&ids).unwrap();
+    assert_eq!(
+        s,
+        "id = 18446744073709551615\nhash = 18446744073709551614\n"
+    );
+    assert_eq!(from_str_with_options::<Ids>(&s, &options()).unwrap(), ids);
+
+    let value = from_str_with_options::<Value>(&s, &options()).unwrap();
+    assert_eq!(value["id"], Value::UInteger(u64::MAX));
+    assert_eq!(value["id"].type_str(), "integer");
+    let again = from_str_with_options::<Value>(&to_string(&value).unwrap(), &options()).unwrap();
+    assert_eq!(again, value);
+    assert_eq!(
+        toml::to_string(&value).unwrap_err(),
+        toml::ser::Error::NumberInvalid
+    );
+    assert_eq!(value.try_into::<Ids>().unwrap(), ids);
+}
+
+#[test]
+fn display() {
+    let value = from_str_with_options::<Value>("a = 18446744073709551615", &options()).unwrap();
+    assert_eq!(value.to_string(), "a = 18446744073709551615\n");
+    assert_eq!(value["a"].to_string(), "18446744073709551615");
+}
+
+#[test]
+fn radixes() {
+    let input =
+        "a = 0xffff_ffff_ffff_ffff\nb = 0o1777777777777777777777\nc = +9223372036854775808\n";
+    let value = from_str_with_options::<Value>(input, &options()).unwrap();
+    assert_eq!(value["a"].as_uinteger(), Some(u64::MAX));
+    assert_eq!(value["b"].as_uinteger(), Some(u64::MAX));
+    assert_eq!(value["c"].as_uinteger(), Some(1 << 63));
+    assert_eq!(value["c"].as_integer(), None);
+
+    // Integers which fit in an i64 are unaffected.
+    let value = from_str_with_options::<Value>("a = 1\nb = -1", &options()).unwrap();
+    assert_eq!(value["a"], Value::Integer(1));
+    assert_eq!(value["a"].as_uinteger(), Some(1));
+    assert_eq!(value["b"].as_uinteger(), None);
+}
+
+#[test]
+#[cfg(not(feature = "arbitrary_precision"))]
+fn out_of_range() {
+    for input in &[
+        "a = 18446744073709551616",
+        "a = 0x1_0000_0000_0000_0000",
+        "a = -9223372036854775809",
+    ] {
+        let err = from_str_with_options::<Value>(input, &options()).unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::NumberInvalid, "{}", input);
+    }
+}
+
+#[test]
+fn disabled_by_default() {
+    if !cfg!(feature = "arbitrary_precision") {
+        let err = toml::from_str::<Value>("a = 9223372036854775808").unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::NumberInvalid);
+    }
+
+    // Nothing is written which the default parser would reject.
+    let ids = Ids {
+        id: u64::MAX,
+        hash: 1,
+    };
+    assert_eq!(
+        toml::to_string(&ids).unwrap_err(),
+        toml::ser::Error::NumberInvalid
+    );
+    let ids = Ids {
+        id: 1,
+        hash: u64::MAX as u128 + 1,
+    };
+    assert_eq!(
+        to_string(&ids).unwrap_err(),
+        toml::ser::Error::NumberInvalid
+    );
+    let value = Value::try_from(Ids { id: 1, hash: 2 }).unwrap();
+    assert_eq!(toml::to_string(&value).unwrap(), "hash = 2\nid = 1\n");
+}
+
+#[test]
+fn value_conversions() {
+    // Only values parsed with the option hold an `UInteger`.
+    assert!(Value::try_from(Ids {
+        id: u64::MAX,
+        hash: 7,
+    })
+    .is_err());
+    assert!(serde_json::from_str::<Value>("18446744073709551615").is_err());
+    let value = Value::UInteger(u64::MAX);
+    assert_eq!(Value::try_from(&value).unwrap(), value);
+    assert_eq!(value.clone().try_into::<Value>().unwrap(), value);
+    assert!(Value::Integer(1).same_type(&Value::UInteger(u64::MAX)));
+    assert_eq!(
+        u64::deserialize(Value::UInteger(u64::MAX)).unwrap(),
+        u64::MAX
+    );
+    assert!(i64::deserialize(Value::UInteger(u64::MAX)).is_err());
+}
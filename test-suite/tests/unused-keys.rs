@@ -0,0 +1,124 @@
+extern crate serde;
+extern crate toml;
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Config {
+    name: String,
+    server: Option<Server>,
+    #[serde(default)]
+    bin: Vec<Bin>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Server {
+    port: u16,
+    #[serde(default)]
+    hosts: Vec<Host>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Host {
+    addr: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Bin {
+    path: String,
+}
+
+fn unused(input: &str) -> Vec<(String, &str)> {
+    let (_, unused) = toml::de::from_str_with_unused::<Config>(input).unwrap();
+    unused
+        .into_iter()
+        .map(|(path, span)| (path.join("."), &input[span]))
+        .collect()
+}
+
+#[test]
+fn nothing_unused() {
+    assert_eq!(unused("name = 'a'\n[server]\nport = 1\n"), []);
+}
+
+#[test]
+fn reports_unused_keys() {
+    let input = r#"
+name = "a"
+nmae = "b"
+
+[server]
+port = 1
+timeout = 5
+
+[server.tls]
+cert = "x"
+
+[[server.hosts]]
+addr = "a"
+weight = 1
+
+[[bin]]
+path = "x"
+"test" = true
+
+[[bin]]
+path = "y"
+[bin.extra]
+a.b = 1
+"#;
+    assert_eq!(
+        unused(input),
+        [
+            ("nmae".to_string(), "nmae"),
+            ("server.timeout".to_string(), "timeout"),
+            ("server.tls".to_string(), "tls"),
+            ("server.hosts.weight".to_string(), "weight"),
+            ("bin.test".to_string(), "\"test\""),
+            ("bin.extra".to_string(), "extra"),
+        ]
+    );
+}
+
+#[test]
+fn reports_unused_keys_in_inline_tables() {
+    let input =
+        "name = 'a'\nserver = { port = 1, hosts = [{ addr = 'x', prio = 1 }], x = { y = 1 } }\n";
+    assert_eq!(
+        unused(input),
+        [
+            ("server.hosts.prio".to_string(), "prio"),
+            ("server.x".to_string(), "x"),
+        ]
+    );
+}
+
+#[test]
+fn maps_use_every_key() {
+    let input = "a = 1\n[b]\nc = 2\n";
+    let (_, unused) = toml::de::from_str_with_unused::<toml::Value>(input).unwrap();
+    assert!(unused.is_empty());
+    let (_, unused) =
+        toml::de::from_str_with_unused::<BTreeMap<String, BTreeMap<String, i64>>>("[b]\nc = 2\n")
+            .unwrap();
+    assert!(unused.is_empty());
+}
+
+#[test]
+fn callback() {
+    let mut seen: Vec<(Vec<String>, Range<usize>)> = Vec::new();
+    let input = "name = 'a'\nextra = 1\n";
+    let mut d = toml::de::Deserializer::new(input);
+    d.set_unused_key_callback(|path: &[String], span| seen.push((path.to_vec(), span)));
+    let config = Config::deserialize(&mut d).unwrap();
+    drop(d);
+    assert_eq!(config.name, "a");
+    assert_eq!(seen, [(vec!["extra".to_string()], 11..16)]);
+}
@@ -17,6 +17,11 @@ fn to_json(toml: toml::Value) -> Json {
     match toml {
         Toml::String(s) => doit("string", Json::String(s)),
         Toml::Integer(i) => doit("integer", Json::String(i.to_string())),
+        Toml::UInteger(i) => doit("integer", Json::String(i.to_string())),
+        Toml::Number(n) => {
+            let type_ = if n.is_float() { "float" } else { "integer" };
+            doit(type_, Json::String(n.to_string()))
+        }
         Toml::Float(f) => doit(
             "float",
             Json::String({
@@ -50,6 +55,7 @@ fn to_json(toml: toml::Value) -> Json {
             }
             Json::Object(map)
         }
+        _ => unreachable!("not parsed by default"),
     }
 }
 
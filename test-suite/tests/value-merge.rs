@@ -0,0 +1,115 @@
+extern crate toml;
+
+use toml::value::{ArrayMerge, MergeOptions, MergeStrategy, TypeConflict};
+use toml::Value;
+
+fn value(input: &str) -> Value {
+    input.parse().unwrap()
+}
+
+fn paths(report: &toml::value::MergeReport) -> Vec<String> {
+    report.overridden().iter().map(|p| p.join(".")).collect()
+}
+
+#[test]
+fn tables_merge_recursively() {
+    let mut base = value("a = 1\nb = 2\n[t]\nx = 'x'\n[t.u]\ny = true\n");
+    let report = base
+        .merge(
+            value("b = 3\nc = 4\n[t.u]\ny = false\nz = 1\n"),
+            &MergeOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(
+        base,
+        value("a = 1\nb = 3\nc = 4\n[t]\nx = 'x'\n[t.u]\ny = false\nz = 1\n")
+    );
+    assert_eq!(paths(&report), ["b", "t.u.y"]);
+}
+
+#[test]
+fn equal_values_are_not_reported() {
+    let mut base = value("a = 1\nb = [1, 2]");
+    let report = base
+        .merge(value("a = 1\nb = [1, 2]"), &MergeOptions::new())
+        .unwrap();
+    assert!(report.overridden().is_empty());
+}
+
+#[test]
+fn arrays() {
+    let base = value("a = [1, 2]");
+    let other = value("a = [2, 3]");
+
+    let mut merged = base.clone();
+    let report = merged.merge(other.clone(), &MergeOptions::new()).unwrap();
+    assert_eq!(merged, value("a = [2, 3]"));
+    assert_eq!(paths(&report), ["a"]);
+
+    let mut merged = base.clone();
+    let options = MergeOptions::new().arrays(ArrayMerge::Append);
+    let report = merged.merge(other.clone(), &options).unwrap();
+    assert_eq!(merged, value("a = [1, 2, 2, 3]"));
+    assert!(report.overridden().is_empty());
+
+    let mut merged = base;
+    let options = MergeOptions::new().arrays(ArrayMerge::Union);
+    merged.merge(other, &options).unwrap();
+    assert_eq!(merged, value("a = [1, 2, 3]"));
+}
+
+#[test]
+fn type_conflicts() {
+    let mut base = value("a = 1\n[t]\nb = 'b'\nc = { d = 1 }");
+    let err = base
+        .merge(value("a = 2\n[t]\nc = 'c'"), &MergeOptions::new())
+        .unwrap_err();
+    assert_eq!(err.path(), ["t", "c"]);
+    assert_eq!(err.types(), ("table", "string"));
+    assert_eq!(
+        err.to_string(),
+        "cannot merge string into table for key `t.c`"
+    );
+    // Nothing is changed if the merge fails.
+    assert_eq!(base, value("a = 1\n[t]\nb = 'b'\nc = { d = 1 }"));
+
+    let err = base
+        .merge(Value::from(1), &MergeOptions::new())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "cannot merge integer into table");
+
+    let options = MergeOptions::new().type_conflicts(TypeConflict::Override);
+    let report = base.merge(value("a = 2\n[t]\nc = 'c'"), &options).unwrap();
+    assert_eq!(base, value("a = 2\n[t]\nb = 'b'\nc = 'c'"));
+    assert_eq!(paths(&report), ["a", "t.c"]);
+}
+
+#[test]
+fn per_path_strategies() {
+    let mut base = value("a = [1]\nb = [1]\n[t]\nx = 1\ny = 2\n[u]\nv = [1]");
+    let options = MergeOptions::new()
+        .arrays(ArrayMerge::Append)
+        .path(&["a"], MergeStrategy::Replace)
+        .path(&["t"], MergeStrategy::Replace)
+        .path(&["u", "v"], MergeStrategy::Union)
+        .path(&["b"], MergeStrategy::Merge);
+    let report = base
+        .merge(
+            value("a = [2]\nb = [2]\n[t]\nx = 'x'\n[u]\nv = [1, 2]"),
+            &options,
+        )
+        .unwrap();
+    assert_eq!(
+        base,
+        value("a = [2]\nb = [1, 2]\n[t]\nx = 'x'\n[u]\nv = [1, 2]")
+    );
+    assert_eq!(paths(&report), ["a", "t"]);
+
+    // A later strategy for the same path replaces an earlier one.
+    let mut base = value("a = [1]");
+    let options = MergeOptions::new()
+        .path(&["a"], MergeStrategy::Append)
+        .path(&["a"], MergeStrategy::Replace);
+    base.merge(value("a = [2]"), &options).unwrap();
+    assert_eq!(base, value("a = [2]"));
+}
@@ -0,0 +1,124 @@
+extern crate serde;
+extern crate toml;
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use toml::value::ValueRef;
+
+fn is_borrowed(s: Cow<'_, str>) -> bool {
+    match s {
+        Cow::Borrowed(_) => true,
+        Cow::Owned(_) => false,
+    }
+}
+
+fn string<'a>(v: &ValueRef<'a>) -> Cow<'a, str> {
+    match v {
+        ValueRef::String(s) => s.clone(),
+        v => panic!("not a string: {:?}", v),
+    }
+}
+
+#[test]
+fn borrows_strings_and_keys() {
+    let input = r#"
+plain = "abc"
+literal = 'C:\path'
+escaped = "a\tb"
+"quoted key" = 1
+'literal key' = 2
+"escaped\u0020key" = 3
+multi = """
+line"""
+
+[table.nested]
+list = ["x", 'y', "\n"]
+"#;
+    let value = toml::from_str::<ValueRef<'_>>(input).unwrap();
+    let table = value.as_table().unwrap();
+    for key in table.keys() {
+        assert_eq!(is_borrowed(key.clone()), key != "escaped key", "{}", key);
+    }
+
+    assert!(is_borrowed(string(&value["plain"])));
+    assert!(is_borrowed(string(&value["literal"])));
+    assert_eq!(value["literal"].as_str(), Some("C:\\path"));
+    assert!(!is_borrowed(string(&value["escaped"])));
+    assert_eq!(value["escaped"].as_str(), Some("a\tb"));
+    assert_eq!(value["multi"].as_str(), Some("line"));
+
+    let list = &value["table"]["nested"]["list"];
+    assert!(is_borrowed(string(&list[0])));
+    assert!(is_borrowed(string(&list[1])));
+    assert!(!is_borrowed(string(&list[2])));
+    assert_eq!(list.as_array().unwrap().len(), 3);
+    assert!(value.get("missing").is_none());
+    assert!(list.get(3).is_none());
+    assert!(value["plain"].get(0).is_none());
+}
+
+#[test]
+fn accessors() {
+    let input = "i = 1\nf = 1.5\nb = true\nd = 1979-05-27T07:32:00Z\na = []\nt = {}\n";
+    let mut value = toml::from_str::<ValueRef<'_>>(input).unwrap();
+    assert_eq!(value["i"].as_integer(), Some(1));
+    assert_eq!(value["f"].as_float(), Some(1.5));
+    assert_eq!(value["b"].as_bool(), Some(true));
+    assert_eq!(
+        value["d"].as_datetime().unwrap().to_string(),
+        "1979-05-27T07:32:00Z"
+    );
+    assert!(value["a"].is_array());
+    assert!(value["t"].is_table());
+    assert_eq!(value["t"].type_str(), "table");
+    assert!(value["i"].same_type(&ValueRef::Integer(2)));
+
+    value["i"] = ValueRef::String("two".into());
+    value["a"]
+        .as_array_mut()
+        .unwrap()
+        .push(ValueRef::Boolean(false));
+    assert_eq!(value.get_mut("i").unwrap().as_str(), Some("two"));
+    assert_eq!(value["a"][0].as_bool(), Some(false));
+}
+
+#[test]
+fn borrowed_fields() {
+    #[derive(Deserialize)]
+    struct Lock<'a> {
+        #[serde(borrow)]
+        package: Vec<ValueRef<'a>>,
+    }
+
+    let input = "[[package]]\nname = 'a'\n\n[[package]]\nname = 'b'\n";
+    let lock = toml::from_str::<Lock<'_>>(input).unwrap();
+    let names = lock
+        .package
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "b"]);
+}
+
+#[test]
+fn into_owned_matches_value() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/valid");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let input = fs::read_to_string(&path).unwrap();
+        let value = toml::from_str::<ValueRef<'_>>(&input)
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert_eq!(
+            value.into_owned(),
+            input.parse::<toml::Value>().unwrap(),
+            "{}",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "arbitrary_precision")]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+use toml::{Number, Value};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Order {
+    price: Number,
+    quantity: u32,
+    total: f64,
+}
+
+#[test]
+fn value_keeps_inexact_numbers() {
+    let input = "a = 19.990\nb = 0xdead_beef\nc = +1_000\nd = 123456789012345678901234567890\n\
+                 e = -inf\nf = 0.1000000000000000000001\n";
+    let value: Value = input.parse().unwrap();
+    assert_eq!(value["a"], Value::Float(19.99));
+    assert_eq!(value["b"], Value::Integer(0xdead_beef));
+    assert_eq!(value["c"], Value::Integer(1000));
+    let big = value["d"].as_number().unwrap();
+    assert_eq!(big.as_i64(), None);
+    assert_eq!(big.as_f64(), Some(1.2345678901234568e29));
+    assert_eq!(value["d"].type_str(), "integer");
+    assert_eq!(value["e"].as_float(), Some(f64::NEG_INFINITY));
+    let precise = value["f"].as_number().unwrap();
+    assert_eq!(precise.as_str(), "0.1000000000000000000001");
+    assert_eq!(value["f"].as_float(), Some(0.1));
+
+    // The text of the numbers kept is written back unchanged.
+    assert_eq!(
+        toml::to_string(&value).unwrap(),
+        "a = 19.99\nb = 3735928559\nc = 1000\nd = 123456789012345678901234567890\n\
+         e = -inf\nf = 0.1000000000000000000001\n"
+    );
+}
+
+#[test]
+fn typed_fields() {
+    let order: Order = toml::from_str("price = 19.990\nquantity = 3\ntotal = 59.97").unwrap();
+    assert_eq!(order.price.as_str(), "19.990");
+    assert_eq!(order.quantity, 3);
+    assert_eq!(order.total, 59.97);
+    assert_eq!(
+        toml::to_string(&order).unwrap(),
+        "price = 19.990\nquantity = 3\ntotal = 59.97\n"
+    );
+
+    let order: Order = toml::from_str("price = 1\nquantity = 1\ntotal = 1").unwrap();
+    assert_eq!(order.total, 1.0);
+
+    let big: u128 = toml::from_str::<Value>("a = 123456789012345678901234567890").unwrap()["a"]
+        .clone()
+        .try_into()
+        .unwrap();
+    assert_eq!(big, 123456789012345678901234567890);
+
+    let err = toml::from_str::<Order>("price = 1\nquantity = 5000000000\ntotal = 1").unwrap_err();
+    assert_eq!(err.line_col(), Some((1, 11)));
+}
+
+#[test]
+fn out_of_range_floats() {
+    let order: Order = toml::from_str("price = 1e400\nquantity = 1\ntotal = 1").unwrap();
+    assert_eq!(order.price.as_str(), "1e400");
+    assert_eq!(
+        toml::to_string(&order).unwrap(),
+        "price = 1e400\nquantity = 1\ntotal = 1.0\n"
+    );
+    let value: Value = "a = -1.5e400".parse().unwrap();
+    assert_eq!(value["a"].as_number().unwrap().as_str(), "-1.5e400");
+    assert_eq!(value.to_string(), "a = -1.5e400\n");
+
+    // They don't fit in an `f64` though, unless they're ignored.
+    let err = toml::from_str::<Order>("price = 1\nquantity = 1\ntotal = 1e400").unwrap_err();
+    assert!(err.to_string().contains("too large"), "{}", err);
+    let input = "price = 1\nquantity = 1\ntotal = 1\nextra = [1e400, 1e99999]";
+    assert!(toml::from_str::<Order>(input).is_ok());
+}
+
+#[test]
+fn other_formats() {
+    // Other formats are given the text of a number.
+    let order: Order = toml::from_str("price = 19.990\nquantity = 3\ntotal = 1e1").unwrap();
+    assert_eq!(
+        serde_json::to_string(&order).unwrap(),
+        r#"{"price":"19.990","quantity":3,"total":10.0}"#
+    );
+    let json: Order = serde_json::from_str(&serde_json::to_string(&order).unwrap()).unwrap();
+    assert_eq!(json, order);
+}
+
+#[test]
+fn through_value() {
+    let value: Value = "price = 1e3\nquantity = 2\ntotal = 2e3".parse().unwrap();
+    let order: Order = value.clone().try_into().unwrap();
+    // Only the text of numbers a `Value` can't represent exactly is kept.
+    assert_eq!(order.price.as_str(), "1000.0");
+    assert_eq!(order.total, 2000.0);
+    let value = Value::try_from(&order).unwrap();
+    assert_eq!(value["price"].as_number(), Some(&order.price));
+    assert_eq!(value["quantity"], Value::Integer(2));
+}
+
+#[test]
+fn parse_number() {
+    for s in &[
+        "0",
+        "-17",
+        "0o755",
+        "0b1010",
+        "6.626e-34",
+        "nan",
+        "1_000.000_1",
+    ] {
+        assert_eq!(s.parse::<Number>().unwrap().to_string(), *s);
+    }
+    for s in &[
+        "",
+        "1.",
+        "01",
+        "0x",
+        "-0xff",
+        "1__0",
+        "true",
+        "1979-05-27",
+        " 1",
+        "1 # one",
+    ] {
+        assert!(s.parse::<Number>().is_err(), "{:?}", s);
+    }
+    assert!(Number::from(u64::MAX).is_integer());
+}
+
+#[test]
+fn spanned_values() {
+    let input = "a = 1.5\nb = 123456789012345678901234567890\nc = 1e400\n";
+    let value: toml::spanned::SpannedValue = input.parse().unwrap();
+    assert_eq!(value.get("a").unwrap().as_float(), Some(1.5));
+    let b = value.get("b").unwrap();
+    assert_eq!(
+        b.as_number().unwrap().as_str(),
+        "123456789012345678901234567890"
+    );
+    assert_eq!(b.span(), (12, 42));
+    assert_eq!(value.get("c").unwrap().type_str(), "float");
+    assert_eq!(Value::from(value), input.parse::<Value>().unwrap());
+}
+
+#[test]
+fn disabled_elsewhere() {
+    // Documents still parse numbers.
+    let doc: toml::document::Document = "a = 1.5".parse().unwrap();
+    assert_eq!(doc.to_string(), "a = 1.5");
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Flattened {
+    name: String,
+    #[serde(flatten)]
+    rest: std::collections::BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum Setting {
+    Integer(i64),
+    Float(f64),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Settings {
+    a: Vec<Setting>,
+}
+
+#[test]
+fn buffered_fields() {
+    // Flattened and untagged fields are given the numbers themselves.
+    let flattened: Flattened = toml::from_str("name = 'a'\nx = 1.5\ny = 2").unwrap();
+    assert_eq!(flattened.rest["x"], 1.5);
+    assert_eq!(flattened.rest["y"], 2.0);
+    let settings: Settings = toml::from_str("a = [1, 2.5]").unwrap();
+    assert_eq!(settings.a, [Setting::Integer(1), Setting::Float(2.5)]);
+}